@@ -0,0 +1,111 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use diffy::{create_patch, create_patch_concurrent, DiffOptions};
+
+// A realistic input: a revision of one of this crate's own source files
+const SOURCE: &str = include_str!("../src/diff/myers.rs");
+
+fn revise(source: &str) -> String {
+    source
+        .lines()
+        .enumerate()
+        .filter(|(i, _)| i % 17 != 0)
+        .map(|(i, line)| {
+            if i % 11 == 0 {
+                format!("{} // revised\n", line)
+            } else {
+                format!("{}\n", line)
+            }
+        })
+        .collect()
+}
+
+fn numbered_lines(prefix: &str, n: usize) -> String {
+    (0..n).map(|i| format!("{} {}\n", prefix, i)).collect()
+}
+
+fn identical(c: &mut Criterion) {
+    c.bench_function("identical", |b| {
+        b.iter(|| create_patch(black_box(SOURCE), black_box(SOURCE)))
+    });
+}
+
+fn completely_different(c: &mut Criterion) {
+    let mut group = c.benchmark_group("completely_different");
+    for &n in &[100, 1000, 5000] {
+        let original = numbered_lines("old", n);
+        let modified = numbered_lines("new", n);
+
+        group.bench_with_input(BenchmarkId::new("unlimited", n), &n, |b, _| {
+            b.iter(|| create_patch(black_box(&original), black_box(&modified)))
+        });
+
+        let mut opts = DiffOptions::new();
+        opts.set_max_edit_distance(Some(100));
+        group.bench_with_input(BenchmarkId::new("max_edit_distance_100", n), &n, |b, _| {
+            b.iter(|| opts.create_patch(black_box(&original), black_box(&modified)))
+        });
+    }
+    group.finish();
+}
+
+fn source_revision(c: &mut Criterion) {
+    let modified = revise(SOURCE);
+    c.bench_function("source_revision", |b| {
+        b.iter(|| create_patch(black_box(SOURCE), black_box(&modified)))
+    });
+}
+
+fn unique_lines(c: &mut Criterion) {
+    // Every line is unique, allowing the O(N log N) fast path to be used
+    let mut group = c.benchmark_group("unique_lines");
+    for &n in &[1000, 10000] {
+        let original = numbered_lines("line", n);
+        let modified: String = original
+            .lines()
+            .enumerate()
+            .filter(|(i, _)| i % 7 != 0)
+            .map(|(i, line)| format!("{}{}\n", line, if i % 5 == 0 { "!" } else { "" }))
+            .collect();
+
+        group.bench_with_input(BenchmarkId::from_parameter(n), &n, |b, _| {
+            b.iter(|| create_patch(black_box(&original), black_box(&modified)))
+        });
+    }
+    group.finish();
+}
+
+fn concurrent(c: &mut Criterion) {
+    let modified = revise(SOURCE);
+    let cores = std::thread::available_parallelism().map_or(1, |n| n.get());
+
+    let mut group = c.benchmark_group("concurrent");
+    let mut n = 1;
+    while n <= cores {
+        let pairs = vec![(SOURCE, modified.as_str()); n];
+
+        group.bench_with_input(BenchmarkId::new("sequential", n), &n, |b, _| {
+            b.iter(|| {
+                pairs
+                    .iter()
+                    .map(|(original, modified)| create_patch(original, modified))
+                    .collect::<Vec<_>>()
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("concurrent", n), &n, |b, _| {
+            b.iter(|| create_patch_concurrent(black_box(&pairs)))
+        });
+
+        n *= 2;
+    }
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    identical,
+    completely_different,
+    source_revision,
+    unique_lines,
+    concurrent
+);
+criterion_main!(benches);
@@ -1,37 +1,135 @@
 use crate::{
     patch::{Hunk, Line, Patch},
-    utils::LineIter,
+    utils::{LineIter, Text},
 };
-use std::{fmt, iter};
+#[cfg(feature = "ropey")]
+use std::ops;
+use std::{cmp, collections::HashMap, fmt, iter};
 
 /// An error returned when [`apply`]ing a `Patch` fails
 ///
 /// [`apply`]: fn.apply.html
-#[derive(Debug)]
-pub struct ApplyError(usize);
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ApplyError {
+    /// A hunk's context and deleted lines could not be found in the base image
+    HunkFailed {
+        /// The zero-based index of the hunk in the patch
+        hunk_index: usize,
+        /// The lines from the hunk that were expected to be in the base image
+        expected_context: String,
+        /// The lines found in the base image at the position the hunk was expected to apply
+        actual_context: String,
+    },
+}
 
 impl fmt::Display for ApplyError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "error applying hunk #{}", self.0)
+        match self {
+            ApplyError::HunkFailed {
+                hunk_index,
+                expected_context,
+                actual_context,
+            } => write!(
+                f,
+                "error applying hunk #{}: expected {:?}, found {:?}",
+                hunk_index + 1,
+                expected_context,
+                actual_context
+            ),
+        }
     }
 }
 
 impl std::error::Error for ApplyError {}
 
-#[derive(Copy, Clone, Debug)]
-enum ImageLine<'a> {
-    Unpatched(&'a str),
-    Patched(&'a str),
+/// An error returned when [`Patch::verify_against`] finds that a `Patch` doesn't transform the
+/// original text into the modified text
+///
+/// [`Patch::verify_against`]: struct.Patch.html#method.verify_against
+#[derive(Debug)]
+pub enum VerifyError {
+    /// The patch could not be applied to the original text
+    Apply(ApplyError),
+    /// Applying the patch succeeded but the result differs from the expected modified text
+    Mismatch {
+        /// The line number (starting at 1) of the first line that differs
+        line: usize,
+        /// The line in the expected modified text, or `None` if it ended early
+        expected: Option<String>,
+        /// The line produced by applying the patch, or `None` if it ended early
+        actual: Option<String>,
+    },
+}
+
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VerifyError::Apply(e) => write!(f, "patch failed to apply: {}", e),
+            VerifyError::Mismatch {
+                line,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "patch produced a different result at line {}: expected {:?}, got {:?}",
+                line, expected, actual
+            ),
+        }
+    }
 }
 
-impl<'a> ImageLine<'a> {
-    fn inner(&self) -> &'a str {
+impl std::error::Error for VerifyError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            VerifyError::Apply(e) => Some(e),
+            VerifyError::Mismatch { .. } => None,
+        }
+    }
+}
+
+pub(crate) fn verify(patch: &Patch<'_>, original: &str, modified: &str) -> Result<(), VerifyError> {
+    let actual = apply(original, patch).map_err(VerifyError::Apply)?;
+
+    let mut expected_lines = LineIter::new(modified);
+    let mut actual_lines = LineIter::new(actual.as_str());
+    let mut line = 1;
+    loop {
+        match (expected_lines.next(), actual_lines.next()) {
+            (None, None) => return Ok(()),
+            (expected, actual) if expected != actual => {
+                return Err(VerifyError::Mismatch {
+                    line,
+                    expected: expected.map(ToOwned::to_owned),
+                    actual: actual.map(ToOwned::to_owned),
+                });
+            }
+            _ => line += 1,
+        }
+    }
+}
+
+#[derive(Debug)]
+enum ImageLine<'a, T: ?Sized> {
+    Unpatched(&'a T),
+    Patched(&'a T),
+}
+
+impl<T: ?Sized> Copy for ImageLine<'_, T> {}
+
+impl<T: ?Sized> Clone for ImageLine<'_, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, T: ?Sized> ImageLine<'a, T> {
+    fn inner(&self) -> &'a T {
         match self {
             ImageLine::Unpatched(inner) | ImageLine::Patched(inner) => inner,
         }
     }
 
-    fn into_inner(self) -> &'a str {
+    fn into_inner(self) -> &'a T {
         self.inner()
     }
 
@@ -81,25 +179,756 @@ impl<'a> ImageLine<'a> {
 /// assert_eq!(apply(base_image, &patch).unwrap(), expected);
 /// ```
 pub fn apply(base_image: &str, patch: &Patch<'_>) -> Result<String, ApplyError> {
+    let image = apply_to_image(base_image, patch, Direction::Forward)?;
+
+    Ok(image.into_iter().map(ImageLine::into_inner).collect())
+}
+
+/// The result of [`Patch::apply_with_rejects`]
+///
+/// [`Patch::apply_with_rejects`]: struct.Patch.html#method.apply_with_rejects
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApplyWithRejectsResult<'a> {
+    /// The base image with all of the hunks which could be applied
+    pub applied: String,
+    /// The zero-based index and content of each hunk which failed to apply
+    pub rejected: Vec<(usize, Hunk<'a>)>,
+}
+
+// Apply the hunks of a `Patch` which can be applied, collecting those which can't
+pub(crate) fn apply_with_rejects<'a>(
+    base_image: &str,
+    patch: &Patch<'a>,
+) -> ApplyWithRejectsResult<'a> {
+    let mut image: Vec<_> = LineIter::new(base_image)
+        .map(ImageLine::Unpatched)
+        .collect();
+    let mut rejected = Vec::new();
+
+    for (i, hunk) in patch.hunks().iter().enumerate() {
+        if apply_hunk(&mut image, hunk, Direction::Forward).is_err() {
+            rejected.push((i, hunk.clone()));
+        }
+    }
+
+    ApplyWithRejectsResult {
+        applied: image.into_iter().map(ImageLine::into_inner).collect(),
+        rejected,
+    }
+}
+
+/// An error returned when [`Patch::apply_in_memory_atomically`] fails
+///
+/// Each variant records the zero-based index of the patch which failed.
+///
+/// [`Patch::apply_in_memory_atomically`]: struct.Patch.html#method.apply_in_memory_atomically
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InMemoryApplyError {
+    /// The patch creates or renames a file to a name which already exists
+    FileExists {
+        /// The zero-based index of the patch
+        patch_index: usize,
+        /// The name of the existing file
+        name: String,
+    },
+    /// The patch changes, renames or deletes a file which doesn't exist
+    FileMissing {
+        /// The zero-based index of the patch
+        patch_index: usize,
+        /// The name of the missing file
+        name: String,
+    },
+    /// A hunk of the patch could not be applied to the file
+    Apply {
+        /// The zero-based index of the patch
+        patch_index: usize,
+        /// The error from applying the patch
+        error: ApplyError,
+    },
+}
+
+impl fmt::Display for InMemoryApplyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InMemoryApplyError::FileExists { patch_index, name } => write!(
+                f,
+                "error applying patch #{}: file {:?} already exists",
+                patch_index + 1,
+                name
+            ),
+            InMemoryApplyError::FileMissing { patch_index, name } => write!(
+                f,
+                "error applying patch #{}: file {:?} doesn't exist",
+                patch_index + 1,
+                name
+            ),
+            InMemoryApplyError::Apply { patch_index, error } => {
+                write!(f, "error applying patch #{}: {}", patch_index + 1, error)
+            }
+        }
+    }
+}
+
+impl std::error::Error for InMemoryApplyError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            InMemoryApplyError::Apply { error, .. } => Some(error),
+            InMemoryApplyError::FileExists { .. } | InMemoryApplyError::FileMissing { .. } => None,
+        }
+    }
+}
+
+// Apply each patch to the file it names, only modifying `files` if every patch applies
+pub(crate) fn apply_in_memory_atomically(
+    files: &mut HashMap<String, String>,
+    patches: &[Patch<'_>],
+) -> Result<(), InMemoryApplyError> {
+    const DEV_NULL: &str = "/dev/null";
+
+    // The content of a file after the patches so far, or `None` if it doesn't exist
+    fn current<'s>(
+        staged: &'s HashMap<&str, Option<String>>,
+        files: &'s HashMap<String, String>,
+        name: &str,
+    ) -> Option<&'s str> {
+        match staged.get(name) {
+            Some(content) => content.as_deref(),
+            None => files.get(name).map(String::as_str),
+        }
+    }
+
+    // The new content of each file which was touched, or `None` if it was deleted
+    let mut staged: HashMap<&str, Option<String>> = HashMap::new();
+
+    for (patch_index, patch) in patches.iter().enumerate() {
+        let source = match patch.original() {
+            DEV_NULL => None,
+            name => Some(name),
+        };
+        let base_image = match source {
+            Some(name) => {
+                current(&staged, files, name).ok_or_else(|| InMemoryApplyError::FileMissing {
+                    patch_index,
+                    name: name.to_owned(),
+                })?
+            }
+            None => "",
+        };
+        let target = match patch.modified() {
+            DEV_NULL => None,
+            name => Some(name),
+        };
+        if let Some(name) = target.filter(|&name| Some(name) != source) {
+            if current(&staged, files, name).is_some() {
+                return Err(InMemoryApplyError::FileExists {
+                    patch_index,
+                    name: name.to_owned(),
+                });
+            }
+        }
+
+        let result = apply(base_image, patch)
+            .map_err(|error| InMemoryApplyError::Apply { patch_index, error })?;
+
+        if let Some(name) = source {
+            staged.insert(name, None);
+        }
+        if let Some(name) = target {
+            staged.insert(name, Some(result));
+        }
+    }
+
+    for (name, content) in staged {
+        match content {
+            Some(content) => files.insert(name.to_owned(), content),
+            None => files.remove(name),
+        };
+    }
+
+    Ok(())
+}
+
+/// A line of the result of applying a `Patch`, see [`Patch::apply_line_by_line_callback`]
+///
+/// Line numbers start at 1 and refer to the base image.
+///
+/// [`Patch::apply_line_by_line_callback`]: struct.Patch.html#method.apply_line_by_line_callback
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputLine<'a> {
+    /// A line of the base image which is kept
+    Unchanged(usize, &'a str),
+    /// A line inserted by the patch
+    Added(&'a str),
+    /// A line of the base image which is removed by the patch
+    Removed(usize, &'a str),
+}
+
+pub(crate) fn apply_line_by_line_callback<'a, F>(
+    base_image: &'a str,
+    patch: &Patch<'a>,
+    mut callback: F,
+) -> Result<(), ApplyError>
+where
+    F: FnMut(OutputLine<'a>),
+{
+    let image: Vec<_> = LineIter::new(base_image)
+        .map(ImageLine::Unpatched)
+        .collect();
+    // The number of lines of the base image which have already been passed to the callback
+    let mut settled = 0;
+    // How many more lines the patched image has than the base image so far
+    let mut offset = 0;
+
+    for (i, hunk) in patch.hunks().iter().enumerate() {
+        // Like `find_position`, but the image is never patched and lines which were already
+        // passed to the callback can't be changed anymore
+        let target = cmp::max(
+            target_position(hunk, Direction::Forward).saturating_add_signed(-offset),
+            settled,
+        );
+        let backward = (settled..target).rev();
+        let forward = target + 1..image.len();
+        let pos = iter::once(target)
+            .chain(interleave(backward, forward))
+            .find(|&pos| match_fragment(&image, hunk.lines(), pos, Direction::Forward))
+            .ok_or_else(|| hunk_failed(&image, i, hunk, Direction::Forward))?;
+
+        for (n, line) in image.iter().enumerate().take(pos).skip(settled) {
+            callback(OutputLine::Unchanged(n + 1, line.inner()));
+        }
+        settled = pos;
+        for line in hunk.lines() {
+            match line {
+                Line::Context(l) => {
+                    settled += 1;
+                    callback(OutputLine::Unchanged(settled, l));
+                }
+                Line::Delete(l) => {
+                    settled += 1;
+                    offset -= 1;
+                    callback(OutputLine::Removed(settled, l));
+                }
+                Line::Insert(l) => {
+                    offset += 1;
+                    callback(OutputLine::Added(l));
+                }
+            }
+        }
+    }
+
+    for (n, line) in image.iter().enumerate().skip(settled) {
+        callback(OutputLine::Unchanged(n + 1, line.inner()));
+    }
+
+    Ok(())
+}
+
+/// A record of how each hunk of a `Patch` was applied, see [`Patch::apply_with_trace`]
+///
+/// Displaying a `TraceLog` prints one line describing each hunk.
+///
+/// [`Patch::apply_with_trace`]: struct.Patch.html#method.apply_with_trace
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TraceLog {
+    /// The hunks which were attempted, in order. Applying stops at the first hunk which fails.
+    pub hunks: Vec<HunkTrace>,
+}
+
+/// How a single hunk was applied, see [`TraceLog`]
+///
+/// Line numbers start at 1 and refer to the text with all of the previous hunks applied.
+///
+/// [`TraceLog`]: struct.TraceLog.html
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HunkTrace {
+    /// The zero-based index of the hunk in the patch
+    pub hunk_index: usize,
+    /// The line at which the hunk was expected to apply
+    pub expected_line: usize,
+    /// The number of context and deleted lines of the hunk, which had to match the text
+    pub matched_lines: usize,
+    /// The line at which the hunk was applied, or `None` if it couldn't be applied anywhere
+    pub applied_line: Option<usize>,
+}
+
+impl HunkTrace {
+    /// Returns how many lines after (or before, if negative) the expected line the hunk was
+    /// applied, or `None` if it wasn't applied
+    pub fn offset(&self) -> Option<isize> {
+        self.applied_line
+            .map(|line| line as isize - self.expected_line as isize)
+    }
+}
+
+impl fmt::Display for TraceLog {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for hunk in &self.hunks {
+            let lines = if hunk.matched_lines == 1 {
+                "line"
+            } else {
+                "lines"
+            };
+            write!(f, "hunk {}: ", hunk.hunk_index + 1)?;
+            match (hunk.applied_line, hunk.offset()) {
+                (Some(line), Some(0)) => write!(f, "applied at line {}", line)?,
+                (Some(line), Some(offset)) => {
+                    write!(f, "applied at line {} with offset {:+}", line, offset)?
+                }
+                _ => {
+                    writeln!(
+                        f,
+                        "failed to match {} {} at or around line {}",
+                        hunk.matched_lines, lines, hunk.expected_line
+                    )?;
+                    continue;
+                }
+            }
+            writeln!(f, ", matching {} {}", hunk.matched_lines, lines)?;
+        }
+        Ok(())
+    }
+}
+
+pub(crate) fn apply_with_trace(
+    base_image: &str,
+    patch: &Patch<'_>,
+) -> (Result<String, ApplyError>, TraceLog) {
     let mut image: Vec<_> = LineIter::new(base_image)
         .map(ImageLine::Unpatched)
         .collect();
+    let mut trace = TraceLog::default();
 
     for (i, hunk) in patch.hunks().iter().enumerate() {
-        apply_hunk(&mut image, hunk).map_err(|_| ApplyError(i + 1))?;
+        let pos = find_position(&image, hunk, Direction::Forward);
+        let len = pre_image_line_count(hunk.lines(), Direction::Forward);
+        trace.hunks.push(HunkTrace {
+            hunk_index: i,
+            expected_line: target_position(hunk, Direction::Forward) + 1,
+            matched_lines: len,
+            applied_line: pos.map(|pos| pos + 1),
+        });
+
+        let pos = match pos {
+            Some(pos) => pos,
+            None => return (Err(hunk_failed(&image, i, hunk, Direction::Forward)), trace),
+        };
+        image.splice(
+            pos..pos + len,
+            post_image(hunk.lines(), Direction::Forward).map(ImageLine::Patched),
+        );
+    }
+
+    (
+        Ok(image.into_iter().map(ImageLine::into_inner).collect()),
+        trace,
+    )
+}
+
+// Like `apply` but allowing up to `fuzz` context lines of each hunk to differ from the base image,
+// as long as a hunk is applied at most `max_offset` lines away from where it's expected
+pub(crate) fn apply_fuzzy_scan(
+    base_image: &str,
+    patch: &Patch<'_>,
+    fuzz: usize,
+    max_offset: usize,
+) -> Result<String, ApplyError> {
+    let mut image: Vec<_> = LineIter::new(base_image)
+        .map(ImageLine::Unpatched)
+        .collect();
+    // How far the previous hunk was from where it was expected, which later hunks are likely to
+    // be off by as well
+    let mut offset = 0;
+
+    for (i, hunk) in patch.hunks().iter().enumerate() {
+        let target = target_position(hunk, Direction::Forward)
+            .saturating_add_signed(offset)
+            .min(image.len());
+        let pos = find_fuzzy_position(&image, hunk, target, fuzz, max_offset)
+            .ok_or_else(|| hunk_failed(&image, i, hunk, Direction::Forward))?;
+        offset = pos as isize - target_position(hunk, Direction::Forward) as isize;
+
+        // Context lines which differ from the base image are kept as they are in the base image
+        let len = pre_image_line_count(hunk.lines(), Direction::Forward);
+        let mut old_lines = image[pos..pos + len].iter().map(ImageLine::inner);
+        let new_lines: Vec<_> = hunk
+            .lines()
+            .iter()
+            .filter_map(|line| match line {
+                Line::Context(_) => old_lines.next(),
+                Line::Delete(_) => {
+                    old_lines.next();
+                    None
+                }
+                Line::Insert(line) => Some(*line),
+            })
+            .map(ImageLine::Patched)
+            .collect();
+        image.splice(pos..pos + len, new_lines);
     }
 
     Ok(image.into_iter().map(ImageLine::into_inner).collect())
 }
 
-fn apply_hunk<'a>(image: &mut Vec<ImageLine<'a>>, hunk: &Hunk<'a>) -> Result<(), ()> {
+// Search up to `max_offset` lines before and after `target` for the position at which the most
+// context lines of `hunk` match, preferring positions closer to `target`
+fn find_fuzzy_position(
+    image: &[ImageLine<'_, str>],
+    hunk: &Hunk<'_>,
+    target: usize,
+    fuzz: usize,
+    max_offset: usize,
+) -> Option<usize> {
+    let backward = (target.saturating_sub(max_offset)..target).rev();
+    let forward = target + 1..=cmp::min(target.saturating_add(max_offset), image.len());
+
+    // (mismatched context lines, position)
+    let mut best: Option<(usize, usize)> = None;
+    for pos in iter::once(target).chain(interleave(backward, forward)) {
+        match context_mismatches(image, hunk.lines(), pos) {
+            Some(0) => return Some(pos),
+            Some(mismatched)
+                if mismatched <= fuzz && best.map_or(true, |(best, _)| mismatched < best) =>
+            {
+                best = Some((mismatched, pos));
+            }
+            _ => {}
+        }
+    }
+
+    best.map(|(_, pos)| pos)
+}
+
+// Returns the number of context lines which don't match when applying a hunk at `pos`, or `None`
+// if it can't be applied there at all because a deleted line doesn't match
+fn context_mismatches(
+    image: &[ImageLine<'_, str>],
+    lines: &[Line<'_>],
+    pos: usize,
+) -> Option<usize> {
+    let len = pre_image_line_count(lines, Direction::Forward);
+    let image = image.get(pos..pos + len)?;
+    if image.iter().any(ImageLine::is_patched) {
+        return None;
+    }
+
+    let mut mismatched = 0;
+    let pre_image = lines.iter().filter(|line| !matches!(line, Line::Insert(_)));
+    for (line, image_line) in pre_image.zip(image) {
+        match line {
+            Line::Context(l) if *l != image_line.inner() => mismatched += 1,
+            Line::Delete(l) if *l != image_line.inner() => return None,
+            _ => {}
+        }
+    }
+
+    Some(mismatched)
+}
+
+/// A single step which undoes part of applying a `Patch`, see [`Patch::apply_with_undo_stack`]
+///
+/// [`Patch::apply_with_undo_stack`]: struct.Patch.html#method.apply_with_undo_stack
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UndoOp {
+    /// The zero-based index of the first line to remove
+    pub position: usize,
+    /// The number of lines to remove
+    pub remove_lines: usize,
+    /// The lines to insert at `position` in place of the removed lines
+    pub insert_lines: Vec<String>,
+}
+
+pub(crate) fn apply_with_undo_stack(
+    base_image: &str,
+    patch: &Patch<'_>,
+) -> Result<(String, Vec<UndoOp>), ApplyError> {
+    let mut image: Vec<_> = LineIter::new(base_image)
+        .map(ImageLine::Unpatched)
+        .collect();
+    let mut undo_stack = Vec::new();
+
+    for (i, hunk) in patch.hunks().iter().enumerate() {
+        let pos = find_position(&image, hunk, Direction::Forward)
+            .ok_or_else(|| hunk_failed(&image, i, hunk, Direction::Forward))?;
+
+        // One operation for each run of deleted and inserted lines. Positions refer to the
+        // patched image, which is valid as long as later operations are undone first.
+        let (mut old_pos, mut new_pos) = (pos, pos);
+        let mut op: Option<UndoOp> = None;
+        for line in hunk.lines() {
+            match line {
+                Line::Context(_) => {
+                    undo_stack.extend(op.take());
+                    old_pos += 1;
+                    new_pos += 1;
+                }
+                Line::Delete(_) => {
+                    let op = op.get_or_insert_with(|| UndoOp {
+                        position: new_pos,
+                        remove_lines: 0,
+                        insert_lines: Vec::new(),
+                    });
+                    op.insert_lines.push(image[old_pos].inner().to_owned());
+                    old_pos += 1;
+                }
+                Line::Insert(_) => {
+                    let op = op.get_or_insert_with(|| UndoOp {
+                        position: new_pos,
+                        remove_lines: 0,
+                        insert_lines: Vec::new(),
+                    });
+                    op.remove_lines += 1;
+                    new_pos += 1;
+                }
+            }
+        }
+        undo_stack.extend(op);
+
+        image.splice(
+            pos..old_pos,
+            post_image(hunk.lines(), Direction::Forward).map(ImageLine::Patched),
+        );
+    }
+
+    Ok((
+        image.into_iter().map(ImageLine::into_inner).collect(),
+        undo_stack,
+    ))
+}
+
+/// Apply a `Patch` to a base image, also returning where each line of the result starts and ends
+///
+/// The `n`th element of the returned `Vec` is the `(start, end)` byte range of line `n + 1` in
+/// the resulting string, including its trailing newline. This allows seeking to a line of the
+/// result without scanning for newlines.
+///
+/// ```
+/// use diffy::{apply_with_byte_offsets, create_patch};
+///
+/// let patch = create_patch("a\nb\nc\n", "a\nbb\nc\n");
+///
+/// let (result, offsets) = apply_with_byte_offsets("a\nb\nc\n", &patch).unwrap();
+/// assert_eq!(result, "a\nbb\nc\n");
+/// assert_eq!(offsets, [(0, 2), (2, 5), (5, 7)]);
+///
+/// let (start, end) = offsets[1];
+/// assert_eq!(&result[start..end], "bb\n");
+/// ```
+pub fn apply_with_byte_offsets(
+    base_image: &str,
+    patch: &Patch<'_>,
+) -> Result<(String, Vec<(usize, usize)>), ApplyError> {
+    let image = apply_to_image(base_image, patch, Direction::Forward)?;
+
+    let mut result = String::new();
+    let mut offsets = Vec::with_capacity(image.len());
+    for line in image {
+        let start = result.len();
+        result.push_str(line.into_inner());
+        offsets.push((start, result.len()));
+    }
+
+    Ok((result, offsets))
+}
+
+/// Apply a `Patch` to a base image made up of arbitrary bytes
+///
+/// Lines of the base image which aren't touched by the patch don't need to be valid UTF-8.
+///
+/// ```
+/// use diffy::{apply_bytes, create_patch};
+///
+/// let patch = create_patch("a\nb\nc\n", "a\nB\nc\n");
+///
+/// let base_image = b"\xff\xfe\na\nb\nc\n";
+/// let expected = b"\xff\xfe\na\nB\nc\n";
+///
+/// assert_eq!(apply_bytes(base_image, &patch).unwrap(), expected);
+/// ```
+pub fn apply_bytes(base_image: &[u8], patch: &Patch<'_>) -> Result<Vec<u8>, ApplyError> {
+    let image = apply_to_image(base_image, patch, Direction::Forward)?;
+
+    Ok(image
+        .into_iter()
+        .flat_map(ImageLine::into_inner)
+        .copied()
+        .collect())
+}
+
+// Apply a `Patch` returning the lines of the resulting image
+pub(crate) fn apply_to_lines<'a>(
+    base_image: &'a str,
+    patch: &Patch<'a>,
+) -> Result<Vec<&'a str>, ApplyError> {
+    let image = apply_to_image(base_image, patch, Direction::Forward)?;
+
+    Ok(image.into_iter().map(ImageLine::into_inner).collect())
+}
+
+// Apply a `Patch` to an empty base image, ignoring any context lines
+pub(crate) fn apply_to_scratch(patch: &Patch<'_>) -> Result<String, ApplyError> {
+    let mut image = String::new();
+
+    for (hunk_index, hunk) in patch.hunks().iter().enumerate() {
+        if hunk
+            .lines()
+            .iter()
+            .any(|line| matches!(line, Line::Delete(_)))
+        {
+            return Err(ApplyError::HunkFailed {
+                hunk_index,
+                expected_context: pre_image(hunk.lines(), Direction::Forward).collect(),
+                actual_context: String::new(),
+            });
+        }
+
+        for line in hunk.lines() {
+            if let Line::Insert(line) = line {
+                image.push_str(line);
+            }
+        }
+    }
+
+    Ok(image)
+}
+
+// Apply a `Patch` in reverse, turning the modified text back into the original
+pub(crate) fn reverse_apply(modified: &str, patch: &Patch<'_>) -> Result<String, ApplyError> {
+    let image = apply_to_image(modified, patch, Direction::Reverse)?;
+
+    Ok(image.into_iter().map(ImageLine::into_inner).collect())
+}
+
+// Apply a `Patch` to a `Rope` in place
+#[cfg(feature = "ropey")]
+pub(crate) fn apply_to_rope(rope: &mut ropey::Rope, patch: &Patch<'_>) -> Result<(), ApplyError> {
+    // The ranges of lines which have already been patched, in the order they were patched
+    let mut patched: Vec<ops::Range<usize>> = Vec::new();
+
+    for (hunk_index, hunk) in patch.hunks().iter().enumerate() {
+        let len = pre_image_line_count(hunk.lines(), Direction::Forward);
+        let pos = find_rope_position(rope, &patched, hunk, len).ok_or_else(|| {
+            let pos = target_position(hunk, Direction::Forward);
+            let end = cmp::min(pos + len, rope_line_count(rope));
+            ApplyError::HunkFailed {
+                hunk_index,
+                expected_context: pre_image(hunk.lines(), Direction::Forward).collect(),
+                actual_context: (pos..end).map(|i| rope.line(i).to_string()).collect(),
+            }
+        })?;
+
+        let start = rope.line_to_char(pos);
+        let end = rope.line_to_char(pos + len);
+        rope.remove(start..end);
+        let replacement: String = post_image(hunk.lines(), Direction::Forward).collect();
+        rope.insert(start, &replacement);
+
+        // Shift the ranges after this hunk to account for the lines it added or removed
+        let new_len = post_image(hunk.lines(), Direction::Forward).count();
+        for range in &mut patched {
+            if range.start >= pos + len {
+                *range = range.start + new_len - len..range.end + new_len - len;
+            }
+        }
+        patched.push(pos..pos + new_len);
+    }
+
+    Ok(())
+}
+
+// The number of lines in `rope`, not counting the empty line after a trailing newline
+#[cfg(feature = "ropey")]
+fn rope_line_count(rope: &ropey::Rope) -> usize {
+    let lines = rope.len_lines();
+    if rope.line(lines - 1).len_chars() == 0 {
+        lines - 1
+    } else {
+        lines
+    }
+}
+
+#[cfg(feature = "ropey")]
+fn find_rope_position(
+    rope: &ropey::Rope,
+    patched: &[ops::Range<usize>],
+    hunk: &Hunk<'_>,
+    len: usize,
+) -> Option<usize> {
+    let line_count = rope_line_count(rope);
+    let pos = target_position(hunk, Direction::Forward);
+
+    let matches = |pos: usize| {
+        pos + len <= line_count
+            // If any of these lines have already been patched then we can't match at this position
+            && !patched
+                .iter()
+                .any(|range| range.start < pos + len && pos < range.end)
+            && pre_image(hunk.lines(), Direction::Forward)
+                .enumerate()
+                .all(|(i, line)| rope.line(pos + i) == line)
+    };
+
+    let backward = (0..pos).rev();
+    let forward = pos + 1..line_count;
+    iter::once(pos)
+        .chain(interleave(backward, forward))
+        .find(|&pos| matches(pos))
+}
+
+// Whether a hunk's deleted lines are replaced by its inserted lines, or the other way around
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Direction {
+    Forward,
+    Reverse,
+}
+
+fn apply_to_image<'a, T: ?Sized + Text>(
+    base_image: &'a T,
+    patch: &Patch<'a>,
+    direction: Direction,
+) -> Result<Vec<ImageLine<'a, T>>, ApplyError> {
+    let mut image: Vec<_> = LineIter::new(base_image)
+        .map(ImageLine::Unpatched)
+        .collect();
+
+    for (i, hunk) in patch.hunks().iter().enumerate() {
+        apply_hunk(&mut image, hunk, direction)
+            .map_err(|_| hunk_failed(&image, i, hunk, direction))?;
+    }
+
+    Ok(image)
+}
+
+fn hunk_failed<T: ?Sized + Text>(
+    image: &[ImageLine<'_, T>],
+    hunk_index: usize,
+    hunk: &Hunk<'_>,
+    direction: Direction,
+) -> ApplyError {
+    let pos = target_position(hunk, direction);
+    let len = pre_image_line_count(hunk.lines(), direction);
+    let actual = image.iter().skip(pos).take(len);
+
+    ApplyError::HunkFailed {
+        hunk_index,
+        expected_context: pre_image(hunk.lines(), direction).collect(),
+        actual_context: actual
+            .map(|line| String::from_utf8_lossy(line.inner().as_bytes()))
+            .collect(),
+    }
+}
+
+fn apply_hunk<'a, T: ?Sized + Text>(
+    image: &mut Vec<ImageLine<'a, T>>,
+    hunk: &Hunk<'a>,
+    direction: Direction,
+) -> Result<(), ()> {
     // Find position
-    let pos = find_position(image, hunk).ok_or(())?;
+    let pos = find_position(image, hunk, direction).ok_or(())?;
 
     // update image
     image.splice(
-        pos..pos + pre_image_line_count(hunk.lines()),
-        post_image(hunk.lines()).map(ImageLine::Patched),
+        pos..pos + pre_image_line_count(hunk.lines(), direction),
+        post_image(hunk.lines(), direction).map(|line| ImageLine::Patched(T::from_str(line))),
     );
 
     Ok(())
@@ -111,42 +940,67 @@ fn apply_hunk<'a>(image: &mut Vec<ImageLine<'a>>, hunk: &Hunk<'a>) -> Result<(),
 //
 // It might be worth looking into other possible positions to apply the hunk to as described here:
 // https://neil.fraser.name/writing/patch/
-fn find_position(image: &[ImageLine], hunk: &Hunk<'_>) -> Option<usize> {
-    let pos = hunk.new_range().start().saturating_sub(1);
+fn find_position<T: ?Sized + Text>(
+    image: &[ImageLine<T>],
+    hunk: &Hunk<'_>,
+    direction: Direction,
+) -> Option<usize> {
+    let pos = target_position(hunk, direction);
 
     // Create an iterator that starts with 'pos' and then interleaves
     // moving pos backward/foward by one.
     let backward = (0..pos).rev();
     let forward = pos + 1..image.len();
-    for pos in iter::once(pos).chain(interleave(backward, forward)) {
-        if match_fragment(image, hunk.lines(), pos) {
-            return Some(pos);
-        }
-    }
+    iter::once(pos)
+        .chain(interleave(backward, forward))
+        .find(|&pos| match_fragment(image, hunk.lines(), pos, direction))
+}
 
-    None
+// The zero-based position in the image at which `hunk` is expected to apply
+fn target_position(hunk: &Hunk<'_>, direction: Direction) -> usize {
+    let range = match direction {
+        Direction::Forward => hunk.new_range(),
+        Direction::Reverse => hunk.old_range(),
+    };
+    range.start().saturating_sub(1)
 }
 
-fn pre_image_line_count(lines: &[Line<'_>]) -> usize {
-    pre_image(lines).count()
+fn pre_image_line_count(lines: &[Line<'_>], direction: Direction) -> usize {
+    pre_image(lines, direction).count()
 }
 
-fn post_image<'a, 'b>(lines: &'b [Line<'a>]) -> impl Iterator<Item = &'a str> + 'b {
-    lines.iter().filter_map(|line| match line {
-        Line::Context(l) | Line::Insert(l) => Some(*l),
-        Line::Delete(_) => None,
-    })
+fn post_image<'a, 'b>(
+    lines: &'b [Line<'a>],
+    direction: Direction,
+) -> impl Iterator<Item = &'a str> + 'b {
+    let direction = match direction {
+        Direction::Forward => Direction::Reverse,
+        Direction::Reverse => Direction::Forward,
+    };
+    pre_image(lines, direction)
 }
 
-fn pre_image<'a, 'b>(lines: &'b [Line<'a>]) -> impl Iterator<Item = &'a str> + 'b {
-    lines.iter().filter_map(|line| match line {
-        Line::Context(l) | Line::Delete(l) => Some(*l),
-        Line::Insert(_) => None,
-    })
+fn pre_image<'a, 'b>(
+    lines: &'b [Line<'a>],
+    direction: Direction,
+) -> impl Iterator<Item = &'a str> + 'b {
+    lines
+        .iter()
+        .filter_map(move |line| match (line, direction) {
+            (Line::Context(l), _)
+            | (Line::Delete(l), Direction::Forward)
+            | (Line::Insert(l), Direction::Reverse) => Some(*l),
+            _ => None,
+        })
 }
 
-fn match_fragment(image: &[ImageLine], lines: &[Line<'_>], pos: usize) -> bool {
-    let len = pre_image_line_count(lines);
+fn match_fragment<T: ?Sized + Text>(
+    image: &[ImageLine<T>],
+    lines: &[Line<'_>],
+    pos: usize,
+    direction: Direction,
+) -> bool {
+    let len = pre_image_line_count(lines, direction);
 
     let image = if let Some(image) = image.get(pos..pos + len) {
         image
@@ -159,7 +1013,9 @@ fn match_fragment(image: &[ImageLine], lines: &[Line<'_>], pos: usize) -> bool {
         return false;
     }
 
-    pre_image(lines).eq(image.iter().map(ImageLine::inner))
+    pre_image(lines, direction)
+        .map(str::as_bytes)
+        .eq(image.iter().map(|line| line.inner().as_bytes()))
 }
 
 #[derive(Debug)]
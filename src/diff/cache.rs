@@ -0,0 +1,158 @@
+//! Memoization of diff results
+
+use crate::{
+    patch::PatchBuf,
+    range::{DiffRange, Range},
+};
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+};
+
+/// A single operation of a cached diff, covering a number of consecutive lines
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DiffOp {
+    /// Lines present in both texts
+    Equal(usize),
+    /// Lines only present in the original text
+    Delete(usize),
+    /// Lines only present in the modified text
+    Insert(usize),
+}
+
+/// A cache of previously computed diffs, see [`DiffOptions::set_diff_cache`]
+///
+/// Before diffing, the lines of both texts are converted into ids which only depend on which
+/// lines are equal to each other. The key is a hash of both sequences of ids, along with the
+/// settings affecting the result, so texts with the same structure can share a cache entry.
+///
+/// ```
+/// use diffy::{DiffCache, DiffOp, DiffOptions};
+/// use std::collections::HashMap;
+///
+/// let mut opts = DiffOptions::new();
+/// opts.set_diff_cache(HashMap::<u64, Vec<DiffOp>>::new());
+///
+/// let first = opts.create_patch("a\nb\n", "a\nc\n");
+/// // The second diff is found in the cache
+/// let second = opts.create_patch("a\nb\n", "a\nc\n");
+/// assert_eq!(first, second);
+/// ```
+///
+/// [`DiffOptions::set_diff_cache`]: struct.DiffOptions.html#method.set_diff_cache
+pub trait DiffCache {
+    /// Returns the diff stored for `key`, if any
+    fn get(&self, key: u64) -> Option<Vec<DiffOp>>;
+
+    /// Stores the diff for `key`
+    fn insert(&mut self, key: u64, value: Vec<DiffOp>);
+}
+
+impl DiffCache for HashMap<u64, Vec<DiffOp>> {
+    fn get(&self, key: u64) -> Option<Vec<DiffOp>> {
+        HashMap::get(self, &key).cloned()
+    }
+
+    fn insert(&mut self, key: u64, value: Vec<DiffOp>) {
+        HashMap::insert(self, key, value);
+    }
+}
+
+/// A cache of previously created patches, see [`create_patch_cached`]
+///
+/// The key is a 128-bit hash of the original and modified texts.
+///
+/// [`create_patch_cached`]: fn.create_patch_cached.html
+pub trait PatchCache {
+    /// Returns the patch stored for `key`, if any
+    fn get(&self, key: u128) -> Option<PatchBuf>;
+
+    /// Stores the patch for `key`
+    fn insert(&mut self, key: u128, patch: PatchBuf);
+}
+
+impl PatchCache for HashMap<u128, PatchBuf> {
+    fn get(&self, key: u128) -> Option<PatchBuf> {
+        HashMap::get(self, &key).cloned()
+    }
+
+    fn insert(&mut self, key: u128, patch: PatchBuf) {
+        HashMap::insert(self, key, patch);
+    }
+}
+
+// Compute the cache key for the patch between `original` and `modified`
+pub fn patch_key(original: &str, modified: &str) -> u128 {
+    let half = |seed: u8| {
+        let mut hasher = DefaultHasher::new();
+        seed.hash(&mut hasher);
+        original.hash(&mut hasher);
+        modified.hash(&mut hasher);
+        hasher.finish()
+    };
+    u128::from(half(0)) << 64 | u128::from(half(1))
+}
+
+// Compute the cache key for diffing `old` and `new`. `settings` should include everything which
+// can affect the result of the diff.
+pub fn key(old: &[u64], new: &[u64], settings: impl Hash) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    old.hash(&mut hasher);
+    new.hash(&mut hasher);
+    settings.hash(&mut hasher);
+    hasher.finish()
+}
+
+pub fn to_ops(solution: &[DiffRange<'_, '_, [u64]>]) -> Vec<DiffOp> {
+    solution
+        .iter()
+        .map(|diff| match diff {
+            DiffRange::Equal(range, _) => DiffOp::Equal(range.len()),
+            DiffRange::Delete(range) => DiffOp::Delete(range.len()),
+            DiffRange::Insert(range) => DiffOp::Insert(range.len()),
+        })
+        .collect()
+}
+
+// Convert cached ops back into a solution. Returns `None` if the ops don't describe a diff between
+// `old` and `new`, e.g. because of a hash collision.
+pub fn from_ops<'a, 'b>(
+    old: &'a [u64],
+    new: &'b [u64],
+    ops: &[DiffOp],
+) -> Option<Vec<DiffRange<'a, 'b, [u64]>>> {
+    let (mut i, mut j) = (0, 0);
+    let mut solution = Vec::with_capacity(ops.len());
+
+    for op in ops {
+        match *op {
+            DiffOp::Equal(len) => {
+                if old.get(i..i + len)? != new.get(j..j + len)? {
+                    return None;
+                }
+                solution.push(DiffRange::Equal(
+                    Range::new(old, i..i + len),
+                    Range::new(new, j..j + len),
+                ));
+                i += len;
+                j += len;
+            }
+            DiffOp::Delete(len) => {
+                old.get(i..i + len)?;
+                solution.push(DiffRange::Delete(Range::new(old, i..i + len)));
+                i += len;
+            }
+            DiffOp::Insert(len) => {
+                new.get(j..j + len)?;
+                solution.push(DiffRange::Insert(Range::new(new, j..j + len)));
+                j += len;
+            }
+        }
+    }
+
+    if i == old.len() && j == new.len() {
+        Some(solution)
+    } else {
+        None
+    }
+}
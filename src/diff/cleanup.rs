@@ -1,14 +1,41 @@
 use crate::range::{DiffRange, SliceLike};
 
+/// Statistics about the work done while compacting a diff, see
+/// [`DiffOptions::create_patch_with_compaction_stats`]
+///
+/// Compaction shifts blocks of deleted and inserted lines up and down past equal lines, merging
+/// them with adjacent blocks of the same kind where possible. The statistics are useful for
+/// diagnosing why a diff looks the way it does.
+///
+/// [`DiffOptions::create_patch_with_compaction_stats`]: struct.DiffOptions.html#method.create_patch_with_compaction_stats
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CompactionStats {
+    /// The number of steps taken while trying to shift edits up or down
+    pub iterations: usize,
+    /// The number of edits which were merged into an adjacent edit of the same kind
+    pub operations_merged: usize,
+    /// The number of times an edit was shifted past equal lines
+    pub operations_shifted: usize,
+}
+
 // Walks through all edits and shifts them up and then down, trying to see if they run into similar
 // edits which can be merged
 pub fn compact<'a, 'b, T: ?Sized + SliceLike>(diffs: &mut Vec<DiffRange<'a, 'b, T>>) {
+    compact_with_stats(diffs);
+}
+
+// Like `compact` but also returns statistics about the work that was done
+pub fn compact_with_stats<'a, 'b, T: ?Sized + SliceLike>(
+    diffs: &mut Vec<DiffRange<'a, 'b, T>>,
+) -> CompactionStats {
+    let mut stats = CompactionStats::default();
+
     // First attempt to compact all Deletions
     let mut pointer = 0;
     while let Some(&diff) = diffs.get(pointer) {
         if let DiffRange::Delete(_) = diff {
-            pointer = shift_diff_up(diffs, pointer);
-            pointer = shift_diff_down(diffs, pointer);
+            pointer = shift_diff_up(diffs, pointer, &mut stats);
+            pointer = shift_diff_down(diffs, pointer, &mut stats);
         }
         pointer += 1;
     }
@@ -18,8 +45,81 @@ pub fn compact<'a, 'b, T: ?Sized + SliceLike>(diffs: &mut Vec<DiffRange<'a, 'b,
     let mut pointer = 0;
     while let Some(&diff) = diffs.get(pointer) {
         if let DiffRange::Insert(_) = diff {
-            pointer = shift_diff_up(diffs, pointer);
-            pointer = shift_diff_down(diffs, pointer);
+            pointer = shift_diff_up(diffs, pointer, &mut stats);
+            pointer = shift_diff_down(diffs, pointer, &mut stats);
+        }
+        pointer += 1;
+    }
+
+    stats
+}
+
+// Compaction leaves every Insertion and Deletion shifted as far downwards as possible. Lines can
+// often be shifted upwards again without changing the size of the diff, in which case the
+// position where the block of lines ends with a blank line, and starts after one, is preferred.
+// This keeps the matched lines of e.g. functions separated by blank lines together.
+pub fn slide_to_blank_lines<'a, T: ?Sized + SliceLike>(
+    diffs: &mut Vec<DiffRange<'a, 'a, T>>,
+    is_old_blank: impl Fn(usize) -> bool,
+    is_new_blank: impl Fn(usize) -> bool,
+) {
+    let mut pointer = 1;
+    while pointer < diffs.len() {
+        let (this_diff, prev_diff, is_blank): (_, _, &dyn Fn(usize) -> bool) =
+            match (diffs[pointer], diffs[pointer - 1]) {
+                (DiffRange::Insert(this_diff), DiffRange::Equal(_, prev_diff2)) => {
+                    (this_diff, prev_diff2, &is_new_blank)
+                }
+                (DiffRange::Delete(this_diff), DiffRange::Equal(prev_diff1, _)) => {
+                    (this_diff, prev_diff1, &is_old_blank)
+                }
+                _ => {
+                    pointer += 1;
+                    continue;
+                }
+            };
+        // Only move blocks which are surrounded by equal lines
+        if let Some(DiffRange::Insert(_)) | Some(DiffRange::Delete(_)) = diffs.get(pointer + 1) {
+            pointer += 1;
+            continue;
+        }
+
+        let score = |shift: usize| {
+            let start = this_diff.offset() - shift;
+            let end = start + this_diff.len();
+            let mut score = 0;
+            if is_blank(end - 1) {
+                score += 2;
+            }
+            if start == 0 || is_blank(start - 1) {
+                score += 1;
+            }
+            score
+        };
+        // Prefer the current position if there is a tie
+        let max_shift = this_diff.common_suffix_len(prev_diff);
+        let shift = (0..=max_shift)
+            .rev()
+            .max_by_key(|&shift| score(shift))
+            .unwrap_or(0);
+
+        if shift != 0 {
+            let moved = this_diff.slice(this_diff.len() - shift..);
+            let matched = prev_diff.slice(prev_diff.len() - shift..);
+            if let Some(DiffRange::Equal(..)) = diffs.get(pointer + 1) {
+                diffs[pointer + 1].grow_up(shift);
+            } else if let DiffRange::Insert(_) = diffs[pointer] {
+                diffs.insert(pointer + 1, DiffRange::Equal(matched, moved));
+            } else {
+                diffs.insert(pointer + 1, DiffRange::Equal(moved, matched));
+            }
+            diffs[pointer].shift_up(shift);
+            diffs[pointer - 1].shrink_back(shift);
+
+            if diffs[pointer - 1].is_empty() {
+                diffs.remove(pointer - 1);
+                pointer -= 1;
+            }
         }
         pointer += 1;
     }
@@ -29,8 +129,10 @@ pub fn compact<'a, 'b, T: ?Sized + SliceLike>(diffs: &mut Vec<DiffRange<'a, 'b,
 fn shift_diff_up<'a, 'b, T: ?Sized + SliceLike>(
     diffs: &mut Vec<DiffRange<'a, 'b, T>>,
     mut pointer: usize,
+    stats: &mut CompactionStats,
 ) -> usize {
     while let Some(&prev_diff) = pointer.checked_sub(1).and_then(|idx| diffs.get(idx)) {
+        stats.iterations += 1;
         match (diffs[pointer], prev_diff) {
             //
             // Shift Inserts Upwards
@@ -39,6 +141,7 @@ fn shift_diff_up<'a, 'b, T: ?Sized + SliceLike>(
                 // check common suffix for the amount we can shift
                 let suffix_len = this_diff.common_suffix_len(prev_diff1);
                 if suffix_len != 0 {
+                    stats.operations_shifted += 1;
                     if let Some(DiffRange::Equal(..)) = diffs.get(pointer + 1) {
                         diffs[pointer + 1].grow_up(suffix_len);
                     } else {
@@ -73,6 +176,7 @@ fn shift_diff_up<'a, 'b, T: ?Sized + SliceLike>(
                 // check common suffix for the amount we can shift
                 let suffix_len = this_diff.common_suffix_len(prev_diff2);
                 if suffix_len != 0 {
+                    stats.operations_shifted += 1;
                     if let Some(DiffRange::Equal(..)) = diffs.get(pointer + 1) {
                         diffs[pointer + 1].grow_up(suffix_len);
                     } else {
@@ -116,6 +220,7 @@ fn shift_diff_up<'a, 'b, T: ?Sized + SliceLike>(
             | (this_diff @ DiffRange::Delete(_), DiffRange::Delete(_)) => {
                 diffs[pointer - 1].grow_down(this_diff.len());
                 diffs.remove(pointer);
+                stats.operations_merged += 1;
                 pointer -= 1;
             }
 
@@ -130,8 +235,10 @@ fn shift_diff_up<'a, 'b, T: ?Sized + SliceLike>(
 fn shift_diff_down<'a, 'b, T: ?Sized + SliceLike>(
     diffs: &mut Vec<DiffRange<'a, 'b, T>>,
     mut pointer: usize,
+    stats: &mut CompactionStats,
 ) -> usize {
     while let Some(&next_diff) = pointer.checked_add(1).and_then(|idx| diffs.get(idx)) {
+        stats.iterations += 1;
         match (diffs[pointer], next_diff) {
             //
             // Shift Insert Downward
@@ -140,6 +247,7 @@ fn shift_diff_down<'a, 'b, T: ?Sized + SliceLike>(
                 // check common prefix for the amoutn we can shift
                 let prefix_len = this_diff.common_prefix_len(next_diff1);
                 if prefix_len != 0 {
+                    stats.operations_shifted += 1;
                     if let Some(DiffRange::Equal(..)) =
                         pointer.checked_sub(1).and_then(|idx| diffs.get(idx))
                     {
@@ -176,6 +284,7 @@ fn shift_diff_down<'a, 'b, T: ?Sized + SliceLike>(
                 // check common prefix for the amoutn we can shift
                 let prefix_len = this_diff.common_prefix_len(next_diff2);
                 if prefix_len != 0 {
+                    stats.operations_shifted += 1;
                     if let Some(DiffRange::Equal(..)) =
                         pointer.checked_sub(1).and_then(|idx| diffs.get(idx))
                     {
@@ -221,6 +330,7 @@ fn shift_diff_down<'a, 'b, T: ?Sized + SliceLike>(
             | (DiffRange::Delete(_), next_diff @ DiffRange::Delete(_)) => {
                 diffs[pointer].grow_down(next_diff.len());
                 diffs.remove(pointer + 1);
+                stats.operations_merged += 1;
             }
 
             _ => panic!("range to shift must be either Insert or Delete"),
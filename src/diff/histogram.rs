@@ -0,0 +1,264 @@
+//! Histogram diffing, used as a fallback when Myers' algorithm gives up
+//!
+//! Each region is split at the longest run of matching lines around the line which occurs the
+//! fewest times in the old region, and the parts before and after it are diffed recursively.
+//! Lines which occur more than `max_frequency` times are never used to split a region, so
+//! repetitive lines like blank lines or closing braces don't produce spurious matches. The result
+//! isn't necessarily a shortest edit script.
+//!
+//! Finding the split of a region takes time proportional to its size, and the regions at each
+//! depth of the recursion don't overlap. Splits are nested at most `MAX_DEPTH` deep, after which
+//! any remaining region is replaced instead, so the whole diff takes `O((N + M) * MAX_DEPTH)`
+//! time in the worst case.
+
+use crate::range::{DiffRange, Range};
+use std::collections::HashMap;
+
+// The maximum depth of nested splits, after which regions are replaced instead of being split
+const MAX_DEPTH: usize = 64;
+
+pub fn diff<'a, 'b>(
+    old: Range<'a, [u64]>,
+    new: Range<'b, [u64]>,
+    max_frequency: usize,
+    solution: &mut Vec<DiffRange<'a, 'b, [u64]>>,
+) {
+    // The positions of each line of `old`, relative to the underlying slice. This is shared by
+    // all of the regions, which only look at the positions within their own bounds.
+    let mut occurrences: HashMap<u64, Vec<usize>> = HashMap::new();
+    for (i, id) in old.as_slice().iter().enumerate() {
+        occurrences.entry(*id).or_default().push(old.offset() + i);
+    }
+
+    let histogram = Histogram {
+        occurrences,
+        max_frequency,
+    };
+    histogram.diff(old, new, 0, solution);
+}
+
+struct Histogram {
+    occurrences: HashMap<u64, Vec<usize>>,
+    max_frequency: usize,
+}
+
+impl Histogram {
+    fn diff<'a, 'b>(
+        &self,
+        old: Range<'a, [u64]>,
+        new: Range<'b, [u64]>,
+        depth: usize,
+        solution: &mut Vec<DiffRange<'a, 'b, [u64]>>,
+    ) {
+        let prefix = old.common_prefix_len(new);
+        if prefix > 0 {
+            solution.push(DiffRange::Equal(old.slice(..prefix), new.slice(..prefix)));
+        }
+        let old = old.slice(prefix..);
+        let new = new.slice(prefix..);
+
+        let suffix = old.common_suffix_len(new);
+        let old_middle = old.slice(..old.len() - suffix);
+        let new_middle = new.slice(..new.len() - suffix);
+
+        let split = if depth < MAX_DEPTH {
+            self.split(old_middle, new_middle)
+        } else {
+            None
+        };
+        match split {
+            Some((i, j, len)) => {
+                self.diff(
+                    old_middle.slice(..i),
+                    new_middle.slice(..j),
+                    depth + 1,
+                    solution,
+                );
+                solution.push(DiffRange::Equal(
+                    old_middle.slice(i..i + len),
+                    new_middle.slice(j..j + len),
+                ));
+                self.diff(
+                    old_middle.slice(i + len..),
+                    new_middle.slice(j + len..),
+                    depth + 1,
+                    solution,
+                );
+            }
+            None => {
+                if !old_middle.is_empty() {
+                    solution.push(DiffRange::Delete(old_middle));
+                }
+                if !new_middle.is_empty() {
+                    solution.push(DiffRange::Insert(new_middle));
+                }
+            }
+        }
+
+        if suffix > 0 {
+            solution.push(DiffRange::Equal(
+                old.slice(old.len() - suffix..),
+                new.slice(new.len() - suffix..),
+            ));
+        }
+    }
+
+    // Returns `(old_start, new_start, len)` of the run of matching lines to split the region at,
+    // or `None` if there are no suitable matching lines
+    //
+    // Of the runs around the lines which occur the fewest times, the longest is picked. Ties are
+    // broken in favor of the run closest to the middle of `new`, which keeps the recursion shallow
+    // when there are many short runs.
+    fn split(&self, old: Range<'_, [u64]>, new: Range<'_, [u64]>) -> Option<(usize, usize, usize)> {
+        if old.is_empty() || new.is_empty() {
+            return None;
+        }
+        let (old_start, old) = (old.offset(), old.as_slice());
+        let new = new.as_slice();
+        let middle = new.len() / 2;
+
+        // (occurrences, old_start, new_start, len)
+        let mut best: Option<(usize, usize, usize, usize)> = None;
+        let mut j = 0;
+        while j < new.len() {
+            let mut next = j + 1;
+            let positions = match self.occurrences.get(&new[j]) {
+                Some(positions) => {
+                    let start = positions.partition_point(|&i| i < old_start);
+                    let end = positions.partition_point(|&i| i < old_start + old.len());
+                    &positions[start..end]
+                }
+                None => &[][..],
+            };
+            let count = positions.len();
+            if count == 0
+                || count > self.max_frequency
+                || best.is_some_and(|(best_count, ..)| count > best_count)
+            {
+                j = next;
+                continue;
+            }
+
+            for i in positions.iter().map(|i| i - old_start) {
+                let before = old[..i]
+                    .iter()
+                    .rev()
+                    .zip(new[..j].iter().rev())
+                    .take_while(|(a, b)| a == b)
+                    .count();
+                let after = old[i..]
+                    .iter()
+                    .zip(&new[j..])
+                    .take_while(|(a, b)| a == b)
+                    .count();
+                let len = before + after;
+                let start = j - before;
+
+                let better = best.map_or(true, |(best_count, _, best_start, best_len)| {
+                    count < best_count
+                        || len > best_len
+                        || (len == best_len && start.abs_diff(middle) < best_start.abs_diff(middle))
+                });
+                if better {
+                    best = Some((count, i - before, start, len));
+                }
+                // Lines within this run don't need to be looked at again
+                next = next.max(j + after);
+            }
+            j = next;
+        }
+
+        best.map(|(_, i, j, len)| (i, j, len))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn histogram_diff() {
+        let old = [1, 2, 3, 9, 4, 5, 9, 6];
+        let new = [7, 3, 9, 4, 8, 9, 6, 5];
+
+        let mut solution = Vec::new();
+        diff(
+            Range::new(&old, ..),
+            Range::new(&new, ..),
+            64,
+            &mut solution,
+        );
+
+        let mut result = Vec::new();
+        let mut pos = 0;
+        for diff in &solution {
+            match diff {
+                DiffRange::Equal(old_range, new_range) => {
+                    assert_eq!(old_range.offset(), pos);
+                    assert_eq!(old_range.as_slice(), new_range.as_slice());
+                    result.extend_from_slice(old_range.as_slice());
+                    pos += old_range.len();
+                }
+                DiffRange::Delete(range) => {
+                    assert_eq!(range.offset(), pos);
+                    pos += range.len();
+                }
+                DiffRange::Insert(range) => {
+                    assert_eq!(range.offset(), result.len());
+                    result.extend_from_slice(range.as_slice());
+                }
+            }
+        }
+        assert_eq!(pos, old.len());
+        assert_eq!(result, new);
+
+        // The region is split around the unique 3 first, after which 9 is unique in what's left
+        let mut solution = Vec::new();
+        diff(Range::new(&old, ..), Range::new(&new, ..), 1, &mut solution);
+        let equal: Vec<_> = solution
+            .iter()
+            .filter_map(|diff| match diff {
+                DiffRange::Equal(range, _) => Some(range.as_slice()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(equal, [&[3, 9, 4][..], &[9, 6]]);
+    }
+
+    #[test]
+    fn max_depth() {
+        // Blocks of decreasing length, separated by lines which differ. Each split happens at the
+        // longest remaining block, right after the previous one.
+        let (mut old, mut new) = (Vec::new(), Vec::new());
+        let mut id = 0;
+        for len in (1..=2 * MAX_DEPTH as u64).rev() {
+            old.extend(id..id + len);
+            new.extend(id..id + len);
+            old.push(id + len);
+            new.push(id + len + 1);
+            id += len + 2;
+        }
+
+        let mut solution = Vec::new();
+        diff(
+            Range::new(&old, ..),
+            Range::new(&new, ..),
+            64,
+            &mut solution,
+        );
+
+        let equal = solution
+            .iter()
+            .filter(|diff| matches!(diff, DiffRange::Equal(..)))
+            .count();
+        // The common prefix and the blocks up to the maximum depth are kept
+        assert_eq!(equal, MAX_DEPTH + 1);
+        match solution[solution.len() - 2..] {
+            [DiffRange::Delete(old_range), DiffRange::Insert(new_range)] => {
+                assert_eq!(old_range.len(), new_range.len());
+                assert!(old_range.len() > MAX_DEPTH * MAX_DEPTH / 2);
+            }
+            _ => panic!("the remaining region should be replaced"),
+        }
+    }
+}
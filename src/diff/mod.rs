@@ -1,22 +1,42 @@
 use crate::{
-    patch::{Hunk, HunkRange, Line, Patch},
-    range::{DiffRange, SliceLike},
-    utils::Classifier,
+    patch::{Hunk, HunkRange, Line, Patch, PatchBuf},
+    range::{DiffRange, Range, SliceLike},
+    token::TokenClassifier,
+    utils::{Classifier, LineIter},
+};
+use std::{
+    borrow::Cow,
+    cmp,
+    collections::HashSet,
+    fmt, ops,
+    sync::{Mutex, PoisonError},
+    thread,
 };
-use std::{cmp, ops};
 
-mod cleanup;
+mod cache;
+pub(crate) mod cleanup;
+mod histogram;
 mod myers;
+mod sparse;
+
+pub use cache::{DiffCache, DiffOp, PatchCache};
+pub use cleanup::CompactionStats;
 
 #[cfg(test)]
 mod tests;
 
-// TODO determine if this should be exposed in the public API
-#[allow(dead_code)]
+/// A single chunk of a diff between two texts
+///
+/// See [`Patch::from_diff_vec`] for building a `Patch` from a list of `Diff`s.
+///
+/// [`Patch::from_diff_vec`]: struct.Patch.html#method.from_diff_vec
 #[derive(Debug, PartialEq, Eq)]
-enum Diff<'a, T: ?Sized> {
+pub enum Diff<'a, T: ?Sized> {
+    /// Text present in both the original and the modified text
     Equal(&'a T),
+    /// Text only present in the original text
     Delete(&'a T),
+    /// Text only present in the modified text
     Insert(&'a T),
 }
 
@@ -28,6 +48,36 @@ impl<T: ?Sized> Clone for Diff<'_, T> {
     }
 }
 
+impl<T> Diff<'_, T>
+where
+    T: ?Sized + SliceLike,
+{
+    /// Returns the total size of the inserted and deleted chunks in `diffs`, as
+    /// `(insertions, deletions)`
+    ///
+    /// The size of a chunk is its length, i.e. the number of bytes of a `str` or the number of
+    /// elements of a slice.
+    ///
+    /// ```
+    /// use diffy::Diff;
+    ///
+    /// let diffs = [Diff::Equal("a\n"), Diff::Delete("b\nc\n"), Diff::Insert("B\n")];
+    /// assert_eq!(Diff::total_changes(&diffs), (2, 4));
+    ///
+    /// let diffs = [Diff::Insert(&[1, 2][..]), Diff::Equal(&[3]), Diff::Insert(&[4])];
+    /// assert_eq!(Diff::total_changes(&diffs), (3, 0));
+    /// ```
+    pub fn total_changes(diffs: &[Diff<'_, T>]) -> (usize, usize) {
+        diffs
+            .iter()
+            .fold((0, 0), |(insertions, deletions), diff| match diff {
+                Diff::Equal(_) => (insertions, deletions),
+                Diff::Delete(text) => (insertions, deletions + text.len()),
+                Diff::Insert(text) => (insertions + text.len(), deletions),
+            })
+    }
+}
+
 impl<'a, T> From<DiffRange<'a, 'a, T>> for Diff<'a, T>
 where
     T: ?Sized + SliceLike,
@@ -41,11 +91,174 @@ where
     }
 }
 
+/// The result of a diff which may have been cut short
+///
+/// See [`DiffOptions::set_max_edit_distance`] for when a diff is truncated.
+///
+/// [`DiffOptions::set_max_edit_distance`]: struct.DiffOptions.html#method.set_max_edit_distance
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DiffResult<T> {
+    /// The diff was computed in full
+    Complete(T),
+    /// The edit distance exceeded the configured limit and the fallback, by default deleting the
+    /// entire original text and inserting the entire modified text, was used instead
+    Truncated(T),
+}
+
+impl<T> DiffResult<T> {
+    /// Returns `true` if the fallback diff was used
+    pub fn is_truncated(&self) -> bool {
+        matches!(self, DiffResult::Truncated(_))
+    }
+
+    /// Returns the inner value, regardless of whether the diff was truncated
+    pub fn into_inner(self) -> T {
+        match self {
+            DiffResult::Complete(inner) | DiffResult::Truncated(inner) => inner,
+        }
+    }
+}
+
+/// An error returned by [`create_patch_from_edits`] if the edits can't be applied to the
+/// original lines
+///
+/// [`create_patch_from_edits`]: fn.create_patch_from_edits.html
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EditError {
+    /// An edit starts before the end of the previous edit, so the edits overlap or aren't sorted
+    Overlapping {
+        /// The zero-based index of the edit in the list of edits
+        edit_index: usize,
+    },
+    /// An edit starts or deletes lines past the end of the original lines
+    OutOfRange {
+        /// The zero-based index of the edit in the list of edits
+        edit_index: usize,
+    },
+}
+
+impl fmt::Display for EditError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EditError::Overlapping { edit_index } => write!(
+                f,
+                "edit #{} overlaps or precedes the previous edit",
+                edit_index + 1
+            ),
+            EditError::OutOfRange { edit_index } => write!(
+                f,
+                "edit #{} extends past the end of the original lines",
+                edit_index + 1
+            ),
+        }
+    }
+}
+
+impl std::error::Error for EditError {}
+
+/// The algorithm used to diff the regions for which the edit distance exceeds
+/// [`AlgorithmConfig::max_d`]
+///
+/// [`AlgorithmConfig::max_d`]: struct.AlgorithmConfig.html#structfield.max_d
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Algorithm {
+    /// Delete all of the original lines and insert all of the modified lines, keeping only their
+    /// common prefix and suffix
+    Replace,
+    /// Recursively match up the lines which occur the fewest times, see
+    /// [`AlgorithmConfig::histogram_frequency_threshold`]
+    ///
+    /// This doesn't find the shortest edit script, but is fast and usually produces a readable
+    /// diff.
+    ///
+    /// [`AlgorithmConfig::histogram_frequency_threshold`]: struct.AlgorithmConfig.html#structfield.histogram_frequency_threshold
+    Histogram,
+}
+
+/// The tuning parameters of the diff algorithm, see [`DiffOptions::set_algorithm_config`]
+///
+/// [`DiffOptions::set_algorithm_config`]: struct.DiffOptions.html#method.set_algorithm_config
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct AlgorithmConfig {
+    /// The maximum edit distance to search for before giving up, see
+    /// [`DiffOptions::set_max_edit_distance`]
+    ///
+    /// [`DiffOptions::set_max_edit_distance`]: struct.DiffOptions.html#method.set_max_edit_distance
+    pub max_d: Option<usize>,
+    /// The maximum number of diagonals the search may stray from the diagonal it started on
+    /// in either direction
+    ///
+    /// Each step of Myers' algorithm extends the search by one diagonal on either side, so this
+    /// is equivalent to a `max_d` of twice the bound. The smaller of the two limits applies.
+    pub diagonal_bound: Option<usize>,
+    /// The algorithm used when the limit is exceeded
+    pub fallback_algorithm: Algorithm,
+    /// Lines which occur more often than this in a region aren't used by [`Algorithm::Histogram`]
+    /// to split the region
+    ///
+    /// If `None`, the threshold is the number of lines of the longer text divided by 64, but at
+    /// least 1. See [`DiffOptions::set_histogram_fallback_limit`].
+    ///
+    /// [`Algorithm::Histogram`]: enum.Algorithm.html#variant.Histogram
+    /// [`DiffOptions::set_histogram_fallback_limit`]: struct.DiffOptions.html#method.set_histogram_fallback_limit
+    pub histogram_frequency_threshold: Option<usize>,
+}
+
+impl AlgorithmConfig {
+    // The limit on the edit distance resulting from both `max_d` and `diagonal_bound`
+    fn limit(&self) -> Option<usize> {
+        let diagonal_limit = self.diagonal_bound.map(|bound| bound.saturating_mul(2));
+        match (self.max_d, diagonal_limit) {
+            (Some(max_d), Some(diagonal_limit)) => Some(max_d.min(diagonal_limit)),
+            (max_d, diagonal_limit) => max_d.or(diagonal_limit),
+        }
+    }
+
+    // The histogram frequency threshold to use when diffing texts of `len` lines
+    fn histogram_limit(&self, len: usize) -> usize {
+        self.histogram_frequency_threshold
+            .unwrap_or_else(|| cmp::max(1, len / 64))
+    }
+}
+
+impl Default for AlgorithmConfig {
+    /// The default configuration, without any limits
+    ///
+    /// ## Defaults
+    /// * max_d = None
+    /// * diagonal_bound = None
+    /// * fallback_algorithm = Algorithm::Replace
+    /// * histogram_frequency_threshold = None
+    fn default() -> Self {
+        Self {
+            max_d: None,
+            diagonal_bound: None,
+            fallback_algorithm: Algorithm::Replace,
+            histogram_frequency_threshold: None,
+        }
+    }
+}
+
+type HunkLabelGenerator = dyn Fn(usize, &[&str]) -> Option<String> + Send + Sync;
+
+type Normalizer = dyn Fn(&str) -> Cow<'_, str> + Send + Sync;
+
 /// A collection of options for modifying the way a diff is performed
-#[derive(Debug)]
 pub struct DiffOptions {
     compact: bool,
     context_len: usize,
+    algorithm: AlgorithmConfig,
+    sparse_diff: bool,
+    blank_line_heuristic: bool,
+    trailing_context_only: bool,
+    equal_blank_lines: bool,
+    output_strip_trailing_newline: bool,
+    #[cfg(feature = "regex")]
+    ignore_lines_matching: Option<regex::Regex>,
+    token_classifier: Option<Box<dyn TokenClassifier + Send + Sync>>,
+    diff_cache: Option<Mutex<Box<dyn DiffCache + Send>>>,
+    hunk_label_generator: Option<Box<HunkLabelGenerator>>,
+    normalizers: Vec<Box<Normalizer>>,
 }
 
 impl DiffOptions {
@@ -53,19 +266,451 @@ impl DiffOptions {
     ///
     /// ## Defaults
     /// * context_len = 3
+    /// * max_edit_distance = None
+    /// * histogram_fallback_limit = None
+    /// * algorithm_config = AlgorithmConfig::default()
+    /// * sparse_diff = false
+    /// * blank_line_heuristic = false
+    /// * trailing_context_only = false
+    /// * equal_blank_lines = false
+    /// * output_strip_trailing_newline = false
     pub fn new() -> Self {
         Self {
             compact: true,
             context_len: 3,
+            algorithm: AlgorithmConfig::default(),
+            sparse_diff: false,
+            blank_line_heuristic: false,
+            trailing_context_only: false,
+            equal_blank_lines: false,
+            output_strip_trailing_newline: false,
+            #[cfg(feature = "regex")]
+            ignore_lines_matching: None,
+            token_classifier: None,
+            diff_cache: None,
+            hunk_label_generator: None,
+            normalizers: Vec::new(),
         }
     }
 
+    /// Construct a new `DiffOptions` which produces patches without any context lines
+    ///
+    /// This is equivalent to `DiffOptions::new()` followed by `set_context_len(0)`, with all
+    /// other settings left at their defaults. A patch without context only contains the changed
+    /// lines, so there is nothing for [`apply`] to use to locate a hunk when the base image
+    /// differs from the original text. Such a patch should only be applied to content which
+    /// exactly matches the original text, as hunks are only guaranteed to apply at the exact
+    /// line numbers recorded in their headers.
+    ///
+    /// ```
+    /// use diffy::DiffOptions;
+    ///
+    /// let original = "a\nb\nc\n";
+    /// let modified = "a\nB\nc\n";
+    /// let expected = "\
+    /// --- original
+    /// +++ modified
+    /// @@ -2 +2 @@
+    /// -b
+    /// +B
+    /// ";
+    ///
+    /// let patch = DiffOptions::zero_context().create_patch(original, modified);
+    /// assert_eq!(patch.to_string(), expected);
+    /// ```
+    ///
+    /// [`apply`]: fn.apply.html
+    pub fn zero_context() -> Self {
+        let mut opts = Self::new();
+        opts.set_context_len(0);
+        opts
+    }
+
     /// Set the number of context lines that should be used when producing a patch
     pub fn set_context_len(&mut self, context_len: usize) -> &mut Self {
         self.context_len = context_len;
         self
     }
 
+    /// Set the maximum edit distance the diff algorithm will search for before giving up
+    ///
+    /// Finding the shortest edit script takes `O((N+M)D)` time where `D` is the edit distance,
+    /// which can be slow for large texts that are completely different. When the edit distance
+    /// exceeds this limit the diff falls back to deleting all of the original text and inserting
+    /// all of the modified text, keeping only their common prefix and suffix. Use
+    /// [`create_patch_checked`] to detect when this happens.
+    ///
+    /// This sets [`AlgorithmConfig::max_d`], see [`set_algorithm_config`] for using a different
+    /// fallback.
+    ///
+    /// [`create_patch_checked`]: #method.create_patch_checked
+    /// [`AlgorithmConfig::max_d`]: struct.AlgorithmConfig.html#structfield.max_d
+    /// [`set_algorithm_config`]: #method.set_algorithm_config
+    pub fn set_max_edit_distance(&mut self, max_edit_distance: Option<usize>) -> &mut Self {
+        self.algorithm.max_d = max_edit_distance;
+        self
+    }
+
+    /// Set how often a line may occur before the histogram fallback stops using it to match up
+    /// regions
+    ///
+    /// When [`Algorithm::Histogram`] is used as the fallback, lines which occur more than `limit`
+    /// times in a region are considered common and the region is never split at them. A higher
+    /// limit can improve the diff of files with many repeated lines, at the cost of speed. By
+    /// default the limit is the number of lines of the longer text divided by 64, but at least
+    /// 1.
+    ///
+    /// This sets [`AlgorithmConfig::histogram_frequency_threshold`], see
+    /// [`set_algorithm_config`] for enabling the histogram fallback.
+    ///
+    /// [`Algorithm::Histogram`]: enum.Algorithm.html#variant.Histogram
+    /// [`AlgorithmConfig::histogram_frequency_threshold`]: struct.AlgorithmConfig.html#structfield.histogram_frequency_threshold
+    /// [`set_algorithm_config`]: #method.set_algorithm_config
+    pub fn set_histogram_fallback_limit(&mut self, limit: usize) -> &mut Self {
+        self.algorithm.histogram_frequency_threshold = Some(limit);
+        self
+    }
+
+    /// Set all of the tuning parameters of the diff algorithm at once
+    ///
+    /// When the edit distance exceeds the limit set by [`AlgorithmConfig::max_d`] and
+    /// [`AlgorithmConfig::diagonal_bound`], the remaining region is diffed using the
+    /// [`fallback_algorithm`] instead, and the diff is reported as truncated by
+    /// [`create_patch_checked`]. The fallback only applies to diffs of lines (or of the tokens
+    /// produced by a [`TokenClassifier`]); other diffs always use [`Algorithm::Replace`].
+    ///
+    /// ```
+    /// use diffy::{Algorithm, AlgorithmConfig, DiffOptions};
+    ///
+    /// let original = "a\nb\nc\nd\ne\n";
+    /// let modified = "x\nb\ny\nd\nz\n";
+    ///
+    /// let mut opts = DiffOptions::new();
+    /// opts.set_algorithm_config(AlgorithmConfig {
+    ///     max_d: Some(1),
+    ///     fallback_algorithm: Algorithm::Histogram,
+    ///     ..AlgorithmConfig::default()
+    /// });
+    ///
+    /// let result = opts.create_patch_checked(original, modified);
+    /// assert!(result.is_truncated());
+    /// // Unlike replacing everything, the histogram fallback still finds the unchanged lines
+    /// assert_eq!(result.into_inner().hunks()[0].lines().len(), 8);
+    /// ```
+    ///
+    /// [`AlgorithmConfig::max_d`]: struct.AlgorithmConfig.html#structfield.max_d
+    /// [`AlgorithmConfig::diagonal_bound`]: struct.AlgorithmConfig.html#structfield.diagonal_bound
+    /// [`fallback_algorithm`]: struct.AlgorithmConfig.html#structfield.fallback_algorithm
+    /// [`create_patch_checked`]: #method.create_patch_checked
+    /// [`TokenClassifier`]: trait.TokenClassifier.html
+    /// [`Algorithm::Replace`]: enum.Algorithm.html#variant.Replace
+    pub fn set_algorithm_config(&mut self, config: AlgorithmConfig) -> &mut Self {
+        self.algorithm = config;
+        self
+    }
+
+    /// Enable/Disable sparse diffing of large texts with few changes
+    ///
+    /// When enabled, blocks of lines which appear exactly once in both texts are located using
+    /// a rolling hash before performing the diff. These blocks are known to be unchanged so only
+    /// the regions between them need to be diffed, making the cost of the diff proportional to
+    /// the size of the changed regions. This is useful for large files with a few local changes,
+    /// like log files or CSV exports.
+    ///
+    /// The unchanged blocks are matched greedily so the resulting patch may be larger than
+    /// necessary if blocks of lines were moved.
+    ///
+    /// ```
+    /// use diffy::DiffOptions;
+    ///
+    /// let original: String = (0..10_000).map(|i| format!("{},{}\n", i, i * 2)).collect();
+    /// let modified = original.replace("\n5000,10000\n", "\n5000,0\n");
+    ///
+    /// let mut opts = DiffOptions::new();
+    /// opts.set_sparse_diff(true);
+    /// let patch = opts.create_patch(&original, &modified);
+    /// assert_eq!(patch.hunks().len(), 1);
+    /// assert_eq!(patch.hunks()[0].old_range().start(), 4998);
+    /// ```
+    pub fn set_sparse_diff(&mut self, sparse_diff: bool) -> &mut Self {
+        self.sparse_diff = sparse_diff;
+        self
+    }
+
+    /// Enable/Disable choosing between equally small diffs based on blank lines
+    ///
+    /// A block of inserted or deleted lines can often be moved up or down without changing the
+    /// size of the diff, e.g. when it starts and ends with the same line. By default the block
+    /// is placed as far down as possible. When enabled, the position where the block ends with a
+    /// blank line and starts after one is preferred instead, which keeps blocks separated by
+    /// blank lines, like functions, in one piece. This makes diffs of similar inputs consistent
+    /// with each other, regardless of how the lines happen to line up.
+    ///
+    /// ```
+    /// use diffy::{DiffOptions, Line::Insert};
+    ///
+    /// let original = "/// Docs\nfn a() {}\n\n/// Docs\nfn b() {}\n";
+    /// let modified = "/// Docs\nfn a() {}\n\n/// Docs\nfn c() {}\n\n/// Docs\nfn b() {}\n";
+    ///
+    /// let mut opts = DiffOptions::new();
+    /// assert_eq!(
+    ///     opts.create_patch(original, modified).hunks()[0].lines()[3..6],
+    ///     [Insert("fn c() {}\n"), Insert("\n"), Insert("/// Docs\n")]
+    /// );
+    ///
+    /// opts.set_blank_line_heuristic(true);
+    /// assert_eq!(
+    ///     opts.create_patch(original, modified).hunks()[0].lines()[3..6],
+    ///     [Insert("/// Docs\n"), Insert("fn c() {}\n"), Insert("\n")]
+    /// );
+    /// ```
+    pub fn set_blank_line_heuristic(&mut self, blank_line_heuristic: bool) -> &mut Self {
+        self.blank_line_heuristic = blank_line_heuristic;
+        self
+    }
+
+    /// Enable/Disable only including context lines after the changes of each hunk
+    ///
+    /// When enabled, hunks start directly with their first changed line, while up to
+    /// `context_len` lines of context still follow the last one. The start lines of the hunks
+    /// are those of their first changed line. A patch like this still applies to the original
+    /// text, but there is less context to locate each hunk with when the text has changed.
+    ///
+    /// ```
+    /// use diffy::DiffOptions;
+    ///
+    /// let patch = DiffOptions::new()
+    ///     .set_context_len(1)
+    ///     .set_trailing_context_only(true)
+    ///     .create_patch("a\nb\nc\n", "a\nB\nc\n");
+    /// assert_eq!(
+    ///     patch.to_string(),
+    ///     "--- original\n+++ modified\n@@ -2,2 +2,2 @@\n-b\n+B\n c\n"
+    /// );
+    /// ```
+    pub fn set_trailing_context_only(&mut self, trailing_context_only: bool) -> &mut Self {
+        self.trailing_context_only = trailing_context_only;
+        self
+    }
+
+    /// Enable/Disable treating all blank lines as equal
+    ///
+    /// When enabled, lines which only contain whitespace are considered equal to each other,
+    /// regardless of which whitespace they contain. This avoids noise in diffs of e.g. Python or
+    /// YAML files, where trailing whitespace on blank lines doesn't matter. Like lines which are
+    /// equal after normalization, see [`add_normalizer`], blank lines are included in the patch
+    /// the way they appear in the modified text.
+    ///
+    /// ```
+    /// use diffy::DiffOptions;
+    ///
+    /// let original = "a\n\nb\n";
+    /// let modified = "a\n    \nb\n";
+    ///
+    /// let mut opts = DiffOptions::new();
+    /// assert_eq!(opts.create_patch(original, modified).hunks().len(), 1);
+    ///
+    /// opts.set_equal_blank_lines(true);
+    /// assert!(opts.create_patch(original, modified).hunks().is_empty());
+    /// ```
+    ///
+    /// [`add_normalizer`]: #method.add_normalizer
+    pub fn set_equal_blank_lines(&mut self, equal_blank_lines: bool) -> &mut Self {
+        self.equal_blank_lines = equal_blank_lines;
+        self
+    }
+
+    /// Enable/Disable ignoring whether the texts end with a newline
+    ///
+    /// By default a text whose last line doesn't end with a newline differs from one where it
+    /// does, and the patch contains that line marked with `\ No newline at end of file`. When
+    /// enabled, the last line of each text is compared without its trailing newline, so texts
+    /// which only differ in that respect produce a patch without any hunks. Like lines which are
+    /// equal after normalization, see [`add_normalizer`], an unchanged last line is included in
+    /// the patch the way it appears in the modified text.
+    ///
+    /// ```
+    /// use diffy::DiffOptions;
+    ///
+    /// let original = "a\nb";
+    /// let modified = "a\nb\n";
+    ///
+    /// let mut opts = DiffOptions::new();
+    /// assert!(opts
+    ///     .create_patch(original, modified)
+    ///     .to_string()
+    ///     .contains("\\ No newline at end of file"));
+    ///
+    /// opts.set_output_strip_trailing_newline(true);
+    /// assert!(opts.create_patch(original, modified).hunks().is_empty());
+    /// ```
+    ///
+    /// [`add_normalizer`]: #method.add_normalizer
+    pub fn set_output_strip_trailing_newline(
+        &mut self,
+        output_strip_trailing_newline: bool,
+    ) -> &mut Self {
+        self.output_strip_trailing_newline = output_strip_trailing_newline;
+        self
+    }
+
+    /// Ignore changes where all inserted and deleted lines match the regular expression `pattern`
+    ///
+    /// This behaves like the `--ignore-matching-lines` option of GNU diff: a hunk is omitted
+    /// from the patch when every line it inserts or deletes matches `pattern`. Hunks which
+    /// contain at least one other change are kept in full, including any changed lines which
+    /// match `pattern`. Lines are matched without their trailing newline.
+    ///
+    /// Note that a patch with omitted hunks won't reproduce the modified text when applied.
+    ///
+    /// Returns an error if `pattern` isn't a valid regular expression.
+    ///
+    /// ```
+    /// use diffy::DiffOptions;
+    ///
+    /// let original = "# version 1\n\na\nb\nc\nd\ne\nf\n";
+    /// let modified = "# version 2\n\na\nb\nc\nd\ne\nF\n";
+    /// let expected = "\
+    /// --- original
+    /// +++ modified
+    /// @@ -5,4 +5,4 @@
+    ///  c
+    ///  d
+    ///  e
+    /// -f
+    /// +F
+    /// ";
+    ///
+    /// let mut opts = DiffOptions::new();
+    /// opts.set_ignore_lines_matching("^#").unwrap();
+    /// assert_eq!(opts.create_patch(original, modified).to_string(), expected);
+    /// ```
+    #[cfg(feature = "regex")]
+    pub fn set_ignore_lines_matching(&mut self, pattern: &str) -> Result<&mut Self, regex::Error> {
+        self.ignore_lines_matching = Some(regex::Regex::new(pattern)?);
+        Ok(self)
+    }
+
+    /// Set the [`TokenClassifier`] used to split texts into the tokens which are compared
+    ///
+    /// By default texts are split into lines. The tokens produced by `classifier` become the
+    /// lines of the resulting patch.
+    ///
+    /// ```
+    /// use diffy::{DiffOptions, Line, WordClassifier};
+    ///
+    /// let mut opts = DiffOptions::new();
+    /// opts.set_token_classifier(WordClassifier).set_context_len(1);
+    ///
+    /// let patch = opts.create_patch("the quick fox", "the slow fox");
+    /// assert_eq!(
+    ///     patch.hunks()[0].lines(),
+    ///     &[
+    ///         Line::Context(" "),
+    ///         Line::Delete("quick"),
+    ///         Line::Insert("slow"),
+    ///         Line::Context(" "),
+    ///     ]
+    /// );
+    /// ```
+    ///
+    /// [`TokenClassifier`]: trait.TokenClassifier.html
+    pub fn set_token_classifier<C>(&mut self, classifier: C) -> &mut Self
+    where
+        C: TokenClassifier + Send + Sync + 'static,
+    {
+        self.token_classifier = Some(Box::new(classifier));
+        self
+    }
+
+    /// Set a [`DiffCache`] used to memoize the results of diffs
+    ///
+    /// Diffing the same pair of texts again returns the cached result instead of recomputing it,
+    /// which is useful when repeatedly diffing the same files, e.g. in a file watcher. Results
+    /// of diffs which were cut short by [`set_max_edit_distance`] aren't cached.
+    ///
+    /// [`DiffCache`]: trait.DiffCache.html
+    /// [`set_max_edit_distance`]: #method.set_max_edit_distance
+    pub fn set_diff_cache<C>(&mut self, cache: C) -> &mut Self
+    where
+        C: DiffCache + Send + 'static,
+    {
+        self.diff_cache = Some(Mutex::new(Box::new(cache)));
+        self
+    }
+
+    /// Set a function used to generate the label shown after the `@@` of each hunk header
+    ///
+    /// The function is called with the zero-based index of the hunk and the content of each of
+    /// the lines in the hunk and can return a label, like the name of the function that was
+    /// changed, to append to the hunk header. The label is cut off at its first line break, since
+    /// the header is a single line.
+    ///
+    /// ```
+    /// use diffy::DiffOptions;
+    ///
+    /// let original = "fn main() {\n    println!(\"Hello\");\n}\n";
+    /// let modified = "fn main() {\n    println!(\"Hello, World!\");\n}\n";
+    ///
+    /// let mut opts = DiffOptions::new();
+    /// opts.set_hunk_label_generator(|_idx, lines| {
+    ///     lines
+    ///         .iter()
+    ///         .find(|line| line.starts_with("fn "))
+    ///         .map(|line| line.trim_end().to_owned())
+    /// });
+    ///
+    /// let patch = opts.create_patch(original, modified);
+    /// assert_eq!(patch.hunks()[0].function_context(), Some("fn main() {"));
+    /// ```
+    pub fn set_hunk_label_generator<F>(&mut self, f: F) -> &mut Self
+    where
+        F: Fn(usize, &[&str]) -> Option<String> + Send + Sync + 'static,
+    {
+        self.hunk_label_generator = Some(Box::new(f));
+        self
+    }
+
+    /// Append a function to the pipeline of normalizers applied to each line before comparing it
+    ///
+    /// Normalizers are applied in the order they were added, each to the output of the previous
+    /// one, and lines are considered equal if they are equal after all normalizers have been
+    /// applied. This can be used to e.g. ignore case, whitespace or comments. Only the comparison
+    /// is affected, the patch contains the lines as they appear in the texts.
+    ///
+    /// Lines which only differ before normalization are treated as context and are included
+    /// the way they appear in the modified text, so such a patch generally doesn't apply to
+    /// the original text.
+    ///
+    /// ```
+    /// use diffy::DiffOptions;
+    /// use std::borrow::Cow;
+    ///
+    /// let mut opts = DiffOptions::new();
+    /// opts.add_normalizer(|line| Cow::Borrowed(line.trim_end()))
+    ///     .add_normalizer(|line| Cow::Owned(line.to_lowercase()));
+    ///
+    /// let patch = opts.create_patch("Hello  \nWorld\n", "hello\nthere\n");
+    /// assert_eq!(
+    ///     patch.to_string(),
+    ///     "--- original\n+++ modified\n@@ -1,2 +1,2 @@\n hello\n-World\n+there\n"
+    /// );
+    /// ```
+    pub fn add_normalizer<F>(&mut self, f: F) -> &mut Self
+    where
+        F: Fn(&str) -> Cow<'_, str> + Send + Sync + 'static,
+    {
+        self.normalizers.push(Box::new(f));
+        self
+    }
+
+    fn split_into_tokens<'a>(&self, text: &'a str) -> Vec<&'a str> {
+        match &self.token_classifier {
+            Some(classifier) => classifier.split_into_tokens(text),
+            None => LineIter::new(text).collect(),
+        }
+    }
+
     /// Enable/Disable diff compaction. Compaction is a post-processing step which attempts to
     /// produce a prettier diff by reducing the number of edited blocks by shifting and merging
     /// edit blocks.
@@ -79,7 +724,11 @@ impl DiffOptions {
     // TODO determine if this should be exposed in the public API
     #[allow(dead_code)]
     fn diff<'a>(&self, original: &'a str, modified: &'a str) -> Vec<Diff<'a, str>> {
-        let solution = myers::diff(original.as_bytes(), modified.as_bytes());
+        let (solution, _truncated) = myers::diff_with_limit(
+            original.as_bytes(),
+            modified.as_bytes(),
+            self.algorithm.limit(),
+        );
 
         let mut solution = solution
             .into_iter()
@@ -95,13 +744,481 @@ impl DiffOptions {
 
     /// Produce a Patch between two texts based on the configured options
     pub fn create_patch<'a>(&self, original: &'a str, modified: &'a str) -> Patch<'a> {
+        self.create_patch_checked(original, modified).into_inner()
+    }
+
+    /// Produce a Patch between two texts based on the configured options, reporting whether the
+    /// configured [`max_edit_distance`] was exceeded
+    ///
+    /// ```
+    /// use diffy::DiffOptions;
+    ///
+    /// let original = "a\nb\nc\n";
+    /// let modified = "d\ne\nf\n";
+    ///
+    /// let mut opts = DiffOptions::new();
+    /// opts.set_max_edit_distance(Some(2));
+    /// assert!(opts.create_patch_checked(original, modified).is_truncated());
+    ///
+    /// opts.set_max_edit_distance(Some(6));
+    /// assert!(!opts.create_patch_checked(original, modified).is_truncated());
+    /// ```
+    ///
+    /// [`max_edit_distance`]: #method.set_max_edit_distance
+    pub fn create_patch_checked<'a>(
+        &self,
+        original: &'a str,
+        modified: &'a str,
+    ) -> DiffResult<Patch<'a>> {
+        let old_lines = self.split_into_tokens(original);
+        let new_lines = self.split_into_tokens(modified);
+
+        self.create_patch_from_lines(&old_lines, &new_lines)
+    }
+
+    /// Produce a Patch between two texts based on the configured options, along with statistics
+    /// about the compaction of the diff
+    ///
+    /// The statistics describe how blocks of deleted and inserted lines were moved and merged
+    /// after the diff was computed, which is useful for diagnosing why a patch looks the way it
+    /// does. They are all zero if the diff was found in the [`DiffCache`].
+    ///
+    /// ```
+    /// use diffy::DiffOptions;
+    ///
+    /// let original = "a\nb\nc\na\nb\nc\nd\n";
+    /// let modified = "a\nb\nc\nd\n";
+    ///
+    /// let opts = DiffOptions::new();
+    /// let (patch, stats) = opts.create_patch_with_compaction_stats(original, modified);
+    /// assert_eq!(patch, opts.create_patch(original, modified));
+    /// assert_eq!(stats.operations_shifted, 2);
+    /// assert_eq!(stats.operations_merged, 0);
+    /// ```
+    ///
+    /// [`DiffCache`]: trait.DiffCache.html
+    pub fn create_patch_with_compaction_stats<'a>(
+        &self,
+        original: &'a str,
+        modified: &'a str,
+    ) -> (Patch<'a>, CompactionStats) {
+        let old_lines = self.split_into_tokens(original);
+        let new_lines = self.split_into_tokens(modified);
+
+        let (patch, stats) =
+            self.create_patch_from_lines_with_stats(&old_lines, &new_lines, self.context_len);
+        (patch.into_inner(), stats)
+    }
+
+    /// Produce a Patch between two texts which only contains the changed lines, regardless of
+    /// the configured `context_len`
+    ///
+    /// This is like calling [`create_patch`] with `context_len` set to `0`, see [`zero_context`]
+    /// for the caveats of applying such a patch. Only the context is omitted: the changed lines
+    /// are the same as those of [`create_patch`], including how they are compacted, and all
+    /// other settings apply as usual.
+    ///
+    /// ```
+    /// use diffy::DiffOptions;
+    ///
+    /// let original = "a\nb\nc\nd\n";
+    /// let modified = "a\nB\nc\nd\n";
+    ///
+    /// let opts = DiffOptions::new();
+    /// let patch = opts.create_patch_only_changed_lines(original, modified);
+    /// assert_eq!(patch.to_string(), "--- original\n+++ modified\n@@ -2 +2 @@\n-b\n+B\n");
+    /// ```
+    ///
+    /// [`create_patch`]: #method.create_patch
+    /// [`zero_context`]: #method.zero_context
+    pub fn create_patch_only_changed_lines<'a>(
+        &self,
+        original: &'a str,
+        modified: &'a str,
+    ) -> Patch<'a> {
+        let old_lines = self.split_into_tokens(original);
+        let new_lines = self.split_into_tokens(modified);
+
+        self.create_patch_from_lines_with_stats(&old_lines, &new_lines, 0)
+            .0
+            .into_inner()
+    }
+
+    /// Produce a Patch between only the regions of two texts which contain changes
+    ///
+    /// Lines at the beginning and end of the texts which are identical, beyond the configured
+    /// `context_len`, are dropped before performing the diff. This keeps the amount of work
+    /// proportional to the size of the changed region for large files with localized changes.
+    ///
+    /// Returns the patch along with the number of lines which were dropped from the beginning of
+    /// both texts. The line numbers of the hunks in the returned patch are relative to that
+    /// offset, so the offset needs to be added to them to get line numbers in the full texts.
+    ///
+    /// ```
+    /// use diffy::{create_patch, DiffOptions};
+    ///
+    /// let original: String = (0..100).map(|i| format!("{}\n", i)).collect();
+    /// let modified = original.replace("\n50\n", "\nfifty\n");
+    ///
+    /// let (patch, offset) = DiffOptions::new().create_patch_relative(&original, &modified);
+    /// assert_eq!(offset, 47);
+    /// assert_eq!(patch.hunks()[0].old_range().start(), 1);
+    ///
+    /// let full = create_patch(&original, &modified);
+    /// assert_eq!(full.hunks()[0].old_range().start(), 48);
+    /// assert_eq!(full.hunks()[0].lines(), patch.hunks()[0].lines());
+    /// ```
+    pub fn create_patch_relative<'a>(
+        &self,
+        original: &'a str,
+        modified: &'a str,
+    ) -> (Patch<'a>, usize) {
+        let old_lines = self.split_into_tokens(original);
+        let new_lines = self.split_into_tokens(modified);
+
+        let prefix_len = old_lines
+            .iter()
+            .zip(&new_lines)
+            .take_while(|(a, b)| a == b)
+            .count();
+        let max_suffix_len = cmp::min(old_lines.len(), new_lines.len()) - prefix_len;
+        let suffix_len = old_lines
+            .iter()
+            .rev()
+            .zip(new_lines.iter().rev())
+            .take(max_suffix_len)
+            .take_while(|(a, b)| a == b)
+            .count();
+
+        let start = prefix_len.saturating_sub(self.context_len);
+        let trailing = suffix_len.saturating_sub(self.context_len);
+        let old_lines = &old_lines[start..old_lines.len() - trailing];
+        let new_lines = &new_lines[start..new_lines.len() - trailing];
+
+        let patch = self
+            .create_patch_from_lines(old_lines, new_lines)
+            .into_inner();
+
+        (patch, start)
+    }
+
+    /// Produce a Patch between two texts, keeping the given lines matched to each other
+    ///
+    /// Each anchor is a pair `(old_index, new_index)` of zero-based line indices into `original`
+    /// and `modified` of lines which are known to correspond to each other, for example the
+    /// first line of a function which exists in both versions. Anchored lines are never part
+    /// of a change, and only the regions between anchors are diffed. This can guide the diff
+    /// toward a more meaningful result when the shortest one matches up the wrong lines.
+    ///
+    /// Anchors need to be sorted. An anchor which comes before the previous one in either text,
+    /// is out of bounds or refers to lines which aren't equal is ignored.
+    ///
+    /// ```
+    /// use diffy::DiffOptions;
+    ///
+    /// let original = "a\nb\nc\n";
+    /// let modified = "b\nc\na\n";
+    /// let opts = DiffOptions::new();
+    ///
+    /// // The shortest diff moves `a` to the end
+    /// assert_eq!(
+    ///     opts.create_patch(original, modified).to_string(),
+    ///     "--- original\n+++ modified\n@@ -1,3 +1,3 @@\n-a\n b\n c\n+a\n"
+    /// );
+    ///
+    /// // Keeping `a` in place moves `b` and `c` instead
+    /// assert_eq!(
+    ///     opts.create_patch_anchored(original, modified, &[(0, 2)]).to_string(),
+    ///     "--- original\n+++ modified\n@@ -1,3 +1,3 @@\n+b\n+c\n a\n-b\n-c\n"
+    /// );
+    /// ```
+    pub fn create_patch_anchored<'a>(
+        &self,
+        original: &'a str,
+        modified: &'a str,
+        anchors: &[(usize, usize)],
+    ) -> Patch<'a> {
+        let old_lines = self.split_into_tokens(original);
+        let new_lines = self.split_into_tokens(modified);
+
+        let (old_ids, new_ids) = self.classify_lines(&old_lines, &new_lines);
+
+        // Segments are cleaned up separately so no lines are shifted across an anchor
+        let mut truncated = false;
+        let mut remaining = self.algorithm.limit();
+        let solution = myers::diff_anchored(&old_ids, &new_ids, anchors, |old, new| {
+            let (mut segment, segment_truncated) =
+                myers::diff_range_with_fallback(old, new, remaining, |old, new, solution| {
+                    self.fallback(old, new, solution)
+                });
+            truncated |= segment_truncated;
+            remaining = remaining.map(|max| max.saturating_sub(myers::edit_distance(&segment)));
+            if self.compact {
+                cleanup::compact(&mut segment);
+            }
+            self.apply_blank_line_heuristic(&mut segment, &old_lines, &new_lines);
+            segment
+        });
+
+        self.create_patch_from_solution(
+            &old_lines,
+            &new_lines,
+            solution,
+            truncated,
+            self.context_len,
+        )
+        .into_inner()
+    }
+
+    /// Produce a Patch from a list of edits to the lines of the original text, based on the
+    /// configured options
+    ///
+    /// See [`create_patch_from_edits`] for details. The options which apply to the patch after
+    /// diffing, like [`set_hunk_label_generator`], apply to it as well.
+    ///
+    /// [`create_patch_from_edits`]: fn.create_patch_from_edits.html
+    /// [`set_hunk_label_generator`]: #method.set_hunk_label_generator
+    pub fn create_patch_from_edits<'a>(
+        &self,
+        original: &[&'a str],
+        edits: &[(usize, usize, &[&'a str])],
+    ) -> Result<Patch<'a>, EditError> {
+        let mut modified = Vec::with_capacity(original.len());
+        let mut old_pos = 0;
+        for (edit_index, &(start, delete_count, insert)) in edits.iter().enumerate() {
+            if start < old_pos {
+                return Err(EditError::Overlapping { edit_index });
+            }
+            if start > original.len() || delete_count > original.len() - start {
+                return Err(EditError::OutOfRange { edit_index });
+            }
+            modified.extend_from_slice(&original[old_pos..start]);
+            modified.extend_from_slice(insert);
+            old_pos = start + delete_count;
+        }
+        modified.extend_from_slice(&original[old_pos..]);
+
+        let mut solution = Vec::with_capacity(edits.len() * 3 + 1);
+        let (mut old_pos, mut new_pos) = (0, 0);
+        for &(start, delete_count, insert) in edits {
+            let equal_len = start - old_pos;
+            if equal_len > 0 {
+                solution.push(DiffRange::Equal(
+                    Range::new(original, old_pos..start),
+                    Range::new(&modified[..], new_pos..new_pos + equal_len),
+                ));
+                new_pos += equal_len;
+            }
+            if delete_count > 0 {
+                solution.push(DiffRange::Delete(Range::new(
+                    original,
+                    start..start + delete_count,
+                )));
+            }
+            if !insert.is_empty() {
+                solution.push(DiffRange::Insert(Range::new(
+                    &modified[..],
+                    new_pos..new_pos + insert.len(),
+                )));
+                new_pos += insert.len();
+            }
+            old_pos = start + delete_count;
+        }
+        if old_pos < original.len() {
+            solution.push(DiffRange::Equal(
+                Range::new(original, old_pos..),
+                Range::new(&modified[..], new_pos..),
+            ));
+        }
+
+        Ok(self
+            .create_patch_from_solution(original, &modified, solution, false, self.context_len)
+            .into_inner())
+    }
+
+    /// Produce a Patch for each `(original, modified)` pair based on the configured options,
+    /// diffing the pairs concurrently
+    ///
+    /// The pairs are split evenly across as many threads as there is available parallelism. The
+    /// returned patches are in the same order as the input pairs.
+    pub fn create_patch_concurrent<'a>(&self, pairs: &[(&'a str, &'a str)]) -> Vec<Patch<'a>> {
+        let threads = thread::available_parallelism().map_or(1, |n| n.get());
+        let chunk_size = cmp::max(1, pairs.len().div_ceil(threads));
+
+        thread::scope(|scope| {
+            let handles: Vec<_> = pairs
+                .chunks(chunk_size)
+                .map(|chunk| {
+                    scope.spawn(move || {
+                        chunk
+                            .iter()
+                            .map(|(original, modified)| self.create_patch(original, modified))
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect();
+
+            handles
+                .into_iter()
+                .flat_map(|handle| handle.join().unwrap())
+                .collect()
+        })
+    }
+
+    pub(crate) fn create_patch_from_lines<'a>(
+        &self,
+        old_lines: &[&'a str],
+        new_lines: &[&'a str],
+    ) -> DiffResult<Patch<'a>> {
+        self.create_patch_from_lines_with_stats(old_lines, new_lines, self.context_len)
+            .0
+    }
+
+    // Like `create_patch_from_lines` but with the given number of context lines, also returning
+    // statistics about the compaction of the diff
+    fn create_patch_from_lines_with_stats<'a>(
+        &self,
+        old_lines: &[&'a str],
+        new_lines: &[&'a str],
+        context_len: usize,
+    ) -> (DiffResult<Patch<'a>>, CompactionStats) {
+        let (old_ids, new_ids) = self.classify_lines(old_lines, new_lines);
+
+        let (mut solution, truncated, stats) = self.diff_ids_checked(&old_ids, &new_ids);
+        self.apply_blank_line_heuristic(&mut solution, old_lines, new_lines);
+
+        let patch =
+            self.create_patch_from_solution(old_lines, new_lines, solution, truncated, context_len);
+        (patch, stats)
+    }
+
+    // Convert lines into ids which are the same for lines which are equal after normalization, or
+    // which are both blank if `equal_blank_lines` is set. The last lines are compared without
+    // their newline if `output_strip_trailing_newline` is set.
+    fn classify_lines<'a>(
+        &self,
+        old_lines: &[&'a str],
+        new_lines: &[&'a str],
+    ) -> (Vec<u64>, Vec<u64>) {
+        let normalize = |lines: &[&'a str]| -> Vec<Cow<'a, str>> {
+            lines
+                .iter()
+                .enumerate()
+                .map(|(i, line)| {
+                    let line = match line.strip_suffix('\n') {
+                        Some(line)
+                            if self.output_strip_trailing_newline && i + 1 == lines.len() =>
+                        {
+                            line
+                        }
+                        _ => line,
+                    };
+                    let line = self
+                        .normalizers
+                        .iter()
+                        .fold(Cow::Borrowed(line), |line, f| match line {
+                            Cow::Borrowed(line) => f(line),
+                            Cow::Owned(line) => Cow::Owned(f(&line).into_owned()),
+                        });
+                    // Give all blank lines the same id
+                    if self.equal_blank_lines && line.trim().is_empty() {
+                        Cow::Borrowed("")
+                    } else {
+                        line
+                    }
+                })
+                .collect()
+        };
+        let old_lines = normalize(old_lines);
+        let new_lines = normalize(new_lines);
+
         let mut classifier = Classifier::default();
-        let (old_lines, old_ids) = classifier.classify_lines(original);
-        let (new_lines, new_ids) = classifier.classify_lines(modified);
+        let old_ids = old_lines.iter().map(|l| classifier.classify(l)).collect();
+        let new_ids = new_lines.iter().map(|l| classifier.classify(l)).collect();
+        (old_ids, new_ids)
+    }
 
-        let solution = self.diff_slice(&old_ids, &new_ids);
+    fn apply_blank_line_heuristic<'a>(
+        &self,
+        solution: &mut Vec<DiffRange<'a, 'a, [u64]>>,
+        old_lines: &[&str],
+        new_lines: &[&str],
+    ) {
+        if self.blank_line_heuristic {
+            let is_blank = |line: &str| line.trim().is_empty();
+            cleanup::slide_to_blank_lines(
+                solution,
+                |i| is_blank(old_lines[i]),
+                |i| is_blank(new_lines[i]),
+            );
+        }
+    }
+
+    // The number of context lines to include before the changes of a hunk, given the number of
+    // context lines after them
+    fn pre_context_len(&self, context_len: usize) -> usize {
+        if self.trailing_context_only {
+            0
+        } else {
+            context_len
+        }
+    }
+
+    // Turn the solution of a diff between two sequences of lines into a patch with the given
+    // number of context lines, applying the options which affect a patch after the diff has been
+    // performed
+    fn create_patch_from_solution<'a, T>(
+        &self,
+        old_lines: &[&'a str],
+        new_lines: &[&'a str],
+        solution: Vec<DiffRange<'_, '_, [T]>>,
+        truncated: bool,
+        context_len: usize,
+    ) -> DiffResult<Patch<'a>> {
+        let mut patch = to_patch(
+            old_lines,
+            new_lines,
+            &solution,
+            self.pre_context_len(context_len),
+            context_len,
+        );
+
+        #[cfg(feature = "regex")]
+        if let Some(regex) = &self.ignore_lines_matching {
+            patch.hunks_mut().retain(|hunk| {
+                hunk.lines().iter().any(|line| match line {
+                    Line::Context(_) => false,
+                    Line::Delete(l) | Line::Insert(l) => {
+                        !regex.is_match(l.strip_suffix('\n').unwrap_or(l))
+                    }
+                })
+            });
+        }
 
-        to_patch(&old_lines, &new_lines, &solution, self.context_len)
+        if let Some(generator) = &self.hunk_label_generator {
+            for (idx, hunk) in patch.hunks_mut().iter_mut().enumerate() {
+                let lines: Vec<&str> = hunk
+                    .lines()
+                    .iter()
+                    .map(|line| match line {
+                        Line::Context(l) | Line::Delete(l) | Line::Insert(l) => *l,
+                    })
+                    .collect();
+                let label = generator(idx, &lines).map(|mut label| {
+                    if let Some(end) = label.find(['\r', '\n']) {
+                        label.truncate(end);
+                    }
+                    label
+                });
+                hunk.set_function_context(label);
+            }
+        }
+
+        if truncated {
+            DiffResult::Truncated(patch)
+        } else {
+            DiffResult::Complete(patch)
+        }
     }
 
     pub(crate) fn diff_slice<'a, T: PartialEq>(
@@ -109,13 +1226,159 @@ impl DiffOptions {
         old: &'a [T],
         new: &'a [T],
     ) -> Vec<DiffRange<'a, 'a, [T]>> {
-        let mut solution = myers::diff(old, new);
+        self.diff_slice_checked(old, new).0
+    }
+
+    fn diff_slice_checked<'a, T: PartialEq>(
+        &self,
+        old: &'a [T],
+        new: &'a [T],
+    ) -> (Vec<DiffRange<'a, 'a, [T]>>, bool) {
+        let (mut solution, truncated) = myers::diff_with_limit(old, new, self.algorithm.limit());
 
         if self.compact {
             cleanup::compact(&mut solution);
         }
 
-        solution
+        (solution, truncated)
+    }
+
+    // Build a patch from a list of diffs whose chunks are made up of whole lines
+    pub(crate) fn create_patch_from_diffs<'a>(
+        &self,
+        old_lines: &[&'a str],
+        new_lines: &[&'a str],
+        diffs: &[Diff<'_, str>],
+    ) -> Patch<'a> {
+        // Returns the number of lines in `text`, checking they match `lines` starting at `pos`
+        fn match_lines(text: &str, lines: &[&str], pos: usize, side: &str) -> usize {
+            let mut len = 0;
+            for line in LineIter::new(text) {
+                assert!(
+                    lines.get(pos + len) == Some(&line),
+                    "diff doesn't match line {} of the {} lines",
+                    pos + len + 1,
+                    side
+                );
+                len += 1;
+            }
+            len
+        }
+
+        let mut solution = Vec::with_capacity(diffs.len());
+        let (mut old_pos, mut new_pos) = (0, 0);
+        for diff in diffs {
+            match diff {
+                Diff::Equal(text) => {
+                    let old_len = match_lines(text, old_lines, old_pos, "original");
+                    let new_len = match_lines(text, new_lines, new_pos, "modified");
+                    solution.push(DiffRange::Equal(
+                        Range::new(old_lines, old_pos..old_pos + old_len),
+                        Range::new(new_lines, new_pos..new_pos + new_len),
+                    ));
+                    old_pos += old_len;
+                    new_pos += new_len;
+                }
+                Diff::Delete(text) => {
+                    let len = match_lines(text, old_lines, old_pos, "original");
+                    solution.push(DiffRange::Delete(Range::new(
+                        old_lines,
+                        old_pos..old_pos + len,
+                    )));
+                    old_pos += len;
+                }
+                Diff::Insert(text) => {
+                    let len = match_lines(text, new_lines, new_pos, "modified");
+                    solution.push(DiffRange::Insert(Range::new(
+                        new_lines,
+                        new_pos..new_pos + len,
+                    )));
+                    new_pos += len;
+                }
+            }
+        }
+        assert!(
+            old_pos == old_lines.len() && new_pos == new_lines.len(),
+            "diff doesn't cover all of the lines"
+        );
+
+        to_patch(
+            old_lines,
+            new_lines,
+            &solution,
+            self.pre_context_len(self.context_len),
+            self.context_len,
+        )
+    }
+
+    // Like `diff_slice_checked` but able to take advantage of `myers::diff_ids_with_fallback`. Also
+    // returns statistics about the compaction of the diff, which are all zero if the diff was
+    // found in the cache.
+    fn diff_ids_checked<'a>(
+        &self,
+        old: &'a [u64],
+        new: &'a [u64],
+    ) -> (Vec<DiffRange<'a, 'a, [u64]>>, bool, CompactionStats) {
+        // The settings which affect the solution are part of the cache key
+        let settings = (self.compact, self.sparse_diff, self.algorithm);
+        let cache = self
+            .diff_cache
+            .as_ref()
+            .map(|cache| (cache, cache::key(old, new, settings)));
+        if let Some((cache, key)) = cache {
+            let ops = cache
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .get(key);
+            if let Some(solution) = ops.and_then(|ops| cache::from_ops(old, new, &ops)) {
+                return (solution, false, CompactionStats::default());
+            }
+        }
+
+        let (mut solution, truncated) = if self.sparse_diff {
+            sparse::diff(old, new, self.algorithm.limit(), |old, new, solution| {
+                self.fallback(old, new, solution)
+            })
+        } else {
+            myers::diff_ids_with_fallback(old, new, self.algorithm.limit(), |old, new, solution| {
+                self.fallback(old, new, solution)
+            })
+        };
+
+        let stats = if self.compact {
+            cleanup::compact_with_stats(&mut solution)
+        } else {
+            CompactionStats::default()
+        };
+
+        if let Some((cache, key)) = cache {
+            if !truncated {
+                cache
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .insert(key, cache::to_ops(&solution));
+            }
+        }
+
+        (solution, truncated, stats)
+    }
+
+    // Use the configured fallback to diff a region of ids for which the edit distance exceeded the
+    // limit
+    fn fallback<'a, 'b>(
+        &self,
+        old: Range<'a, [u64]>,
+        new: Range<'b, [u64]>,
+        solution: &mut Vec<DiffRange<'a, 'b, [u64]>>,
+    ) {
+        match self.algorithm.fallback_algorithm {
+            Algorithm::Replace => myers::replace(old, new, solution),
+            Algorithm::Histogram => {
+                let len = cmp::max(old.inner().len(), new.inner().len());
+                let max_frequency = self.algorithm.histogram_limit(len);
+                histogram::diff(old, new, max_frequency, solution)
+            }
+        }
     }
 }
 
@@ -125,6 +1388,105 @@ impl Default for DiffOptions {
     }
 }
 
+impl fmt::Debug for DiffOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut debug = f.debug_struct("DiffOptions");
+        debug
+            .field("compact", &self.compact)
+            .field("context_len", &self.context_len)
+            .field("algorithm", &self.algorithm)
+            .field("sparse_diff", &self.sparse_diff)
+            .field("blank_line_heuristic", &self.blank_line_heuristic)
+            .field("trailing_context_only", &self.trailing_context_only)
+            .field("equal_blank_lines", &self.equal_blank_lines)
+            .field(
+                "output_strip_trailing_newline",
+                &self.output_strip_trailing_newline,
+            );
+        #[cfg(feature = "regex")]
+        debug.field("ignore_lines_matching", &self.ignore_lines_matching);
+        debug
+            .field(
+                "token_classifier",
+                &self.token_classifier.as_ref().map(|_| ".."),
+            )
+            .field("diff_cache", &self.diff_cache.as_ref().map(|_| ".."))
+            .field(
+                "hunk_label_generator",
+                &self.hunk_label_generator.as_ref().map(|_| ".."),
+            )
+            .field(
+                "normalizers",
+                &self.normalizers.iter().map(|_| "..").collect::<Vec<_>>(),
+            )
+            .finish()
+    }
+}
+
+impl fmt::Display for DiffOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "DiffOptions {{ context_len: {}, compact: {}, max_edit_distance: ",
+            self.context_len, self.compact
+        )?;
+        match self.algorithm.max_d {
+            Some(max_edit_distance) => write!(f, "{}", max_edit_distance)?,
+            None => write!(f, "unlimited")?,
+        }
+        if let Some(diagonal_bound) = self.algorithm.diagonal_bound {
+            write!(f, ", diagonal_bound: {}", diagonal_bound)?;
+        }
+        if self.algorithm.fallback_algorithm != Algorithm::Replace {
+            write!(
+                f,
+                ", fallback_algorithm: {:?}",
+                self.algorithm.fallback_algorithm
+            )?;
+        }
+        if let Some(threshold) = self.algorithm.histogram_frequency_threshold {
+            write!(f, ", histogram_frequency_threshold: {}", threshold)?;
+        }
+        if self.sparse_diff {
+            write!(f, ", sparse_diff: true")?;
+        }
+        if self.blank_line_heuristic {
+            write!(f, ", blank_line_heuristic: true")?;
+        }
+        if self.trailing_context_only {
+            write!(f, ", trailing_context_only: true")?;
+        }
+        if self.equal_blank_lines {
+            write!(f, ", equal_blank_lines: true")?;
+        }
+        if self.output_strip_trailing_newline {
+            write!(f, ", output_strip_trailing_newline: true")?;
+        }
+        #[cfg(feature = "regex")]
+        if let Some(regex) = &self.ignore_lines_matching {
+            write!(f, ", ignore_lines_matching: {:?}", regex.as_str())?;
+        }
+        if self.token_classifier.is_some() {
+            write!(f, ", token_classifier: custom")?;
+        }
+        if self.diff_cache.is_some() {
+            write!(f, ", diff_cache: custom")?;
+        }
+        if !self.normalizers.is_empty() {
+            write!(f, ", normalizers: {}", self.normalizers.len())?;
+        }
+        write!(
+            f,
+            ", hunk_label_generator: {} }}",
+            if self.hunk_label_generator.is_some() {
+                "custom"
+            } else {
+                "none"
+            }
+        )
+    }
+}
+
 // TODO determine if this should be exposed in the public API
 #[allow(dead_code)]
 fn diff<'a>(original: &'a str, modified: &'a str) -> Vec<Diff<'a, str>> {
@@ -168,10 +1530,261 @@ pub fn create_patch<'a>(original: &'a str, modified: &'a str) -> Patch<'a> {
     DiffOptions::default().create_patch(original, modified)
 }
 
-fn to_patch<'a>(
+/// Create a patch between two texts, reusing a previously created patch from `cache` if the same
+/// texts have been diffed before
+///
+/// The patch is created using the default [`DiffOptions`] and stored in `cache` if it isn't found
+/// there. A cached patch is only used if it describes the differences between the texts, i.e. if
+/// the lines of its hunks match the texts and the texts are equal everywhere else, so a hash
+/// collision results in the patch being created again rather than an incorrect patch.
+///
+/// ```
+/// use diffy::{create_patch, create_patch_cached, PatchBuf};
+/// use std::collections::HashMap;
+///
+/// let mut cache = HashMap::<u128, PatchBuf>::new();
+///
+/// let first = create_patch_cached("a\nb\n", "a\nc\n", &mut cache);
+/// assert_eq!(cache.len(), 1);
+/// // The second patch is found in the cache
+/// let second = create_patch_cached("a\nb\n", "a\nc\n", &mut cache);
+/// assert_eq!(cache.len(), 1);
+/// assert_eq!(first, second);
+/// assert_eq!(second, create_patch("a\nb\n", "a\nc\n"));
+/// ```
+///
+/// [`DiffOptions`]: struct.DiffOptions.html
+pub fn create_patch_cached<'a>(
+    original: &'a str,
+    modified: &'a str,
+    cache: &mut impl PatchCache,
+) -> Patch<'a> {
+    let key = cache::patch_key(original, modified);
+    if let Some(patch) = cache
+        .get(key)
+        .and_then(|buf| buf.borrow_from(original, modified))
+    {
+        return patch;
+    }
+
+    let patch = create_patch(original, modified);
+    cache.insert(key, PatchBuf::from(&patch));
+    patch
+}
+
+/// Count the number of lines which differ between two texts with the same number of lines
+///
+/// This is the line-level Hamming distance: lines are compared pairwise by position so, unlike
+/// [`create_patch`], no attempt is made to find inserted or deleted lines. This makes it much
+/// cheaper to compute, e.g. for detecting changes which only reformat lines in place.
+///
+/// Returns `usize::MAX` if the texts have a different number of lines.
+///
+/// ```
+/// use diffy::hamming_distance_lines;
+///
+/// assert_eq!(hamming_distance_lines("a\nb\nc\n", "a\nB\nC\n"), 2);
+/// assert_eq!(hamming_distance_lines("a\nb\nc\n", "a\nb\n"), usize::MAX);
+/// ```
+///
+/// [`create_patch`]: fn.create_patch.html
+pub fn hamming_distance_lines(a: &str, b: &str) -> usize {
+    let mut a = LineIter::new(a);
+    let mut b = LineIter::new(b);
+    let mut distance = 0;
+
+    loop {
+        match (a.next(), b.next()) {
+            (Some(line1), Some(line2)) => {
+                if line1 != line2 {
+                    distance += 1;
+                }
+            }
+            (None, None) => return distance,
+            _ => return usize::MAX,
+        }
+    }
+}
+
+/// Quickly estimate how similar two texts are
+///
+/// Returns a value between `0.0` (nothing in common) and `1.0` (very likely identical), computed
+/// as the Jaccard similarity of the sets of character trigrams of `a` and `b`. Texts shorter than
+/// three characters are treated as a single n-gram. This runs in `O(N)` time instead of finding
+/// the actual differences, which makes it suitable for roughly sorting large files by similarity.
+///
+/// The result is only an approximation:
+///
+/// * Each inserted, deleted or replaced character changes at most three trigrams, so if `a` has
+///   `T` distinct trigrams and `k` characters were edited to produce `b`, the result is at least
+///   `(T - 3k) / (T + 3k)`.
+/// * Trigrams are compared as sets, so repeated or reordered content isn't detected. For example,
+///   `"abcabc"` and `"abcabcabc"` are reported as identical.
+///
+/// ```
+/// use diffy::diff_ratio_fast;
+///
+/// assert_eq!(diff_ratio_fast("abcdef", "abcdef"), 1.0);
+/// assert_eq!(diff_ratio_fast("abcdef", "uvwxyz"), 0.0);
+///
+/// let ratio = diff_ratio_fast("The quick brown fox", "The quick brown cat");
+/// assert!(ratio > 0.5 && ratio < 1.0);
+/// ```
+pub fn diff_ratio_fast(a: &str, b: &str) -> f64 {
+    fn trigrams(s: &str) -> HashSet<&str> {
+        let boundaries: Vec<usize> = s
+            .char_indices()
+            .map(|(i, _)| i)
+            .chain(Some(s.len()))
+            .collect();
+
+        if boundaries.len() <= 4 {
+            return Some(s).into_iter().collect();
+        }
+        boundaries.windows(4).map(|w| &s[w[0]..w[3]]).collect()
+    }
+
+    if a == b {
+        return 1.0;
+    }
+
+    let a = trigrams(a);
+    let b = trigrams(b);
+    let intersection = a.intersection(&b).count();
+    let union = a.len() + b.len() - intersection;
+
+    intersection as f64 / union as f64
+}
+
+/// Diff two texts at the granularity of extended grapheme clusters
+///
+/// Unlike diffing individual `char`s, a user-perceived character made up of several `char`s,
+/// like `e` followed by a combining accent or an emoji with a skin tone modifier, is never split
+/// between an `Equal` and a `Delete` or `Insert` chunk. Each chunk consists of whole grapheme
+/// clusters of `original` or `modified`.
+///
+/// Requires the `unicode-segmentation` feature.
+///
+/// ```
+/// use diffy::{diff_graphemes, Diff};
+///
+/// // `e` with a combining acute accent vs `e` with a combining grave accent
+/// let diffs = diff_graphemes("cafe\u{301}!", "cafe\u{300}!");
+/// assert_eq!(
+///     diffs,
+///     [
+///         Diff::Equal("caf"),
+///         Diff::Insert("e\u{300}"),
+///         Diff::Delete("e\u{301}"),
+///         Diff::Equal("!"),
+///     ]
+/// );
+/// ```
+#[cfg(feature = "unicode-segmentation")]
+pub fn diff_graphemes<'a>(original: &'a str, modified: &'a str) -> Vec<Diff<'a, str>> {
+    myers::diff_unicode_graphemes(original, modified)
+        .into_iter()
+        .map(Diff::from)
+        .collect()
+}
+
+/// Find the elements which only appear in one of two slices, ignoring their order
+///
+/// Returns `(only_in_a, only_in_b)`, both sorted. The slices are treated as multisets, so an
+/// element which appears twice in `a` and once in `b` is returned once in `only_in_a`. This is
+/// useful for comparing things like lists of imports or dependencies, where a reordering isn't a
+/// change. It runs in `O(N log N)` time, since sorted copies of the slices are compared.
+///
+/// ```
+/// use diffy::diff_ignore_order;
+///
+/// let old = ["serde", "regex", "log", "log"];
+/// let new = ["log", "rand", "serde"];
+///
+/// assert_eq!(diff_ignore_order(&old, &new), (vec!["log", "regex"], vec!["rand"]));
+/// ```
+pub fn diff_ignore_order<T: Ord + Clone>(a: &[T], b: &[T]) -> (Vec<T>, Vec<T>) {
+    let mut a: Vec<&T> = a.iter().collect();
+    let mut b: Vec<&T> = b.iter().collect();
+    a.sort_unstable();
+    b.sort_unstable();
+
+    let (mut only_in_a, mut only_in_b) = (Vec::new(), Vec::new());
+    let (mut a, mut b) = (a.into_iter().peekable(), b.into_iter().peekable());
+    while let (Some(x), Some(y)) = (a.peek(), b.peek()) {
+        match x.cmp(y) {
+            cmp::Ordering::Less => only_in_a.extend(a.next().cloned()),
+            cmp::Ordering::Greater => only_in_b.extend(b.next().cloned()),
+            cmp::Ordering::Equal => {
+                a.next();
+                b.next();
+            }
+        }
+    }
+    only_in_a.extend(a.cloned());
+    only_in_b.extend(b.cloned());
+
+    (only_in_a, only_in_b)
+}
+
+/// Create a patch for each `(original, modified)` pair, diffing the pairs concurrently.
+///
+/// The returned patches are in the same order as the input pairs.
+///
+/// ```
+/// use diffy::{create_patch, create_patch_concurrent};
+///
+/// let pairs = [("a\n", "b\n"), ("c\n", "c\n"), ("d\n", "d\ne\n")];
+/// let patches = create_patch_concurrent(&pairs);
+///
+/// assert_eq!(patches.len(), 3);
+/// for (patch, (original, modified)) in patches.iter().zip(&pairs) {
+///     assert_eq!(*patch, create_patch(original, modified));
+/// }
+/// ```
+pub fn create_patch_concurrent<'a>(pairs: &[(&'a str, &'a str)]) -> Vec<Patch<'a>> {
+    DiffOptions::default().create_patch_concurrent(pairs)
+}
+
+/// Create a patch from a list of edits to the lines of the original text
+///
+/// Each edit is a tuple `(start, delete_count, insert)`, which deletes `delete_count` lines of
+/// `original` starting at the zero-based index `start` and inserts the lines `insert` in their
+/// place. The edits refer to positions in `original`, so they must be sorted by `start` and
+/// must not overlap. As with [`Patch::from_diff_vec`], each line should include its line
+/// ending. Context lines are added around the edits as for any other patch.
+///
+/// This is useful when the positions of the changes are already known, e.g. from an editor,
+/// and avoids diffing the texts again.
+///
+/// Returns an [`EditError`] if the edits aren't sorted, overlap or extend past the end of
+/// `original`.
+///
+/// ```
+/// use diffy::{create_patch, create_patch_from_edits, EditError};
+///
+/// let original = ["a\n", "b\n", "c\n", "d\n"];
+/// let patch = create_patch_from_edits(&original, &[(1, 1, &["B\n"]), (4, 0, &["e\n"])]);
+/// assert_eq!(patch.unwrap(), create_patch("a\nb\nc\nd\n", "a\nB\nc\nd\ne\n"));
+///
+/// let patch = create_patch_from_edits(&original, &[(3, 2, &[])]);
+/// assert_eq!(patch.unwrap_err(), EditError::OutOfRange { edit_index: 0 });
+/// ```
+///
+/// [`Patch::from_diff_vec`]: struct.Patch.html#method.from_diff_vec
+/// [`EditError`]: enum.EditError.html
+pub fn create_patch_from_edits<'a>(
+    original: &[&'a str],
+    edits: &[(usize, usize, &[&'a str])],
+) -> Result<Patch<'a>, EditError> {
+    DiffOptions::default().create_patch_from_edits(original, edits)
+}
+
+fn to_patch<'a, T>(
     lines1: &[&'a str],
     lines2: &[&'a str],
-    solution: &[DiffRange<[u64]>],
+    solution: &[DiffRange<[T]>],
+    pre_context_len: usize,
     context_len: usize,
 ) -> Patch<'a> {
     let edit_script = build_edit_script(solution);
@@ -180,8 +1793,8 @@ fn to_patch<'a>(
 
     let mut idx = 0;
     while let Some(mut script) = edit_script.get(idx) {
-        let start1 = script.old.start.saturating_sub(context_len);
-        let start2 = script.new.start.saturating_sub(context_len);
+        let start1 = script.old.start.saturating_sub(pre_context_len);
+        let start2 = script.new.start.saturating_sub(pre_context_len);
 
         let (mut end1, mut end2) = calc_end(
             context_len,
@@ -212,7 +1825,7 @@ fn to_patch<'a>(
             if let Some(s) = edit_script.get(idx + 1) {
                 // Check to see if we can merge the hunks
                 let start1_next =
-                    cmp::min(s.old.start, lines1.len() - 1).saturating_sub(context_len);
+                    cmp::min(s.old.start, lines1.len() - 1).saturating_sub(pre_context_len);
                 if start1_next < end1 {
                     // Context lines between hunks
                     for (_i1, i2) in (script.old.end..s.old.start).zip(script.new.end..s.new.start)
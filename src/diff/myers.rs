@@ -1,5 +1,8 @@
 use crate::range::{DiffRange, Range};
-use std::ops::{Index, IndexMut};
+use std::{
+    collections::{HashMap, HashSet},
+    ops::{Index, IndexMut},
+};
 
 // A D-path is a path which starts at (0,0) that has exactly D non-diagonal edges. All D-paths
 // consist of a (D - 1)-path followed by a non-diagonal edge and then a possibly empty sequence of
@@ -37,12 +40,24 @@ impl Index<isize> for V {
     type Output = usize;
 
     fn index(&self, index: isize) -> &Self::Output {
+        debug_assert!(
+            index + self.offset >= 0 && ((index + self.offset) as usize) < self.v.len(),
+            "diagonal {} out of bounds for V of length {}",
+            index,
+            self.v.len()
+        );
         &self.v[(index + self.offset) as usize]
     }
 }
 
 impl IndexMut<isize> for V {
     fn index_mut(&mut self, index: isize) -> &mut Self::Output {
+        debug_assert!(
+            index + self.offset >= 0 && ((index + self.offset) as usize) < self.v.len(),
+            "diagonal {} out of bounds for V of length {}",
+            index,
+            self.v.len()
+        );
         &mut self.v[(index + self.offset) as usize]
     }
 }
@@ -69,7 +84,7 @@ impl ::std::fmt::Display for Snake {
 
 fn max_d(len1: usize, len2: usize) -> usize {
     // XXX look into reducing the need to have the additional '+ 1'
-    (len1 + len2 + 1) / 2 + 1
+    (len1 + len2).div_ceil(2) + 1
 }
 
 // The divide part of a divide-and-conquer strategy. A D-path has D+1 snakes some of which may
@@ -77,12 +92,16 @@ fn max_d(len1: usize, len2: usize) -> usize {
 // D-path. The idea for doing so is to simultaneously run the basic algorithm in both the
 // forward and reverse directions until furthest reaching forward and reverse paths starting at
 // opposing corners 'overlap'.
+//
+// If `max_edit_distance` is provided and the shortest edit script is found to be longer than it,
+// the search is abandoned and `None` is returned.
 fn find_middle_snake<T: PartialEq>(
     old: Range<'_, [T]>,
     new: Range<'_, [T]>,
     vf: &mut V,
     vb: &mut V,
-) -> (isize, Snake) {
+    max_edit_distance: Option<usize>,
+) -> Option<(isize, Snake)> {
     let n = old.len();
     let m = new.len();
 
@@ -101,7 +120,15 @@ fn find_middle_snake<T: PartialEq>(
     assert!(vf.len() >= d_max);
     assert!(vb.len() >= d_max);
 
+    let exceeds_limit =
+        |edit_distance: isize| max_edit_distance.is_some_and(|max| edit_distance > max as isize);
+
     for d in 0..d_max as isize {
+        // Any snake found in the forward pass of this iteration has an edit distance of `2 * d - 1`
+        if exceeds_limit(2 * d - 1) {
+            return None;
+        }
+
         // Forward path
         for k in (-d..=d).rev().step_by(2) {
             let mut x = if k == -d || (k != d && vf[k - 1] < vf[k + 1]) {
@@ -109,6 +136,12 @@ fn find_middle_snake<T: PartialEq>(
             } else {
                 vf[k - 1] + 1
             };
+            debug_assert!(
+                x as isize >= k,
+                "forward path at x = {} left diagonal {}",
+                x,
+                k
+            );
             let mut y = (x as isize - k) as usize;
 
             // The coordinate of the start of a snake
@@ -118,6 +151,10 @@ fn find_middle_snake<T: PartialEq>(
                 let advance = s1.common_prefix_len(s2);
                 x += advance;
                 y += advance;
+                debug_assert!(
+                    x <= n && y <= m,
+                    "forward snake ended outside of the edit graph"
+                );
             }
 
             // This is the new best x value
@@ -135,11 +172,16 @@ fn find_middle_snake<T: PartialEq>(
                         y_end: y,
                     };
                     // Edit distance to this snake is `2 * d - 1`
-                    return (2 * d - 1, snake);
+                    return Some((2 * d - 1, snake));
                 }
             }
         }
 
+        // Any snake found in the backward pass of this iteration has an edit distance of `2 * d`
+        if exceeds_limit(2 * d) {
+            return None;
+        }
+
         // Backward path
         for k in (-d..=d).rev().step_by(2) {
             let mut x = if k == -d || (k != d && vb[k - 1] < vb[k + 1]) {
@@ -147,6 +189,12 @@ fn find_middle_snake<T: PartialEq>(
             } else {
                 vb[k - 1] + 1
             };
+            debug_assert!(
+                x as isize >= k,
+                "backward path at x = {} left diagonal {}",
+                x,
+                k
+            );
             let mut y = (x as isize - k) as usize;
 
             // The coordinate of the start of a snake
@@ -155,6 +203,10 @@ fn find_middle_snake<T: PartialEq>(
                 let advance = old.slice(..n - x).common_suffix_len(new.slice(..m - y));
                 x += advance;
                 y += advance;
+                debug_assert!(
+                    x <= n && y <= m,
+                    "backward snake ended outside of the edit graph"
+                );
             }
 
             // This is the new best x value
@@ -171,7 +223,7 @@ fn find_middle_snake<T: PartialEq>(
                         y_end: m - y0,
                     };
                     // Edit distance to this snake is `2 * d`
-                    return (2 * d, snake);
+                    return Some((2 * d, snake));
                 }
             }
         }
@@ -182,13 +234,23 @@ fn find_middle_snake<T: PartialEq>(
     unreachable!("unable to find a middle snake");
 }
 
-fn conquer<'a, 'b, T: PartialEq>(
+// Returns `true` if `max_edit_distance` was exceeded and `fallback` was used to diff `old` and `new`
+// (minus any common prefix and suffix) instead.
+fn conquer<'a, 'b, T, F>(
     mut old: Range<'a, [T]>,
     mut new: Range<'b, [T]>,
     vf: &mut V,
     vb: &mut V,
     solution: &mut Vec<DiffRange<'a, 'b, [T]>>,
-) {
+    max_edit_distance: Option<usize>,
+    fallback: &mut F,
+) -> bool
+where
+    T: PartialEq,
+    F: FnMut(Range<'a, [T]>, Range<'b, [T]>, &mut Vec<DiffRange<'a, 'b, [T]>>),
+{
+    let mut truncated = false;
+
     // Check for common prefix
     let common_prefix_len = old.common_prefix_len(new);
     if common_prefix_len > 0 {
@@ -219,43 +281,346 @@ fn conquer<'a, 'b, T: PartialEq>(
     } else if new.is_empty() {
         // Deletes
         solution.push(DiffRange::Delete(old));
-    } else {
+    } else if let Some((_shortest_edit_script_len, snake)) =
+        find_middle_snake(old, new, vf, vb, max_edit_distance)
+    {
         // Divide & Conquer
-        let (_shortest_edit_script_len, snake) = find_middle_snake(old, new, vf, vb);
-
+        debug_assert!(
+            snake.x_start <= snake.x_end && snake.x_end <= old.len(),
+            "snake {} outside of old range of length {}",
+            snake,
+            old.len()
+        );
+        debug_assert!(
+            snake.y_start <= snake.y_end && snake.y_end <= new.len(),
+            "snake {} outside of new range of length {}",
+            snake,
+            new.len()
+        );
         let (old_a, old_b) = old.split_at(snake.x_start);
         let (new_a, new_b) = new.split_at(snake.y_start);
 
-        conquer(old_a, new_a, vf, vb, solution);
-        conquer(old_b, new_b, vf, vb, solution);
+        // The edit distance of each half is bounded by the edit distance of the whole so there's
+        // no need to check against the limit again
+        conquer(old_a, new_a, vf, vb, solution, None, fallback);
+        conquer(old_b, new_b, vf, vb, solution, None, fallback);
+    } else {
+        // The edit distance is larger than the limit
+        fallback(old, new, solution);
+        truncated = true;
     }
 
     if common_suffix_len > 0 {
         solution.push(common_suffix);
     }
+
+    truncated
 }
 
+#[allow(dead_code)]
 pub fn diff<'a, 'b, T: PartialEq>(old: &'a [T], new: &'b [T]) -> Vec<DiffRange<'a, 'b, [T]>> {
-    let old_recs = Range::new(old, ..);
-    let new_recs = Range::new(new, ..);
+    diff_with_limit(old, new, None).0
+}
+
+// Like `diff` but gives up on finding the shortest edit script when its length exceeds
+// `max_edit_distance`. The returned flag indicates whether the limit was hit.
+pub fn diff_with_limit<'a, 'b, T: PartialEq>(
+    old: &'a [T],
+    new: &'b [T],
+    max_edit_distance: Option<usize>,
+) -> (Vec<DiffRange<'a, 'b, [T]>>, bool) {
+    diff_range_with_limit(Range::new(old, ..), Range::new(new, ..), max_edit_distance)
+}
 
+// Like `diff_with_limit` but diffs sub-ranges of two slices, producing ranges into the full slices
+pub fn diff_range_with_limit<'a, 'b, T: PartialEq>(
+    old_recs: Range<'a, [T]>,
+    new_recs: Range<'b, [T]>,
+    max_edit_distance: Option<usize>,
+) -> (Vec<DiffRange<'a, 'b, [T]>>, bool) {
+    diff_range_with_fallback(old_recs, new_recs, max_edit_distance, replace)
+}
+
+// Like `diff_range_with_limit` but uses `fallback` to diff the region for which the limit was
+// exceeded, instead of replacing all of it
+pub fn diff_range_with_fallback<'a, 'b, T, F>(
+    old_recs: Range<'a, [T]>,
+    new_recs: Range<'b, [T]>,
+    max_edit_distance: Option<usize>,
+    mut fallback: F,
+) -> (Vec<DiffRange<'a, 'b, [T]>>, bool)
+where
+    T: PartialEq,
+    F: FnMut(Range<'a, [T]>, Range<'b, [T]>, &mut Vec<DiffRange<'a, 'b, [T]>>),
+{
     let mut solution = Vec::new();
 
     // The arrays that hold the 'best possible x values' in search from:
     // `vf`: top left to bottom right
     // `vb`: bottom right to top left
-    let max_d = max_d(old.len(), new.len());
+    let max_d = max_d(old_recs.len(), new_recs.len());
     let mut vf = V::new(max_d);
     let mut vb = V::new(max_d);
 
-    conquer(old_recs, new_recs, &mut vf, &mut vb, &mut solution);
+    let truncated = conquer(
+        old_recs,
+        new_recs,
+        &mut vf,
+        &mut vb,
+        &mut solution,
+        max_edit_distance,
+        &mut fallback,
+    );
+
+    (solution, truncated)
+}
+
+// The fallback used when the edit distance exceeds the limit: delete all of `old` and insert all
+// of `new`
+pub fn replace<'a, 'b, T>(
+    old: Range<'a, [T]>,
+    new: Range<'b, [T]>,
+    solution: &mut Vec<DiffRange<'a, 'b, [T]>>,
+) {
+    solution.push(DiffRange::Delete(old));
+    solution.push(DiffRange::Insert(new));
+}
+
+// The number of elements which were inserted or deleted in a solution
+pub fn edit_distance<T: ?Sized>(solution: &[DiffRange<'_, '_, T>]) -> usize {
+    solution
+        .iter()
+        .map(|diff| match diff {
+            DiffRange::Equal(..) => 0,
+            DiffRange::Delete(range) | DiffRange::Insert(range) => range.len(),
+        })
+        .sum()
+}
+
+// Diff the regions between anchors, pairs of indices `(old_i, new_j)` of elements which are known
+// to correspond to each other, using `diff_segment`. Every anchor becomes part of an `Equal` range
+// in the solution. Anchors which aren't in increasing order on both sides, are out of bounds or
+// refer to different elements are ignored.
+pub fn diff_anchored<'a, 'b, T: PartialEq>(
+    old: &'a [T],
+    new: &'b [T],
+    anchors: &[(usize, usize)],
+    mut diff_segment: impl FnMut(Range<'a, [T]>, Range<'b, [T]>) -> Vec<DiffRange<'a, 'b, [T]>>,
+) -> Vec<DiffRange<'a, 'b, [T]>> {
+    let old_recs = Range::new(old, ..);
+    let new_recs = Range::new(new, ..);
+
+    let mut solution: Vec<DiffRange<'a, 'b, [T]>> = Vec::new();
+    let (mut old_pos, mut new_pos) = (0, 0);
+    // The end of the last anchor, to merge anchors of consecutive elements
+    let mut last_anchor = None;
+
+    for &(i, j) in anchors {
+        let valid =
+            i >= old_pos && j >= new_pos && i < old.len() && j < new.len() && old[i] == new[j];
+        if !valid {
+            continue;
+        }
+
+        if last_anchor == Some((i, j)) {
+            if let Some(last) = solution.last_mut() {
+                last.grow_down(1);
+            }
+        } else {
+            solution.extend(diff_segment(
+                old_recs.slice(old_pos..i),
+                new_recs.slice(new_pos..j),
+            ));
+            solution.push(DiffRange::Equal(
+                old_recs.slice(i..i + 1),
+                new_recs.slice(j..j + 1),
+            ));
+        }
+        old_pos = i + 1;
+        new_pos = j + 1;
+        last_anchor = Some((old_pos, new_pos));
+    }
 
+    solution.extend(diff_segment(
+        old_recs.slice(old_pos..),
+        new_recs.slice(new_pos..),
+    ));
     solution
 }
 
+// Like `diff_with_limit` but specialized for the ids produced by `Classifier`. When every id
+// appears at most once in each of `old` and `new` the longest common subsequence is the longest
+// increasing subsequence of the positions in `new` of the ids in `old`, which can be found in
+// O(N log N) time instead of O(ND).
+#[cfg(test)]
+pub fn diff_ids_with_limit<'a, 'b>(
+    old: &'a [u64],
+    new: &'b [u64],
+    max_edit_distance: Option<usize>,
+) -> (Vec<DiffRange<'a, 'b, [u64]>>, bool) {
+    diff_ids_with_fallback(old, new, max_edit_distance, replace)
+}
+
+// Like `diff_ids_with_limit` but uses `fallback` when the limit is exceeded, see
+// `diff_range_with_fallback`
+pub fn diff_ids_with_fallback<'a, 'b, F>(
+    old: &'a [u64],
+    new: &'b [u64],
+    max_edit_distance: Option<usize>,
+    fallback: F,
+) -> (Vec<DiffRange<'a, 'b, [u64]>>, bool)
+where
+    F: FnMut(Range<'a, [u64]>, Range<'b, [u64]>, &mut Vec<DiffRange<'a, 'b, [u64]>>),
+{
+    match diff_unique(old, new) {
+        Some((solution, edit_distance))
+            if max_edit_distance.map_or(true, |max| edit_distance <= max) =>
+        {
+            (solution, false)
+        }
+        _ => diff_range_with_fallback(
+            Range::new(old, ..),
+            Range::new(new, ..),
+            max_edit_distance,
+            fallback,
+        ),
+    }
+}
+
+// Returns `None` if either `old` or `new` contain duplicate ids. Otherwise returns the solution
+// along with its edit distance.
+fn diff_unique<'a, 'b>(
+    old: &'a [u64],
+    new: &'b [u64],
+) -> Option<(Vec<DiffRange<'a, 'b, [u64]>>, usize)> {
+    fn all_unique(ids: &[u64]) -> bool {
+        let mut seen = HashSet::with_capacity(ids.len());
+        ids.iter().all(|id| seen.insert(id))
+    }
+
+    if !all_unique(old) || !all_unique(new) {
+        return None;
+    }
+
+    let new_positions: HashMap<u64, usize> =
+        new.iter().enumerate().map(|(j, &id)| (id, j)).collect();
+    // The `(old, new)` index pairs of the ids present in both, ordered by their index in `old`
+    let pairs: Vec<(usize, usize)> = old
+        .iter()
+        .enumerate()
+        .filter_map(|(i, id)| new_positions.get(id).map(|&j| (i, j)))
+        .collect();
+
+    // Patience sorting: `tails[len]` is the index into `pairs` of the smallest possible last
+    // element of an increasing subsequence of length `len + 1`
+    let mut tails: Vec<usize> = Vec::new();
+    let mut predecessors: Vec<Option<usize>> = vec![None; pairs.len()];
+    for (p, &(_, j)) in pairs.iter().enumerate() {
+        let len = tails.partition_point(|&t| pairs[t].1 < j);
+        predecessors[p] = len.checked_sub(1).map(|prev| tails[prev]);
+        if len == tails.len() {
+            tails.push(p);
+        } else {
+            tails[len] = p;
+        }
+    }
+
+    let mut matches = Vec::with_capacity(tails.len());
+    let mut next = tails.last().copied();
+    while let Some(p) = next {
+        matches.push(pairs[p]);
+        next = predecessors[p];
+    }
+    matches.reverse();
+
+    let old_recs = Range::new(old, ..);
+    let new_recs = Range::new(new, ..);
+    let mut solution = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    let mut matches = matches.into_iter().peekable();
+    while let Some((match_i, match_j)) = matches.next() {
+        if i < match_i {
+            solution.push(DiffRange::Delete(old_recs.slice(i..match_i)));
+        }
+        if j < match_j {
+            solution.push(DiffRange::Insert(new_recs.slice(j..match_j)));
+        }
+
+        // Extend the match over any directly following matches
+        let mut len = 1;
+        while matches
+            .next_if(|&(a, b)| a == match_i + len && b == match_j + len)
+            .is_some()
+        {
+            len += 1;
+        }
+        solution.push(DiffRange::Equal(
+            old_recs.slice(match_i..match_i + len),
+            new_recs.slice(match_j..match_j + len),
+        ));
+        i = match_i + len;
+        j = match_j + len;
+    }
+    if i < old.len() {
+        solution.push(DiffRange::Delete(old_recs.slice(i..)));
+    }
+    if j < new.len() {
+        solution.push(DiffRange::Insert(new_recs.slice(j..)));
+    }
+
+    let edit_distance = solution
+        .iter()
+        .map(|diff| match diff {
+            DiffRange::Equal(..) => 0,
+            DiffRange::Delete(range) | DiffRange::Insert(range) => range.len(),
+        })
+        .sum();
+
+    Some((solution, edit_distance))
+}
+
+// Diff two strings at the granularity of extended grapheme clusters. This ensures that a
+// user-perceived character (e.g. `e` followed by a combining accent) is never split between an
+// `Equal` and a `Delete`/`Insert`. The returned ranges are byte ranges into `old` and `new`.
+#[cfg(feature = "unicode-segmentation")]
+pub fn diff_unicode_graphemes<'a, 'b>(old: &'a str, new: &'b str) -> Vec<DiffRange<'a, 'b, str>> {
+    use unicode_segmentation::UnicodeSegmentation;
+
+    // Returns the graphemes of `text` along with the byte offset each one starts at, plus a
+    // trailing offset for the end of the text
+    fn graphemes(text: &str) -> (Vec<&str>, Vec<usize>) {
+        let (mut offsets, graphemes): (Vec<usize>, Vec<&str>) = text.grapheme_indices(true).unzip();
+        offsets.push(text.len());
+        (graphemes, offsets)
+    }
+
+    fn to_bytes<'t>(text: &'t str, offsets: &[usize], range: Range<'_, [&str]>) -> Range<'t, str> {
+        Range::new(
+            text,
+            offsets[range.offset()]..offsets[range.offset() + range.len()],
+        )
+    }
+
+    let (old_graphemes, old_offsets) = graphemes(old);
+    let (new_graphemes, new_offsets) = graphemes(new);
+
+    diff(&old_graphemes, &new_graphemes)
+        .into_iter()
+        .map(|diff_range| match diff_range {
+            DiffRange::Equal(range1, range2) => DiffRange::Equal(
+                to_bytes(old, &old_offsets, range1),
+                to_bytes(new, &new_offsets, range2),
+            ),
+            DiffRange::Delete(range) => DiffRange::Delete(to_bytes(old, &old_offsets, range)),
+            DiffRange::Insert(range) => DiffRange::Insert(to_bytes(new, &new_offsets, range)),
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::utils::Rng;
 
     #[test]
     fn test_find_middle_snake() {
@@ -264,6 +629,224 @@ mod tests {
         let max_d = max_d(a.len(), b.len());
         let mut vf = V::new(max_d);
         let mut vb = V::new(max_d);
-        find_middle_snake(a, b, &mut vf, &mut vb);
+        find_middle_snake(a, b, &mut vf, &mut vb, None).unwrap();
+    }
+
+    #[test]
+    fn test_max_edit_distance() {
+        let a = b"ABCABBA";
+        let b = b"CBABAC";
+
+        // The shortest edit script between these has a length of 5
+        let (solution, truncated) = diff_with_limit(&a[..], &b[..], Some(5));
+        assert!(!truncated);
+        assert_eq!(solution.len(), diff(&a[..], &b[..]).len());
+
+        let (solution, truncated) = diff_with_limit(&a[..], &b[..], Some(4));
+        assert!(truncated);
+        assert_eq!(solution.len(), 2);
+        assert!(matches!(solution[0], DiffRange::Delete(r) if r.len() == a.len()));
+        assert!(matches!(solution[1], DiffRange::Insert(r) if r.len() == b.len()));
+    }
+
+    #[test]
+    fn test_diff_anchored() {
+        let a = b"xabcx";
+        let b = b"abxcx";
+        let segment = |old, new| diff_range_with_limit(old, new, None).0;
+
+        // Without anchors this is an ordinary diff
+        let solution = diff_anchored(&a[..], &b[..], &[], segment);
+        assert_eq!(
+            edit_distance(&solution),
+            edit_distance(&diff(&a[..], &b[..]))
+        );
+
+        // The anchored elements are matched to each other, consecutive ones in a single range.
+        // Invalid anchors are ignored.
+        let anchors = [(0, 2), (3, 3), (4, 4), (1, 0), (2, 2), (9, 9)];
+        let solution = diff_anchored(&a[..], &b[..], &anchors, segment);
+        let equal: Vec<_> = solution
+            .iter()
+            .filter_map(|diff| match diff {
+                DiffRange::Equal(old, new) => Some((old.range(), new.range())),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(equal, [(0..1, 2..3), (3..5, 3..5)]);
+        assert_eq!(edit_distance(&solution), 4);
+    }
+
+    #[test]
+    fn test_diff_ids_with_limit() {
+        // Shuffled sequences of unique ids
+        let mut rng = Rng(12345);
+
+        for _ in 0..50 {
+            let old: Vec<u64> = (0..40).filter(|_| rng.next() % 4 != 0).collect();
+            let mut new: Vec<u64> = (0..40).filter(|_| rng.next() % 4 != 0).collect();
+            for _ in 0..3 {
+                let (a, b) = (
+                    rng.next() as usize % new.len(),
+                    rng.next() as usize % new.len(),
+                );
+                new.swap(a, b);
+            }
+
+            let (solution, truncated) = diff_ids_with_limit(&old, &new, None);
+            assert!(!truncated);
+            assert_eq!(
+                oracle::check(&old, &new, &solution),
+                oracle::check(&old, &new, &diff(&old[..], &new[..]))
+            );
+        }
+
+        // Duplicate ids use the regular algorithm
+        assert!(diff_unique(&[0, 1, 0], &[1, 0]).is_none());
+        assert!(diff_unique(&[0, 1], &[1, 1]).is_none());
+
+        // The limit is still respected
+        let (solution, truncated) = diff_ids_with_limit(&[0, 1, 2], &[2, 1, 0], Some(3));
+        assert!(truncated);
+        assert_eq!(solution.len(), 2);
+        let (_, truncated) = diff_ids_with_limit(&[0, 1, 2], &[2, 1, 0], Some(4));
+        assert!(!truncated);
+    }
+
+    #[cfg(feature = "unicode-segmentation")]
+    #[test]
+    fn test_diff_unicode_graphemes() {
+        // `e` with a combining acute accent vs `e` with a combining grave accent
+        let a = "cafe\u{301}!";
+        let b = "cafe\u{300}!";
+
+        let solution: Vec<_> = diff_unicode_graphemes(a, b)
+            .into_iter()
+            .map(|diff| match diff {
+                DiffRange::Equal(range, _) => ("=", range.as_slice()),
+                DiffRange::Delete(range) => ("-", range.as_slice()),
+                DiffRange::Insert(range) => ("+", range.as_slice()),
+            })
+            .collect();
+        assert_eq!(
+            solution,
+            [
+                ("=", "caf"),
+                ("+", "e\u{300}"),
+                ("-", "e\u{301}"),
+                ("=", "!"),
+            ]
+        );
+
+        // A byte based diff would happily split the grapheme
+        let bytes = diff(a.as_bytes(), b.as_bytes());
+        assert!(matches!(bytes[0], DiffRange::Equal(range, _) if range.len() == 5));
+
+        assert!(diff_unicode_graphemes("", "").is_empty());
+    }
+}
+
+// A correctness oracle for the diff algorithms in this module: replaying a solution against `old`
+// must produce exactly `new`
+#[cfg(test)]
+mod oracle {
+    use super::*;
+    use crate::utils::Rng;
+    use std::{cmp, fmt::Debug};
+
+    // Replay `solution`, checking that it covers `old` and `new` completely and in order, and
+    // return its edit distance
+    pub(super) fn check<T: PartialEq + Debug>(
+        old: &[T],
+        new: &[T],
+        solution: &[DiffRange<'_, '_, [T]>],
+    ) -> usize {
+        let mut result: Vec<&T> = Vec::new();
+        let (mut old_pos, mut new_pos) = (0, 0);
+        let mut edit_distance = 0;
+
+        for diff in solution {
+            match diff {
+                DiffRange::Equal(old_range, new_range) => {
+                    assert_eq!(old_range.offset(), old_pos);
+                    assert_eq!(new_range.offset(), new_pos);
+                    assert_eq!(old_range.as_slice(), new_range.as_slice());
+                    result.extend(old_range.as_slice());
+                    old_pos += old_range.len();
+                    new_pos += new_range.len();
+                }
+                DiffRange::Delete(range) => {
+                    assert_eq!(range.offset(), old_pos);
+                    old_pos += range.len();
+                    edit_distance += range.len();
+                }
+                DiffRange::Insert(range) => {
+                    assert_eq!(range.offset(), new_pos);
+                    result.extend(range.as_slice());
+                    new_pos += range.len();
+                    edit_distance += range.len();
+                }
+            }
+        }
+
+        assert_eq!(old_pos, old.len(), "solution doesn't cover all of old");
+        assert_eq!(result, new.iter().collect::<Vec<_>>());
+        edit_distance
+    }
+
+    // The length of the longest common subsequence, using the classic O(NM) dynamic program
+    fn lcs_len<T: PartialEq>(old: &[T], new: &[T]) -> usize {
+        let mut row = vec![0; new.len() + 1];
+        for a in old {
+            let mut diagonal = 0;
+            for (j, b) in new.iter().enumerate() {
+                let above = row[j + 1];
+                row[j + 1] = if a == b {
+                    diagonal + 1
+                } else {
+                    cmp::max(row[j], above)
+                };
+                diagonal = above;
+            }
+        }
+        row[new.len()]
+    }
+
+    #[test]
+    fn diff_matches_oracle() {
+        let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+        let mut next = move |bound: u64| rng.next() % bound;
+
+        for _ in 0..500 {
+            let old_len = next(30) as usize;
+            let new_len = next(30) as usize;
+            // Small alphabets produce many matches, large alphabets produce few
+            let alphabet = 1 + next(8);
+            let old: Vec<u64> = (0..old_len).map(|_| next(alphabet)).collect();
+            let new: Vec<u64> = (0..new_len).map(|_| next(alphabet)).collect();
+            let shortest = old.len() + new.len() - 2 * lcs_len(&old, &new);
+
+            assert_eq!(check(&old, &new, &diff(&old[..], &new[..])), shortest);
+
+            let (solution, _) = diff_ids_with_limit(&old, &new, None);
+            assert_eq!(check(&old, &new, &solution), shortest);
+
+            let limit = next(10) as usize;
+            let (solution, truncated) = diff_with_limit(&old[..], &new[..], Some(limit));
+            let edit_distance = check(&old, &new, &solution);
+            if !truncated {
+                assert_eq!(edit_distance, shortest);
+            }
+        }
+    }
+
+    #[test]
+    fn diff_edge_cases() {
+        let empty: &[u8] = &[];
+        check(empty, empty, &diff(empty, empty));
+        check(b"abc", empty, &diff(&b"abc"[..], empty));
+        check(empty, b"abc", &diff(empty, &b"abc"[..]));
+        check(b"abc", b"abc", &diff(&b"abc"[..], &b"abc"[..]));
+        check(b"abc", b"xyz", &diff(&b"abc"[..], &b"xyz"[..]));
     }
 }
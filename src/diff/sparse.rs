@@ -0,0 +1,202 @@
+//! Sparse diffing of large inputs with a small number of localized changes
+//!
+//! Before running Myers' algorithm, blocks of lines which appear exactly once in both inputs are
+//! located using a rolling hash and used as anchors. Only the regions between anchors are then
+//! diffed, which keeps the cost proportional to the size of the changed regions rather than the
+//! size of the whole input.
+//!
+//! The anchors are picked greedily in order, so the result isn't guaranteed to be a minimal diff
+//! when blocks of lines have been moved.
+
+use super::myers;
+use crate::range::{DiffRange, Range};
+use std::collections::{hash_map::Entry, HashMap};
+
+// The number of lines in each block used as an anchor
+const BLOCK_LEN: usize = 8;
+
+// The base of the polynomial rolling hash
+const BASE: u64 = 0x100_0000_01b3;
+
+pub fn diff<'a, 'b, F>(
+    old: &'a [u64],
+    new: &'b [u64],
+    max_edit_distance: Option<usize>,
+    mut fallback: F,
+) -> (Vec<DiffRange<'a, 'b, [u64]>>, bool)
+where
+    F: FnMut(Range<'a, [u64]>, Range<'b, [u64]>, &mut Vec<DiffRange<'a, 'b, [u64]>>),
+{
+    let old_recs = Range::new(old, ..);
+    let new_recs = Range::new(new, ..);
+
+    let mut solution = Vec::new();
+    let mut truncated = false;
+    let mut remaining = max_edit_distance;
+    let (mut old_pos, mut new_pos) = (0, 0);
+
+    for (old_start, new_start, len) in anchors(old, new) {
+        let (segment, segment_truncated) = myers::diff_range_with_fallback(
+            old_recs.slice(old_pos..old_start),
+            new_recs.slice(new_pos..new_start),
+            remaining,
+            &mut fallback,
+        );
+        truncated |= segment_truncated;
+        remaining = remaining.map(|max| max.saturating_sub(myers::edit_distance(&segment)));
+        solution.extend(segment);
+
+        solution.push(DiffRange::Equal(
+            old_recs.slice(old_start..old_start + len),
+            new_recs.slice(new_start..new_start + len),
+        ));
+        old_pos = old_start + len;
+        new_pos = new_start + len;
+    }
+
+    let (segment, segment_truncated) = myers::diff_range_with_fallback(
+        old_recs.slice(old_pos..),
+        new_recs.slice(new_pos..),
+        remaining,
+        fallback,
+    );
+    solution.extend(segment);
+
+    (solution, truncated | segment_truncated)
+}
+
+// Returns `(old_start, new_start, len)` for each region of identical lines found by matching
+// blocks, in increasing order and without overlapping
+fn anchors(old: &[u64], new: &[u64]) -> Vec<(usize, usize, usize)> {
+    let mut anchors = Vec::new();
+    if old.len() < BLOCK_LEN || new.len() < BLOCK_LEN {
+        return anchors;
+    }
+
+    // Hash the non-overlapping blocks of `old`, discarding any which aren't unique
+    let mut blocks: HashMap<u64, Option<usize>> = HashMap::new();
+    for start in (0..=old.len() - BLOCK_LEN).step_by(BLOCK_LEN) {
+        match blocks.entry(hash(&old[start..start + BLOCK_LEN])) {
+            Entry::Occupied(mut o) => {
+                o.insert(None);
+            }
+            Entry::Vacant(v) => {
+                v.insert(Some(start));
+            }
+        }
+    }
+
+    // The hash of every window of `BLOCK_LEN` lines in `new`
+    let high = (1..BLOCK_LEN).fold(1u64, |high, _| high.wrapping_mul(BASE));
+    let mut windows = Vec::with_capacity(new.len() - BLOCK_LEN + 1);
+    let mut h = hash(&new[..BLOCK_LEN]);
+    windows.push(h);
+    for j in 0..new.len() - BLOCK_LEN {
+        h = h
+            .wrapping_sub(new[j].wrapping_mul(high))
+            .wrapping_mul(BASE)
+            .wrapping_add(new[j + BLOCK_LEN]);
+        windows.push(h);
+    }
+
+    // Only blocks which are also unique in `new` can be used as anchors
+    let mut counts: HashMap<u64, usize> = HashMap::new();
+    for h in &windows {
+        if let Some(Some(_)) = blocks.get(h) {
+            *counts.entry(*h).or_default() += 1;
+        }
+    }
+
+    let mut old_end = 0;
+    let mut j = 0;
+    while j < windows.len() {
+        let h = windows[j];
+        match blocks.get(&h) {
+            Some(&Some(i))
+                if counts[&h] == 1
+                    && i >= old_end
+                    && old[i..i + BLOCK_LEN] == new[j..j + BLOCK_LEN] =>
+            {
+                // Extend the anchor over any following identical lines
+                let len = BLOCK_LEN
+                    + old[i + BLOCK_LEN..]
+                        .iter()
+                        .zip(&new[j + BLOCK_LEN..])
+                        .take_while(|(a, b)| a == b)
+                        .count();
+                anchors.push((i, j, len));
+                old_end = i + len;
+                j += len;
+            }
+            _ => j += 1,
+        }
+    }
+
+    anchors
+}
+
+fn hash(ids: &[u64]) -> u64 {
+    ids.iter()
+        .fold(0u64, |h, &id| h.wrapping_mul(BASE).wrapping_add(id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn apply(old: &[u64], solution: &[DiffRange<'_, '_, [u64]>]) -> Vec<u64> {
+        let mut result = Vec::new();
+        let mut pos = 0;
+        for diff in solution {
+            match diff {
+                DiffRange::Equal(old_range, new_range) => {
+                    assert_eq!(old_range.offset(), pos);
+                    assert_eq!(new_range.offset(), result.len());
+                    result.extend_from_slice(old_range.as_slice());
+                    pos += old_range.len();
+                }
+                DiffRange::Delete(range) => {
+                    assert_eq!(range.offset(), pos);
+                    pos += range.len();
+                }
+                DiffRange::Insert(range) => {
+                    assert_eq!(range.offset(), result.len());
+                    result.extend_from_slice(range.as_slice());
+                }
+            }
+        }
+        assert_eq!(pos, old.len());
+        result
+    }
+
+    #[test]
+    fn sparse_diff() {
+        // Every 10th line is the same, but blocks of 8 lines are still unique
+        let old: Vec<u64> = (0..1000)
+            .map(|i| if i % 10 == 0 { 5000 } else { i })
+            .collect();
+        let mut new = old.clone();
+        new[100] = 1000;
+        new.insert(500, 1001);
+        new.remove(900);
+
+        let (solution, truncated) = diff(&old, &new, None, myers::replace);
+        assert!(!truncated);
+        assert_eq!(apply(&old, &solution), new);
+        assert_eq!(myers::edit_distance(&solution), 4);
+
+        // Anchors cover everything outside of the changes
+        let anchored: usize = anchors(&old, &new).iter().map(|&(_, _, len)| len).sum();
+        assert!(anchored > 900);
+
+        // Small inputs don't have any anchors
+        let (solution, _) = diff(&[1, 2, 3], &[1, 3], None, myers::replace);
+        assert_eq!(apply(&[1, 2, 3], &solution), [1, 3]);
+
+        // The limit applies to the whole diff
+        let (_, truncated) = diff(&old, &new, Some(1), myers::replace);
+        assert!(truncated);
+        let (_, truncated) = diff(&old, &new, Some(4), myers::replace);
+        assert!(!truncated);
+    }
+}
@@ -1,9 +1,13 @@
 use super::*;
 use crate::{
-    apply::apply,
+    apply::{
+        apply, apply_bytes, apply_with_byte_offsets, ApplyError, HunkTrace, InMemoryApplyError,
+        OutputLine, VerifyError,
+    },
     diff::{Diff, DiffRange},
     patch::Patch,
     range::Range,
+    utils::Rng,
 };
 
 // Helper macros are based off of the ones used in [dissimilar](https://docs.rs/dissimilar)
@@ -323,6 +327,50 @@ fn test_compact() {
     );
 }
 
+#[test]
+fn compact_with_stats() {
+    let mut solution = diff_range_list![Equal("a"), Delete("b"), Insert("c")];
+    let stats = cleanup::compact_with_stats(&mut solution);
+    assert_eq!(stats.operations_merged, 0);
+    assert_eq!(stats.operations_shifted, 0);
+
+    let mut solution = diff_range_list![Delete("a"), Delete("b"), Delete("c")];
+    let stats = cleanup::compact_with_stats(&mut solution);
+    assert_diff_range!([Delete("abc")], solution);
+    assert_eq!(stats.operations_merged, 2);
+    assert_eq!(stats.operations_shifted, 0);
+
+    let mut solution = diff_range_list![
+        Equal("a"),
+        Delete("b"),
+        Equal("c"),
+        Delete("ac"),
+        Equal("x"),
+    ];
+    let stats = cleanup::compact_with_stats(&mut solution);
+    assert_diff_range!([Equal("a"), Delete("bca"), Equal("cx")], solution);
+    assert_eq!(stats.operations_merged, 1);
+    assert_eq!(stats.operations_shifted, 3);
+    assert!(stats.iterations >= stats.operations_merged + stats.operations_shifted);
+
+    // The statistics of diffing two texts
+    let original = "x\na\nb\na\nb\ny\n";
+    let modified = "x\na\nb\ny\n";
+    let mut opts = DiffOptions::new();
+    let (patch, stats) = opts.create_patch_with_compaction_stats(original, modified);
+    assert_eq!(patch, create_patch(original, modified));
+    assert_eq!(stats.operations_merged, 0);
+    assert_eq!(stats.operations_shifted, 2);
+
+    // Nothing is compacted when the diff is found in the cache
+    opts.set_diff_cache(std::collections::HashMap::<u64, Vec<crate::DiffOp>>::new());
+    let (_, stats) = opts.create_patch_with_compaction_stats(original, modified);
+    assert_ne!(stats, cleanup::CompactionStats::default());
+    let (cached, stats) = opts.create_patch_with_compaction_stats(original, modified);
+    assert_eq!(cached, patch);
+    assert_eq!(stats, cleanup::CompactionStats::default());
+}
+
 macro_rules! assert_patch {
     ($diff_options:expr, $old:ident, $new:ident, $expected:ident $(,)?) => {
         let patch = $diff_options.create_patch($old, $new);
@@ -330,7 +378,7 @@ macro_rules! assert_patch {
         assert_eq!(patch_str, $expected);
         assert_eq!(Patch::from_str($expected).unwrap(), patch);
         assert_eq!(Patch::from_str(&patch_str).unwrap(), patch);
-        assert_eq!(apply($old, &patch).unwrap(), $new);
+        patch.verify_against($old, $new).unwrap();
     };
     ($old:ident, $new:ident, $expected:ident $(,)?) => {
         assert_patch!(DiffOptions::default(), $old, $new, $expected);
@@ -571,7 +619,7 @@ void Chunk_copy(Chunk *src, size_t src_start, Chunk *dst, size_t dst_start, size
  }
 ";
     let git_patch = Patch::from_str(expected_git).unwrap();
-    assert_eq!(apply(original, &git_patch).unwrap(), a);
+    git_patch.verify_against(original, a).unwrap();
 
     let expected_diffy = "\
 --- original
@@ -601,3 +649,1453 @@ void Chunk_copy(Chunk *src, size_t src_start, Chunk *dst, size_t dst_start, size
 ";
     assert_patch!(original, a, expected_diffy);
 }
+
+#[test]
+fn hunk_label_generator() {
+    let original = "\
+fn one() {
+    1
+}
+
+fn two() {
+    2
+}
+";
+    let modified = "\
+fn one() {
+    1
+}
+
+fn two() {
+    3
+}
+";
+    let expected = "\
+--- original
++++ modified
+@@ -5,3 +5,3 @@ hunk 0: fn two() {
+ fn two() {
+-    2
++    3
+ }
+";
+
+    let mut opts = DiffOptions::default();
+    opts.set_context_len(1)
+        .set_hunk_label_generator(|idx, lines| {
+            lines
+                .iter()
+                .find(|line| line.starts_with("fn "))
+                .map(|line| format!("hunk {}: {}", idx, line.trim_end()))
+        });
+    assert_patch!(opts, original, modified, expected);
+
+    let mut opts = DiffOptions::default();
+    opts.set_hunk_label_generator(|_, _| None);
+    let patch = opts.create_patch(original, modified);
+    assert_eq!(patch.hunks()[0].function_context(), None);
+
+    // A label ends at its first line break, which would otherwise corrupt the hunk header
+    let mut opts = DiffOptions::default();
+    opts.set_context_len(1)
+        .set_hunk_label_generator(|_, lines| lines.first().map(|line| format!("{}rest\n", line)));
+    let patch = opts.create_patch(original, modified);
+    assert_eq!(patch.hunks()[0].function_context(), Some("fn two() {"));
+    assert_eq!(Patch::from_str(&patch.to_string()).unwrap(), patch);
+    opts.set_hunk_label_generator(|_, _| Some("label\r\n".to_owned()));
+    let patch = opts.create_patch(original, modified);
+    assert_eq!(patch.hunks()[0].function_context(), Some("label"));
+}
+
+#[test]
+fn max_edit_distance() {
+    let original = "same\nold 1\nold 2\nsame\n";
+    let modified = "same\nnew 1\nsame\nnew 2\n";
+
+    let mut opts = DiffOptions::default();
+    opts.set_max_edit_distance(Some(3));
+    let result = opts.create_patch_checked(original, modified);
+    assert!(result.is_truncated());
+
+    // The fallback keeps the common prefix but replaces everything else
+    let expected = "\
+--- original
++++ modified
+@@ -1,4 +1,4 @@
+ same
+-old 1
+-old 2
+-same
++new 1
++same
++new 2
+";
+    let patch = result.into_inner();
+    assert_eq!(patch.to_string(), expected);
+    patch.verify_against(original, modified).unwrap();
+
+    opts.set_max_edit_distance(Some(4));
+    let result = opts.create_patch_checked(original, modified);
+    assert!(!result.is_truncated());
+    assert_eq!(result.into_inner(), create_patch(original, modified));
+}
+
+#[test]
+fn verify_against() {
+    let original = "a\nb\n";
+    let modified = "a\nb\nc\n";
+    let patch = create_patch(original, modified);
+    patch.verify_against(original, modified).unwrap();
+
+    match patch.verify_against(original, "a\nb\n") {
+        Err(VerifyError::Mismatch {
+            line: 3,
+            expected: None,
+            actual: Some(actual),
+        }) => assert_eq!(actual, "c\n"),
+        e => panic!("unexpected result: {:?}", e),
+    }
+
+    assert!(matches!(
+        patch.verify_against("x\ny\n", modified),
+        Err(VerifyError::Apply(_))
+    ));
+}
+
+#[test]
+fn apply_error() {
+    let original = "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\n";
+    let modified = "A\nb\nc\nd\ne\nf\ng\nh\ni\nJ\n";
+    let patch = DiffOptions::default()
+        .set_context_len(1)
+        .create_patch(original, modified);
+    assert_eq!(patch.hunks().len(), 2);
+
+    let base_image = "a\nb\nc\nd\ne\nf\ng\nh\nx\ny\n";
+    let expected = ApplyError::HunkFailed {
+        hunk_index: 1,
+        expected_context: "i\nj\n".to_owned(),
+        actual_context: "x\ny\n".to_owned(),
+    };
+    assert_eq!(apply(base_image, &patch).unwrap_err(), expected);
+    assert_eq!(
+        apply_bytes(base_image.as_bytes(), &patch).unwrap_err(),
+        expected
+    );
+    assert_eq!(
+        expected.to_string(),
+        "error applying hunk #2: expected \"i\\nj\\n\", found \"x\\ny\\n\""
+    );
+
+    assert_eq!(
+        apply_bytes(original.as_bytes(), &patch).unwrap(),
+        modified.as_bytes()
+    );
+}
+
+#[test]
+fn apply_to_scratch() {
+    let modified = "a\nb\nc\n";
+    let patch = create_patch("", modified);
+    assert_eq!(patch.apply_to_scratch().unwrap(), modified);
+
+    // Context lines are ignored
+    let s = "\
+--- /dev/null
++++ b/file
+@@ -1,1 +1,3 @@
+ x
++a
++b
+@@ -5,1 +7,2 @@
+ y
++c
+";
+    let patch = Patch::from_str(s).unwrap();
+    assert_eq!(patch.apply_to_scratch().unwrap(), modified);
+
+    let patch = create_patch("a\nb\n", "a\nB\n");
+    assert_eq!(
+        patch.apply_to_scratch().unwrap_err(),
+        ApplyError::HunkFailed {
+            hunk_index: 0,
+            expected_context: "a\nb\n".to_owned(),
+            actual_context: String::new(),
+        }
+    );
+}
+
+#[test]
+fn diff_options_display() {
+    let mut opts = DiffOptions::default();
+    assert_eq!(
+        opts.to_string(),
+        "DiffOptions { context_len: 3, compact: true, max_edit_distance: unlimited, \
+         hunk_label_generator: none }"
+    );
+
+    opts.set_context_len(1)
+        .set_max_edit_distance(Some(100))
+        .set_compact(false)
+        .set_hunk_label_generator(|_, _| None);
+    assert_eq!(
+        opts.to_string(),
+        "DiffOptions { context_len: 1, compact: false, max_edit_distance: 100, \
+         hunk_label_generator: custom }"
+    );
+}
+
+#[test]
+fn invert_selection() {
+    let original = "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\n";
+    let modified = "A\nb\nc\nd\ne\nf\ng\nh\ni\nJ\n";
+    let full = create_patch(original, modified);
+    assert_eq!(full.hunks().len(), 2);
+
+    // Select only the second hunk
+    let selection = Patch::new("original", "modified", vec![full.hunks()[1].clone()]);
+    let complement = selection.invert_selection(original, modified).unwrap();
+
+    let expected = "\
+--- modified
++++ original
+@@ -1,4 +1,4 @@
+-A
++a
+ b
+ c
+ d
+";
+    assert_eq!(complement.to_string(), expected);
+    complement
+        .verify_against(modified, "a\nb\nc\nd\ne\nf\ng\nh\ni\nJ\n")
+        .unwrap();
+
+    // The complement of the full patch has nothing left to revert
+    let complement = full.invert_selection(original, modified).unwrap();
+    assert!(complement.hunks().is_empty());
+
+    assert!(full.invert_selection(modified, modified).is_err());
+}
+
+#[test]
+fn to_xml() {
+    let original = "one\ntwo\"\n";
+    let modified = "one\n\tthree";
+    let mut opts = DiffOptions::default();
+    opts.set_hunk_label_generator(|_, _| Some("<fn>".to_owned()));
+    let patch = opts.create_patch(original, modified);
+
+    let expected = r#"<?xml version="1.0" encoding="UTF-8"?>
+<patch original="original" modified="modified">
+  <hunk old_start="1" old_len="2" new_start="1" new_len="2" function_context="&lt;fn&gt;">
+    <context>one</context>
+    <delete>two&quot;</delete>
+    <insert no_newline_at_eof="true">&#9;three</insert>
+  </hunk>
+</patch>
+"#;
+    assert_eq!(patch.to_xml(), expected);
+}
+
+#[test]
+fn hamming_distance() {
+    assert_eq!(hamming_distance_lines("", ""), 0);
+    assert_eq!(hamming_distance_lines("a\nb\n", "a\nb\n"), 0);
+    assert_eq!(hamming_distance_lines("a\nb\n", "b\na\n"), 2);
+    // A missing newline at the end of the file is a difference
+    assert_eq!(hamming_distance_lines("a\nb\n", "a\nb"), 1);
+    assert_eq!(hamming_distance_lines("a\n", ""), usize::MAX);
+    assert_eq!(hamming_distance_lines("a\n", "a\nb\n"), usize::MAX);
+}
+
+#[test]
+fn diff_ignore_order() {
+    use crate::diff::diff_ignore_order;
+
+    let empty: [u32; 0] = [];
+    assert_eq!(diff_ignore_order(&empty, &empty), (vec![], vec![]));
+    assert_eq!(diff_ignore_order(&[3, 1, 2], &[2, 3, 1]), (vec![], vec![]));
+    assert_eq!(diff_ignore_order(&[1, 2], &empty), (vec![1, 2], vec![]));
+    assert_eq!(diff_ignore_order(&empty, &[2, 1]), (vec![], vec![1, 2]));
+
+    // Duplicates are counted
+    assert_eq!(
+        diff_ignore_order(&[5, 1, 1, 4, 1], &[1, 6, 4, 4]),
+        (vec![1, 1, 5], vec![4, 6])
+    );
+}
+
+#[test]
+fn create_patch_concurrent() {
+    assert!(super::create_patch_concurrent(&[]).is_empty());
+
+    let texts: Vec<(String, String)> = (0..50)
+        .map(|i| {
+            let original: String = (0..i).map(|j| format!("line {}\n", j)).collect();
+            let modified: String = (0..i)
+                .map(|j| format!("line {}\n", if j % 7 == 0 { j * 2 } else { j }))
+                .collect();
+            (original, modified)
+        })
+        .collect();
+    let pairs: Vec<(&str, &str)> = texts
+        .iter()
+        .map(|(original, modified)| (original.as_str(), modified.as_str()))
+        .collect();
+
+    let mut opts = DiffOptions::default();
+    opts.set_context_len(1);
+    let patches = opts.create_patch_concurrent(&pairs);
+    assert_eq!(patches.len(), pairs.len());
+    for (patch, (original, modified)) in patches.iter().zip(&pairs) {
+        assert_eq!(*patch, opts.create_patch(original, modified));
+    }
+}
+
+#[test]
+fn create_patch_relative() {
+    let original: String = (0..40).map(|i| format!("line {}\n", i)).collect();
+    let modified = original
+        .replace("line 10\n", "line ten\n")
+        .replace("line 14\n", "");
+
+    let opts = DiffOptions::default();
+    let full = opts.create_patch(&original, &modified);
+    let (patch, offset) = opts.create_patch_relative(&original, &modified);
+    assert_eq!(offset, 7);
+    assert_eq!(full.hunks().len(), patch.hunks().len());
+    for (full, relative) in full.hunks().iter().zip(patch.hunks()) {
+        assert_eq!(
+            full.old_range().start(),
+            relative.old_range().start() + offset
+        );
+        assert_eq!(
+            full.new_range().start(),
+            relative.new_range().start() + offset
+        );
+        assert_eq!(full.lines(), relative.lines());
+    }
+
+    // Identical texts produce an empty patch
+    let (patch, _) = opts.create_patch_relative(&original, &original);
+    assert!(patch.hunks().is_empty());
+
+    // Changes at the very start and end of the texts
+    let (patch, offset) = opts.create_patch_relative("a\nb\n", "b\nc\n");
+    assert_eq!(offset, 0);
+    assert_eq!(patch, opts.create_patch("a\nb\n", "b\nc\n"));
+}
+
+#[cfg(feature = "regex")]
+#[test]
+fn ignore_lines_matching() {
+    let original = "\
+// header
+a
+b
+c
+d
+e
+f
+g
+h
+// footer
+";
+    let modified = "\
+// HEADER
+a
+b
+c
+D
+e
+f
+g
+h
+// FOOTER
+";
+
+    let mut opts = DiffOptions::default();
+    opts.set_context_len(1);
+    assert_eq!(opts.create_patch(original, modified).hunks().len(), 3);
+
+    // Only the hunk containing a non-comment change is kept
+    let expected = "\
+--- original
++++ modified
+@@ -4,3 +4,3 @@
+ c
+-d
++D
+ e
+";
+    opts.set_ignore_lines_matching("^//").unwrap();
+    assert_eq!(opts.create_patch(original, modified).to_string(), expected);
+
+    // Hunks with other changes keep their matching lines too
+    opts.set_context_len(3);
+    let patch = opts.create_patch(original, modified);
+    assert_eq!(patch.hunks().len(), 1);
+    patch.verify_against(original, modified).unwrap();
+
+    assert!(opts.set_ignore_lines_matching("(").is_err());
+    assert!(opts.to_string().contains("ignore_lines_matching: \"^//\""));
+}
+
+#[test]
+fn diff_ratio_fast() {
+    use crate::diff::diff_ratio_fast;
+
+    assert_eq!(diff_ratio_fast("", ""), 1.0);
+    assert_eq!(diff_ratio_fast("", "abc"), 0.0);
+    assert_eq!(diff_ratio_fast("ab", "ab"), 1.0);
+    assert_eq!(diff_ratio_fast("ab", "abc"), 0.0);
+    // Multi-byte characters are handled as single characters
+    assert_eq!(diff_ratio_fast("äöüß", "äöüx"), 1.0 / 3.0);
+
+    // Set semantics ignore repetition
+    assert_eq!(diff_ratio_fast("abcabc", "abcabcabc"), 1.0);
+    assert_eq!(diff_ratio_fast("aaaa", "aaa"), 1.0);
+
+    // A single edit changes at most three trigrams
+    let original = "the quick brown fox jumps over the lazy dog";
+    let modified = "the quick brown fox jumps over the lazy cat";
+    let distinct = 41.0;
+    let k = 3.0;
+    let ratio = diff_ratio_fast(original, modified);
+    assert!(ratio >= (distinct - 3.0 * k) / (distinct + 3.0 * k));
+    assert!(ratio < 1.0);
+}
+
+#[test]
+fn reverse_apply() {
+    let original = "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\n";
+    let modified = "A\nb\nc\nd\ne\nf\ng\nh\nh2\ni\nj\n";
+    let patch = DiffOptions::default()
+        .set_context_len(1)
+        .create_patch(original, modified);
+    assert_eq!(patch.hunks().len(), 2);
+    assert_eq!(patch.reverse_apply(modified).unwrap(), original);
+
+    // Patches without context can still be reversed
+    let patch = DiffOptions::zero_context().create_patch(original, modified);
+    assert_eq!(patch.reverse_apply(modified).unwrap(), original);
+
+    // Applying to the original fails on the hunk's post-image
+    let expected = ApplyError::HunkFailed {
+        hunk_index: 0,
+        expected_context: "A\nb\n".to_owned(),
+        actual_context: "a\nb\n".to_owned(),
+    };
+    let patch = DiffOptions::default()
+        .set_context_len(1)
+        .create_patch(original, modified);
+    assert_eq!(patch.reverse_apply(original).unwrap_err(), expected);
+}
+
+#[cfg(feature = "unicode-segmentation")]
+#[test]
+fn graphemes() {
+    // A thumbs up with different skin tone modifiers shares its first `char`
+    let original = "ok \u{1f44d}\u{1f3fb} done";
+    let modified = "ok \u{1f44d}\u{1f3ff} done";
+    assert_eq!(
+        crate::diff_graphemes(original, modified),
+        [
+            Diff::Equal("ok "),
+            Diff::Insert("\u{1f44d}\u{1f3ff}"),
+            Diff::Delete("\u{1f44d}\u{1f3fb}"),
+            Diff::Equal(" done"),
+        ]
+    );
+
+    assert_eq!(crate::diff_graphemes("same", "same"), [Diff::Equal("same")]);
+    assert_eq!(
+        crate::diff_graphemes("", "a\u{301}"),
+        [Diff::Insert("a\u{301}")]
+    );
+}
+
+#[cfg(feature = "ropey")]
+#[test]
+fn apply_to_rope() {
+    let original = "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\n";
+    let modified = "A\nb\nc\nd\ne\nf\ng\nh\ni\nj\nk";
+    let patch = DiffOptions::default()
+        .set_context_len(1)
+        .create_patch(original, modified);
+    assert_eq!(patch.hunks().len(), 2);
+
+    let mut rope = ropey::Rope::from_str(original);
+    patch.apply_to_rope(&mut rope).unwrap();
+    assert_eq!(rope.to_string(), modified);
+
+    // Hunks are found even if the lines they apply to have moved
+    let mut rope = ropey::Rope::from_str(&format!("x\ny\n{}", original));
+    patch.apply_to_rope(&mut rope).unwrap();
+    assert_eq!(rope.to_string(), format!("x\ny\n{}", modified));
+
+    let base_image = "a\nb\nc\nd\ne\nf\ng\nh\nx\ny\n";
+    let mut rope = ropey::Rope::from_str(base_image);
+    assert_eq!(
+        patch.apply_to_rope(&mut rope).unwrap_err(),
+        apply(base_image, &patch).unwrap_err()
+    );
+}
+
+#[test]
+fn apply_byte_offsets() {
+    let original = "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\n";
+    let modified = "A\nb\nc\nd\ne\nf\ngg\nh\ni\nj";
+    let patch = DiffOptions::default()
+        .set_context_len(1)
+        .create_patch(original, modified);
+
+    let (result, offsets) = apply_with_byte_offsets(original, &patch).unwrap();
+    assert_eq!(result, modified);
+    let lines: Vec<&str> = offsets
+        .iter()
+        .map(|&(start, end)| &result[start..end])
+        .collect();
+    assert_eq!(
+        lines,
+        crate::utils::LineIter::new(modified).collect::<Vec<_>>()
+    );
+
+    let (result, offsets) = apply_with_byte_offsets("", &create_patch("", "")).unwrap();
+    assert!(result.is_empty());
+    assert!(offsets.is_empty());
+
+    assert_eq!(
+        apply_with_byte_offsets("x\n", &patch).unwrap_err(),
+        apply("x\n", &patch).unwrap_err()
+    );
+}
+
+#[test]
+fn sparse_diff() {
+    let original: String = (0..2000).map(|i| format!("line {}\n", i % 100)).collect();
+    let modified = original.replacen("line 10\n", "line ten\n", 3).replacen(
+        "line 50\nline 51\n",
+        "line 51\n",
+        20,
+    );
+
+    let mut opts = DiffOptions::default();
+    opts.set_sparse_diff(true);
+    let patch = opts.create_patch(&original, &modified);
+    patch.verify_against(&original, &modified).unwrap();
+    assert_eq!(
+        patch.to_string(),
+        create_patch(&original, &modified).to_string()
+    );
+    assert!(opts.to_string().contains("sparse_diff: true"));
+}
+
+#[test]
+fn from_diff_vec() {
+    let original = "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\n";
+    let modified = "A\nb\nc\nd\ne\nf\ng\nh\ni\nJ\n";
+    let old_lines: Vec<&str> = crate::utils::LineIter::new(original).collect();
+    let new_lines: Vec<&str> = crate::utils::LineIter::new(modified).collect();
+
+    let diffs = [
+        Diff::Delete("a\n"),
+        Diff::Insert("A\n"),
+        Diff::Equal("b\nc\nd\ne\nf\ng\nh\ni\n"),
+        Diff::Delete("j\n"),
+        Diff::Insert("J\n"),
+    ];
+    let patch = Patch::from_diff_vec(&old_lines, &new_lines, &diffs);
+    assert_eq!(patch, create_patch(original, modified));
+}
+
+#[test]
+#[should_panic(expected = "diff doesn't match line 2 of the original lines")]
+fn from_diff_vec_mismatch() {
+    let diffs = [Diff::Equal("a\nx\n")];
+    Patch::from_diff_vec(&["a\n", "b\n"], &["a\n", "b\n"], &diffs);
+}
+
+#[test]
+fn diff_cache() {
+    use crate::diff::{DiffCache, DiffOp};
+    use std::{
+        collections::HashMap,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc, Mutex,
+        },
+    };
+
+    // Counts the lookups which hit the cache
+    #[derive(Clone, Default)]
+    struct Counting {
+        entries: Arc<Mutex<HashMap<u64, Vec<DiffOp>>>>,
+        hits: Arc<AtomicUsize>,
+    }
+
+    impl DiffCache for Counting {
+        fn get(&self, key: u64) -> Option<Vec<DiffOp>> {
+            let value = self.entries.lock().unwrap().get(&key).cloned();
+            if value.is_some() {
+                self.hits.fetch_add(1, Ordering::SeqCst);
+            }
+            value
+        }
+
+        fn insert(&mut self, key: u64, value: Vec<DiffOp>) {
+            self.entries.lock().unwrap().insert(key, value);
+        }
+    }
+
+    // Returns the same entry for every key
+    struct Colliding;
+
+    impl DiffCache for Colliding {
+        fn get(&self, _key: u64) -> Option<Vec<DiffOp>> {
+            Some(vec![DiffOp::Equal(1), DiffOp::Delete(100)])
+        }
+
+        fn insert(&mut self, _key: u64, _value: Vec<DiffOp>) {}
+    }
+
+    let cache = Counting::default();
+    let hits = || cache.hits.load(Ordering::SeqCst);
+    let mut opts = DiffOptions::default();
+    opts.set_diff_cache(cache.clone());
+
+    let original = "a\nb\nc\nd\n";
+    let modified = "a\nc\nd\ne\n";
+    let patch = opts.create_patch(original, modified);
+    assert_eq!(hits(), 0);
+    assert_eq!(opts.create_patch(original, modified), patch);
+    assert_eq!(hits(), 1);
+
+    // Texts with the same structure share an entry
+    let patch = opts.create_patch("w\nx\ny\nz\n", "w\ny\nz\nv\n");
+    assert_eq!(hits(), 2);
+    patch
+        .verify_against("w\nx\ny\nz\n", "w\ny\nz\nv\n")
+        .unwrap();
+
+    // Changing the settings produces a different key
+    opts.set_sparse_diff(true);
+    opts.create_patch(original, modified);
+    assert_eq!(hits(), 2);
+    assert!(opts.to_string().contains("diff_cache: custom"));
+
+    // Entries which don't match the texts are ignored
+    let mut opts = DiffOptions::default();
+    let patch = opts.create_patch(original, modified);
+    opts.set_diff_cache(Colliding);
+    assert_eq!(opts.create_patch(original, modified), patch);
+}
+
+#[test]
+fn apply_with_rejects() {
+    let original = "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\nk\nl\n";
+    let modified = "A\nb\nc\nd\ne\nF\ng\nh\ni\nj\nk\nL\n";
+    let patch = DiffOptions::default()
+        .set_context_len(1)
+        .create_patch(original, modified);
+    assert_eq!(patch.hunks().len(), 3);
+
+    let result = patch.apply_with_rejects(original);
+    assert_eq!(result.applied, modified);
+    assert!(result.rejected.is_empty());
+
+    // The middle hunk fails, the others still apply
+    let base_image = "a\nb\nc\nd\nx\nf\ny\nh\ni\nj\nk\nl\n";
+    let result = patch.apply_with_rejects(base_image);
+    assert_eq!(result.applied, "A\nb\nc\nd\nx\nf\ny\nh\ni\nj\nk\nL\n");
+    assert_eq!(result.rejected, [(1, patch.hunks()[1].clone())]);
+    assert!(apply(base_image, &patch).is_err());
+}
+
+#[test]
+fn apply_in_memory_atomically() {
+    use std::collections::HashMap;
+
+    let mut files: HashMap<String, String> = HashMap::new();
+    files.insert("edit".to_owned(), "a\nb\n".to_owned());
+    files.insert("old name".to_owned(), "c\n".to_owned());
+    files.insert("delete".to_owned(), "d\n".to_owned());
+
+    let patches = [
+        Patch::new(
+            "edit",
+            "edit",
+            create_patch("a\nb\n", "a\nB\n").hunks().to_vec(),
+        ),
+        // A second patch to the same file applies to the result of the first
+        Patch::new(
+            "edit",
+            "edit",
+            create_patch("a\nB\n", "A\nB\n").hunks().to_vec(),
+        ),
+        Patch::new(
+            "old name",
+            "new name",
+            create_patch("c\n", "C\n").hunks().to_vec(),
+        ),
+        Patch::new(
+            "/dev/null",
+            "create",
+            create_patch("", "e\n").hunks().to_vec(),
+        ),
+        Patch::new(
+            "delete",
+            "/dev/null",
+            create_patch("d\n", "").hunks().to_vec(),
+        ),
+    ];
+
+    // Nothing changes unless every patch applies
+    let failing = Patch::new("edit", "edit", create_patch("x\n", "y\n").hunks().to_vec());
+    let all = [&patches[..], &[failing]].concat();
+    let before = files.clone();
+    assert!(Patch::apply_in_memory_atomically(&mut files, &all).is_err());
+    assert_eq!(files, before);
+
+    Patch::apply_in_memory_atomically(&mut files, &patches).unwrap();
+    let mut expected: HashMap<String, String> = HashMap::new();
+    expected.insert("edit".to_owned(), "A\nB\n".to_owned());
+    expected.insert("new name".to_owned(), "C\n".to_owned());
+    expected.insert("create".to_owned(), "e\n".to_owned());
+    assert_eq!(files, expected);
+
+    // Creating a file which exists and patching one which doesn't fail without changing anything
+    let before = files.clone();
+    let edit = Patch::new(
+        "edit",
+        "edit",
+        create_patch("A\nB\n", "a\nB\n").hunks().to_vec(),
+    );
+    let create = Patch::new(
+        "/dev/null",
+        "edit",
+        create_patch("", "x\n").hunks().to_vec(),
+    );
+    assert_eq!(
+        Patch::apply_in_memory_atomically(&mut files, &[edit, create]),
+        Err(InMemoryApplyError::FileExists {
+            patch_index: 1,
+            name: "edit".to_owned(),
+        })
+    );
+    let rename = Patch::new("create", "edit", Vec::new());
+    assert_eq!(
+        Patch::apply_in_memory_atomically(&mut files, &[rename]),
+        Err(InMemoryApplyError::FileExists {
+            patch_index: 0,
+            name: "edit".to_owned(),
+        })
+    );
+    let missing = Patch::new("missing", "missing", Vec::new());
+    assert_eq!(
+        Patch::apply_in_memory_atomically(&mut files, &[missing]),
+        Err(InMemoryApplyError::FileMissing {
+            patch_index: 0,
+            name: "missing".to_owned(),
+        })
+    );
+    // A deleted file is missing for the patches after it
+    let delete = Patch::new(
+        "create",
+        "/dev/null",
+        create_patch("e\n", "").hunks().to_vec(),
+    );
+    let result = Patch::apply_in_memory_atomically(&mut files, &[delete.clone(), delete]);
+    assert_eq!(
+        result,
+        Err(InMemoryApplyError::FileMissing {
+            patch_index: 1,
+            name: "create".to_owned(),
+        })
+    );
+    assert_eq!(files, before);
+
+    let failing = Patch::new("edit", "edit", create_patch("x\n", "y\n").hunks().to_vec());
+    match Patch::apply_in_memory_atomically(&mut files, &[failing]) {
+        Err(InMemoryApplyError::Apply { patch_index: 0, .. }) => {}
+        result => panic!("unexpected result {:?}", result),
+    }
+}
+
+#[test]
+fn blank_line_heuristic() {
+    let mut opts = DiffOptions::new();
+    opts.set_blank_line_heuristic(true);
+
+    // Deleted blocks are moved as well
+    let original = "# a\nx = 1\n\n# b\nx = 1\n\n# c\nx = 1\n";
+    let modified = "# a\nx = 1\n\n# c\nx = 1\n";
+    let patch = opts.create_patch(original, modified);
+    assert_eq!(
+        patch.hunks()[0].lines()[3..6],
+        [
+            Line::Delete("# b\n"),
+            Line::Delete("x = 1\n"),
+            Line::Delete("\n")
+        ]
+    );
+
+    // The diffs stay correct
+    let lines = ["a\n", "b\n", "\n", "}\n"];
+    let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+    for _ in 0..500 {
+        let original = rng.text(&lines, 8);
+        let modified = rng.text(&lines, 8);
+        let patch = opts.create_patch(&original, &modified);
+        assert_eq!(apply(&original, &patch).unwrap(), modified);
+        // Only the position of changes is affected, not their number
+        let changes = |patch: &Patch<'_>| {
+            patch
+                .hunks()
+                .iter()
+                .flat_map(|hunk| hunk.lines())
+                .filter(|line| !matches!(line, Line::Context(_)))
+                .count()
+        };
+        assert_eq!(
+            changes(&patch),
+            changes(&create_patch(&original, &modified))
+        );
+    }
+}
+
+#[test]
+fn create_patch_anchored() {
+    let original = "fn a() {\n    x\n}\n\nfn b() {\n    y\n}\n";
+    let modified = "fn b() {\n    y\n}\n\nfn a() {\n    x\n}\n";
+    let opts = DiffOptions::new();
+
+    // Anchor the first line of `a` so it is treated as having been moved down
+    let patch = opts.create_patch_anchored(original, modified, &[(0, 4)]);
+    assert_eq!(apply(original, &patch).unwrap(), modified);
+    let hunk = &patch.hunks()[0];
+    assert_eq!(hunk.lines()[4], Line::Context("fn a() {\n"));
+    assert!(hunk.lines()[..4]
+        .iter()
+        .all(|line| matches!(line, Line::Insert(_))));
+
+    // Without valid anchors the result is the same as an ordinary diff
+    assert_eq!(
+        opts.create_patch_anchored(original, modified, &[(1, 0), (50, 50)]),
+        opts.create_patch(original, modified)
+    );
+}
+
+#[test]
+fn trailing_context_only() {
+    let original = "1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n11\n12\n";
+    let modified = "1\n2\nthree\n4\n5\n6\n7\nseven\n8\n9\n10\nELEVEN\n12\n";
+
+    let mut opts = DiffOptions::new();
+    opts.set_context_len(2).set_trailing_context_only(true);
+    let patch = opts.create_patch(original, modified);
+    let expected = "\
+--- original
++++ modified
+@@ -3,3 +3,3 @@
+-3
++three
+ 4
+ 5
+@@ -8,2 +8,3 @@
++seven
+ 8
+ 9
+@@ -11,2 +12,2 @@
+-11
++ELEVEN
+ 12
+";
+    assert_eq!(patch.to_string(), expected);
+    assert_eq!(apply(original, &patch).unwrap(), modified);
+    assert_eq!(
+        opts.to_string(),
+        "DiffOptions { context_len: 2, compact: true, max_edit_distance: unlimited, \
+         trailing_context_only: true, hunk_label_generator: none }"
+    );
+
+    // Changes at the start of the text
+    let patch = opts.create_patch("a\nb\n", "b\n");
+    assert_eq!(
+        patch.to_string(),
+        "--- original\n+++ modified\n@@ -1,2 +1 @@\n-a\n b\n"
+    );
+}
+
+#[test]
+fn add_normalizer() {
+    let original = "x = 1  # one\ny = 2\nz = 3\n";
+    let modified = "x = 1 # the first\ny = 4\nz = 3  # three\n";
+
+    let mut opts = DiffOptions::new();
+    opts.add_normalizer(|line| match line.find('#') {
+        Some(idx) => Cow::Borrowed(&line[..idx]),
+        None => Cow::Borrowed(line),
+    })
+    .add_normalizer(|line| Cow::Owned(line.split_whitespace().collect::<Vec<_>>().join(" ")));
+
+    let expected = "\
+--- original
++++ modified
+@@ -1,3 +1,3 @@
+ x = 1 # the first
+-y = 2
++y = 4
+ z = 3  # three
+";
+    assert_eq!(opts.create_patch(original, modified).to_string(), expected);
+    assert!(opts.to_string().contains(", normalizers: 2"));
+
+    // Without normalizers every line differs
+    let patch = DiffOptions::new().create_patch(original, modified);
+    assert_eq!(patch.hunks()[0].lines().len(), 6);
+}
+
+#[test]
+fn equal_blank_lines() {
+    let original = "def a():\n    pass\n\n\ndef b():\n    pass\n";
+    let modified = "def a():\n    pass\n    \n\t\ndef b():\n    return\n";
+
+    let mut opts = DiffOptions::new();
+    opts.set_equal_blank_lines(true);
+    let expected = "\
+--- original
++++ modified
+@@ -3,4 +3,4 @@
+ \x20\x20\x20\x20
+ \t
+ def b():
+-    pass
++    return
+";
+    assert_eq!(opts.create_patch(original, modified).to_string(), expected);
+    assert!(opts.to_string().contains(", equal_blank_lines: true"));
+
+    // Blank lines still need to line up
+    let patch = opts.create_patch("a\n\nb\n", "a\nb\n\n");
+    assert_eq!(patch.hunks().len(), 1);
+}
+
+#[test]
+fn apply_line_by_line_callback() {
+    let lines = ["a\n", "b\n", "c\n", "d\n"];
+    let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+    for _ in 0..300 {
+        let original = rng.text(&lines, 12);
+        let modified = rng.text(&lines, 12);
+        let patch = DiffOptions::new()
+            .set_context_len(1)
+            .create_patch(&original, &modified);
+
+        // Apply the patch with some unrelated lines added at the start
+        let base = format!("x\ny\n{}", original);
+        let mut output = Vec::new();
+        Patch::apply_line_by_line_callback(&base, &patch, |line| output.push(line)).unwrap();
+
+        let result: String = output
+            .iter()
+            .filter_map(|line| match line {
+                OutputLine::Unchanged(_, l) | OutputLine::Added(l) => Some(*l),
+                OutputLine::Removed(..) => None,
+            })
+            .collect();
+        assert_eq!(result, apply(&base, &patch).unwrap());
+
+        let removed_or_kept: Vec<(usize, &str)> = output
+            .iter()
+            .filter_map(|line| match *line {
+                OutputLine::Unchanged(n, l) | OutputLine::Removed(n, l) => Some((n, l)),
+                OutputLine::Added(_) => None,
+            })
+            .collect();
+        let expected: Vec<(usize, &str)> = LineIter::new(base.as_str())
+            .enumerate()
+            .map(|(i, l)| (i + 1, l))
+            .collect();
+        assert_eq!(removed_or_kept, expected);
+    }
+
+    // Nothing is reported if the first hunk fails to apply
+    let patch = create_patch("a\nb\n", "a\nc\n");
+    let mut called = false;
+    let result = Patch::apply_line_by_line_callback("a\nd\n", &patch, |_| called = true);
+    assert!(result.is_err());
+    assert!(!called);
+
+    // Lines are reported as soon as they are settled, so the lines of the hunks before a failing
+    // one have already been passed to the callback
+    let patch = DiffOptions::zero_context().create_patch("a\nb\nc\nd\n", "A\nb\nc\nD\n");
+    let mut output = Vec::new();
+    let result =
+        Patch::apply_line_by_line_callback("a\nb\nc\nx\n", &patch, |line| output.push(line));
+    assert!(matches!(
+        result,
+        Err(ApplyError::HunkFailed { hunk_index: 1, .. })
+    ));
+    assert_eq!(
+        output,
+        [OutputLine::Removed(1, "a\n"), OutputLine::Added("A\n"),]
+    );
+}
+
+#[test]
+fn algorithm_config() {
+    let original = "a\nb\nc\nd\ne\nf\ng\nh\n";
+    let modified = "a\nx\nc\nd\ny\nf\nz\nh\n";
+
+    let mut opts = DiffOptions::new();
+    opts.set_algorithm_config(AlgorithmConfig {
+        max_d: Some(2),
+        fallback_algorithm: Algorithm::Histogram,
+        ..AlgorithmConfig::default()
+    });
+    let result = opts.create_patch_checked(original, modified);
+    assert!(result.is_truncated());
+    let patch = result.into_inner();
+    patch.verify_against(original, modified).unwrap();
+    // The histogram fallback finds the same diff as the full search
+    assert_eq!(patch, create_patch(original, modified));
+    assert_eq!(
+        opts.to_string(),
+        "DiffOptions { context_len: 3, compact: true, max_edit_distance: 2, \
+         fallback_algorithm: Histogram, hunk_label_generator: none }"
+    );
+
+    // The diagonal bound limits the edit distance to twice the bound
+    opts.set_algorithm_config(AlgorithmConfig {
+        diagonal_bound: Some(2),
+        ..AlgorithmConfig::default()
+    });
+    assert!(opts.create_patch_checked(original, modified).is_truncated());
+    opts.set_algorithm_config(AlgorithmConfig {
+        diagonal_bound: Some(3),
+        ..AlgorithmConfig::default()
+    });
+    assert!(!opts.create_patch_checked(original, modified).is_truncated());
+    // `set_max_edit_distance` only changes the limit
+    opts.set_max_edit_distance(Some(4));
+    assert!(opts.create_patch_checked(original, modified).is_truncated());
+    assert_eq!(
+        opts.to_string(),
+        "DiffOptions { context_len: 3, compact: true, max_edit_distance: 4, \
+         diagonal_bound: 3, hunk_label_generator: none }"
+    );
+
+    // Both fallbacks produce correct patches, also when diffing sparsely
+    let lines = ["a\n", "b\n", "c\n", "d\n", "e\n"];
+    let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+    for i in 0..200 {
+        let original = rng.text(&lines, 30);
+        let modified = rng.text(&lines, 30);
+        let mut opts = DiffOptions::new();
+        opts.set_sparse_diff(i % 2 == 0)
+            .set_algorithm_config(AlgorithmConfig {
+                max_d: Some(4),
+                fallback_algorithm: if i % 4 < 2 {
+                    Algorithm::Histogram
+                } else {
+                    Algorithm::Replace
+                },
+                histogram_frequency_threshold: Some(1 + i % 8),
+                ..AlgorithmConfig::default()
+            });
+        let patch = opts.create_patch(&original, &modified);
+        patch.verify_against(&original, &modified).unwrap();
+    }
+}
+
+#[test]
+fn histogram_fallback_limit() {
+    let changed_lines = |opts: &DiffOptions, original: &str, modified: &str| {
+        let patch = opts.create_patch_checked(original, modified);
+        assert!(patch.is_truncated());
+        let patch = patch.into_inner();
+        patch.verify_against(original, modified).unwrap();
+        patch
+            .hunks()
+            .iter()
+            .flat_map(|hunk| hunk.lines())
+            .filter(|line| !matches!(line, Line::Context(_)))
+            .count()
+    };
+    let original = "1\nx\n2\nx\n3\n";
+    let modified = "4\nx\n5\nx\n6\n";
+
+    let mut opts = DiffOptions::new();
+    opts.set_algorithm_config(AlgorithmConfig {
+        max_d: Some(0),
+        fallback_algorithm: Algorithm::Histogram,
+        ..AlgorithmConfig::default()
+    });
+    // By default the limit of a short file is 1, so `x` isn't used to match up the lines
+    assert_eq!(changed_lines(&opts, original, modified), 10);
+
+    // The default limit grows with the length of the file
+    let unchanged: String = (0..128).map(|i| format!("line {}\n", i)).collect();
+    assert_eq!(
+        changed_lines(
+            &opts,
+            &format!("{}{}", unchanged, original),
+            &format!("{}{}", unchanged, modified)
+        ),
+        6
+    );
+
+    opts.set_histogram_fallback_limit(2);
+    assert_eq!(changed_lines(&opts, original, modified), 6);
+    assert_eq!(
+        opts.to_string(),
+        "DiffOptions { context_len: 3, compact: true, max_edit_distance: 0, \
+         fallback_algorithm: Histogram, histogram_frequency_threshold: 2, \
+         hunk_label_generator: none }"
+    );
+}
+
+#[test]
+fn histogram_fallback_large_input() {
+    // Every pair of adjacent lines is swapped. Without bounding the depth of the recursion, and
+    // with each split peeling off a single pair, this used to take minutes.
+    let n = 40_000;
+    let original: String = (0..n).map(|i| format!("{}\n", i)).collect();
+    let modified: String = (0..n).map(|i| format!("{}\n", i ^ 1)).collect();
+
+    let mut opts = DiffOptions::new();
+    opts.set_algorithm_config(AlgorithmConfig {
+        max_d: Some(10),
+        fallback_algorithm: Algorithm::Histogram,
+        ..AlgorithmConfig::default()
+    });
+    let result = opts.create_patch_checked(&original, &modified);
+    assert!(result.is_truncated());
+    let patch = result.into_inner();
+    patch.verify_against(&original, &modified).unwrap();
+    // One line of each pair is kept
+    assert_eq!(patch.hunks().len(), 1);
+    assert_eq!(patch.hunks()[0].lines().len(), 3 * n / 2);
+}
+
+#[test]
+fn apply_with_undo_stack() {
+    let lines = ["a\n", "b\n", "c\n", "d\n"];
+    let mut rng = Rng(0x853c_49e6_748f_ea9b);
+    for _ in 0..300 {
+        let original = rng.text(&lines, 16);
+        let modified = rng.text(&lines, 16);
+        let patch = DiffOptions::new()
+            .set_context_len(1)
+            .create_patch(&original, &modified);
+
+        // Apply the patch with some unrelated lines added at the start
+        let base = format!("x\ny\n{}", original);
+        let (result, undo_stack) = Patch::apply_with_undo_stack(&base, &patch).unwrap();
+        assert_eq!(result, apply(&base, &patch).unwrap());
+
+        let mut image: Vec<String> = LineIter::new(result.as_str())
+            .map(ToOwned::to_owned)
+            .collect();
+        for op in undo_stack.iter().rev() {
+            assert!(op.remove_lines > 0 || !op.insert_lines.is_empty());
+            image.splice(
+                op.position..op.position + op.remove_lines,
+                op.insert_lines.iter().cloned(),
+            );
+        }
+        assert_eq!(image.concat(), base);
+    }
+
+    let patch = create_patch("a\nb\n", "a\nc\n");
+    assert!(Patch::apply_with_undo_stack("a\nd\n", &patch).is_err());
+}
+
+#[test]
+fn only_changed_lines() {
+    let original: String = (1..=12).map(|i| format!("{}\n", i)).collect();
+    let modified = original.replace("3\n", "three\n").replace("8\n", "");
+
+    // The default context merges both changes into a hunk with the surrounding lines
+    let patch = create_patch(&original, &modified);
+    assert_eq!(patch.hunks().len(), 1);
+    assert_eq!(patch.hunks()[0].lines().len(), 12);
+
+    // Without context, each change is a hunk of its own which only contains the changed lines
+    let mut opts = DiffOptions::new();
+    opts.set_hunk_label_generator(|idx, _| Some(format!("change {}", idx)));
+    let expected = "\
+--- original
++++ modified
+@@ -3 +3 @@ change 0
+-3
++three
+@@ -8 +7,0 @@ change 1
+-8
+";
+    let patch = opts.create_patch_only_changed_lines(&original, &modified);
+    assert_eq!(patch.to_string(), expected);
+    assert_eq!(
+        patch,
+        DiffOptions::zero_context()
+            .set_hunk_label_generator(|idx, _| Some(format!("change {}", idx)))
+            .create_patch(&original, &modified)
+    );
+    // The configured context is left alone
+    assert_eq!(opts.create_patch(&original, &modified).hunks().len(), 1);
+
+    // Such a patch only applies to the exact original text
+    assert_eq!(apply(&original, &patch).unwrap(), modified);
+}
+
+#[test]
+fn create_patch_from_edits() {
+    let original = [
+        "a\n", "b\n", "c\n", "d\n", "e\n", "f\n", "g\n", "h\n", "i\n",
+    ];
+    let edits: [(usize, usize, &[&str]); 4] = [
+        (0, 0, &["start\n"]),
+        (1, 2, &[]),
+        (3, 1, &["D\n", "DD\n"]),
+        (9, 0, &["end\n"]),
+    ];
+    let modified = "start\na\nD\nDD\ne\nf\ng\nh\ni\nend\n";
+
+    let patch = crate::create_patch_from_edits(&original, &edits).unwrap();
+    patch.verify_against(&original.concat(), modified).unwrap();
+    assert_eq!(patch.hunks().len(), 1);
+
+    let patch = DiffOptions::new()
+        .set_context_len(1)
+        .create_patch_from_edits(&original, &edits)
+        .unwrap();
+    patch.verify_against(&original.concat(), modified).unwrap();
+    assert_eq!(patch.hunks().len(), 2);
+
+    // Edits which don't change anything don't produce a hunk
+    let patch = crate::create_patch_from_edits(&original, &[(4, 0, &[])]).unwrap();
+    assert!(patch.hunks().is_empty());
+
+    // The options applied after diffing are used as well
+    let patch = DiffOptions::new()
+        .set_context_len(1)
+        .set_hunk_label_generator(|idx, _| Some(format!("hunk {}", idx)))
+        .create_patch_from_edits(&original, &edits)
+        .unwrap();
+    let labels: Vec<_> = patch
+        .hunks()
+        .iter()
+        .map(|hunk| hunk.function_context())
+        .collect();
+    assert_eq!(labels, [Some("hunk 0"), Some("hunk 1")]);
+}
+
+#[test]
+fn create_patch_from_edits_invalid() {
+    let original = ["a\n", "b\n", "c\n"];
+    assert_eq!(
+        crate::create_patch_from_edits(&original, &[(0, 2, &[]), (1, 1, &[])]),
+        Err(EditError::Overlapping { edit_index: 1 })
+    );
+    assert_eq!(
+        crate::create_patch_from_edits(&original, &[(2, 0, &[]), (1, 0, &["x\n"])]),
+        Err(EditError::Overlapping { edit_index: 1 })
+    );
+    assert_eq!(
+        crate::create_patch_from_edits(&original, &[(0, 0, &[]), (2, 2, &[])]),
+        Err(EditError::OutOfRange { edit_index: 1 })
+    );
+    assert_eq!(
+        crate::create_patch_from_edits(&original, &[(4, 0, &["x\n"])]),
+        Err(EditError::OutOfRange { edit_index: 0 })
+    );
+    assert_eq!(
+        crate::create_patch_from_edits(&original, &[(1, usize::MAX, &[])]),
+        Err(EditError::OutOfRange { edit_index: 0 })
+    );
+    assert_eq!(
+        EditError::Overlapping { edit_index: 1 }.to_string(),
+        "edit #2 overlaps or precedes the previous edit"
+    );
+}
+
+#[test]
+fn apply_fuzzy_scan() {
+    let original: String = (0..30).map(|i| format!("{}\n", i)).collect();
+    let modified = original
+        .replace("\n5\n", "\nfive\n")
+        .replace("\n25\n", "\n25!\n");
+    let patch = create_patch(&original, &modified);
+    assert_eq!(patch.hunks().len(), 2);
+
+    // Without any differences this is the same as `apply`
+    assert_eq!(
+        Patch::apply_fuzzy_scan(&original, &patch, 0, 0).unwrap(),
+        modified
+    );
+
+    // Both hunks moved down by 4 lines, which only needs to be found once
+    let base = format!("a\nb\nc\nd\n{}", original);
+    assert!(Patch::apply_fuzzy_scan(&base, &patch, 0, 3).is_err());
+    assert_eq!(
+        Patch::apply_fuzzy_scan(&base, &patch, 0, 4).unwrap(),
+        format!("a\nb\nc\nd\n{}", modified)
+    );
+
+    // Differing context lines are kept
+    let base = original
+        .replace("\n3\n", "\nthree\n")
+        .replace("\n27\n", "\ntwenty-seven\n");
+    assert!(Patch::apply_fuzzy_scan(&base, &patch, 0, 10).is_err());
+    assert_eq!(
+        Patch::apply_fuzzy_scan(&base, &patch, 1, 10).unwrap(),
+        modified
+            .replace("\n3\n", "\nthree\n")
+            .replace("\n27\n", "\ntwenty-seven\n")
+    );
+
+    // Deleted lines always have to match
+    let base = original.replace("\n5\n", "\nFIVE\n");
+    let err = Patch::apply_fuzzy_scan(&base, &patch, 6, 30).unwrap_err();
+    assert!(matches!(err, ApplyError::HunkFailed { hunk_index: 0, .. }));
+}
+
+#[test]
+fn apply_with_trace() {
+    let original: String = (0..20).map(|i| format!("{}\n", i)).collect();
+    let modified = original
+        .replace("\n3\n", "\nthree\n")
+        .replace("\n16\n", "\n");
+    let patch = create_patch(&original, &modified);
+    assert_eq!(patch.hunks().len(), 2);
+
+    // The second hunk moved down
+    let base = original.replace("\n12\n", "\n12\nx\ny\n");
+    let (result, trace) = Patch::apply_with_trace(&base, &patch);
+    assert_eq!(result.unwrap(), apply(&base, &patch).unwrap());
+    assert_eq!(
+        trace.hunks,
+        [
+            HunkTrace {
+                hunk_index: 0,
+                expected_line: 1,
+                matched_lines: 7,
+                applied_line: Some(1),
+            },
+            HunkTrace {
+                hunk_index: 1,
+                expected_line: 14,
+                matched_lines: 7,
+                applied_line: Some(16),
+            },
+        ]
+    );
+    assert_eq!(trace.hunks[1].offset(), Some(2));
+    assert_eq!(
+        trace.to_string(),
+        "hunk 1: applied at line 1, matching 7 lines\n\
+         hunk 2: applied at line 16 with offset +2, matching 7 lines\n"
+    );
+
+    // Nothing after the failing hunk is attempted
+    let base = original.replace("\n3\n", "\n");
+    let (result, trace) = Patch::apply_with_trace(&base, &patch);
+    assert!(matches!(
+        result,
+        Err(ApplyError::HunkFailed { hunk_index: 0, .. })
+    ));
+    assert_eq!(trace.hunks.len(), 1);
+    assert_eq!(trace.hunks[0].applied_line, None);
+    assert_eq!(trace.hunks[0].offset(), None);
+}
+
+#[test]
+fn output_strip_trailing_newline() {
+    let mut opts = DiffOptions::new();
+    opts.set_output_strip_trailing_newline(true);
+    assert!(opts.create_patch("a\nb\n", "a\nb").hunks().is_empty());
+    assert!(opts
+        .to_string()
+        .contains(", output_strip_trailing_newline: true"));
+
+    // The last line is included as it appears in the modified text
+    let expected = "\
+--- original
++++ modified
+@@ -1,3 +1,3 @@
+-a
++x
+ b
+ c
+";
+    assert_eq!(
+        opts.create_patch("a\nb\nc", "x\nb\nc\n").to_string(),
+        expected
+    );
+
+    // Only the last line is compared without its newline
+    let patch = opts.create_patch("a\nb", "a\nb\nb");
+    patch.verify_against("a\nb", "a\nb\nb").unwrap();
+    assert_eq!(
+        patch.to_string(),
+        "--- original\n+++ modified\n@@ -1,2 +1,3 @@\n a\n+b\n b\n\\ No newline at end of file\n"
+    );
+}
+
+#[test]
+fn create_patch_cached() {
+    use crate::{
+        diff::{create_patch_cached, PatchCache},
+        patch::PatchBuf,
+    };
+    use std::collections::HashMap;
+
+    let original = "a\nb\nc\nd\n";
+    let modified = "a\nc\nd\ne\n";
+    let mut cache = HashMap::<u128, PatchBuf>::new();
+    let patch = create_patch_cached(original, modified, &mut cache);
+    assert_eq!(patch, create_patch(original, modified));
+    assert_eq!(cache.len(), 1);
+    assert_eq!(create_patch_cached(original, modified, &mut cache), patch);
+    assert_eq!(cache.len(), 1);
+
+    // The order of the texts matters
+    let inverted = create_patch_cached(modified, original, &mut cache);
+    assert_eq!(inverted, create_patch(modified, original));
+    assert_eq!(cache.len(), 2);
+
+    // Returns the same entry for every key
+    struct Colliding(PatchBuf);
+
+    impl PatchCache for Colliding {
+        fn get(&self, _key: u128) -> Option<PatchBuf> {
+            Some(self.0.clone())
+        }
+
+        fn insert(&mut self, _key: u128, _patch: PatchBuf) {}
+    }
+
+    // A cached patch which doesn't match the texts is ignored
+    let mut cache = Colliding(PatchBuf::from(&patch));
+    let patch = create_patch_cached("w\nx\ny\nz\n", "w\ny\nz\nv\n", &mut cache);
+    assert_eq!(patch, create_patch("w\nx\ny\nz\n", "w\ny\nz\nv\n"));
+    let patch = create_patch_cached("a\nb\n", "a\n", &mut cache);
+    assert_eq!(patch, create_patch("a\nb\n", "a\n"));
+
+    // The hunk of the cached patch matches, but the texts also differ outside of it
+    let original: String = (0..20).map(|i| format!("{}\n", i)).collect();
+    let modified = original.replace("\n2\n", "\ntwo\n");
+    let changed_twice = modified.replace("\n15\n", "\nfifteen\n");
+    let mut cache = Colliding(PatchBuf::from(&create_patch(&original, &modified)));
+    let patch = create_patch_cached(&original, &changed_twice, &mut cache);
+    assert_eq!(patch.hunks().len(), 2);
+    assert_eq!(apply(&original, &patch).unwrap(), changed_twice);
+    // The texts only differ in length after the hunk
+    let longer = format!("{}20\n", modified);
+    let patch = create_patch_cached(&original, &longer, &mut cache);
+    assert_eq!(apply(&original, &patch).unwrap(), longer);
+    let patch = create_patch_cached(&original, &modified, &mut cache);
+    assert_eq!(patch, create_patch(&original, &modified));
+}
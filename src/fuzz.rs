@@ -0,0 +1,112 @@
+//! Entry points for fuzzing the crate's internals
+//!
+//! These are only compiled when building with `--cfg fuzzing`, which `cargo fuzz` does
+//! automatically, and aren't part of the public API.
+
+use crate::{
+    diff::cleanup,
+    range::{DiffRange, Range},
+};
+
+/// Build a list of `DiffRange`s from arbitrary bytes and run `cleanup::compact` on it.
+///
+/// Each pair of bytes describes one range: the first byte selects the kind of range (and the
+/// characters it contains) while the second selects its length. The characters are drawn from a
+/// small alphabet so that edits frequently have room to be shifted and merged.
+pub fn compact(data: &[u8]) {
+    let ops: Vec<(u8, usize)> = data
+        .chunks_exact(2)
+        .map(|pair| (pair[0], (pair[1] % 4) as usize + 1))
+        .collect();
+
+    let mut text1 = Vec::new();
+    let mut text2 = Vec::new();
+    for &(op, len) in &ops {
+        let content = (0..len).map(|j| b'a' + ((op >> 2) as usize + j) as u8 % 3);
+        match op % 3 {
+            0 => {
+                text1.extend(content.clone());
+                text2.extend(content);
+            }
+            1 => text1.extend(content),
+            _ => text2.extend(content),
+        }
+    }
+
+    let (mut offset1, mut offset2) = (0, 0);
+    let mut solution = Vec::new();
+    for &(op, len) in &ops {
+        let diff = match op % 3 {
+            0 => {
+                let diff = DiffRange::Equal(
+                    Range::new(&text1[..], offset1..offset1 + len),
+                    Range::new(&text2[..], offset2..offset2 + len),
+                );
+                offset1 += len;
+                offset2 += len;
+                diff
+            }
+            1 => {
+                let diff = DiffRange::Delete(Range::new(&text1[..], offset1..offset1 + len));
+                offset1 += len;
+                diff
+            }
+            _ => {
+                let diff = DiffRange::Insert(Range::new(&text2[..], offset2..offset2 + len));
+                offset2 += len;
+                diff
+            }
+        };
+        solution.push(diff);
+    }
+
+    let before = range_lengths(&solution);
+    cleanup::compact(&mut solution);
+    let after = range_lengths(&solution);
+
+    // Compaction only moves edits around, it never changes how much was changed
+    assert_eq!(before, after, "compaction changed the size of the diff");
+
+    // And the compacted diff must still describe the same two texts
+    let mut new1 = Vec::new();
+    let mut new2 = Vec::new();
+    for diff in &solution {
+        match diff {
+            DiffRange::Equal(range1, range2) => {
+                assert_eq!(range1.as_slice(), range2.as_slice());
+                new1.extend_from_slice(range1.as_slice());
+                new2.extend_from_slice(range2.as_slice());
+            }
+            DiffRange::Delete(range) => new1.extend_from_slice(range.as_slice()),
+            DiffRange::Insert(range) => new2.extend_from_slice(range.as_slice()),
+        }
+    }
+    assert_eq!(new1, text1);
+    assert_eq!(new2, text2);
+}
+
+// Returns the total lengths of the (Equal, Delete, Insert) ranges
+fn range_lengths(solution: &[DiffRange<'_, '_, [u8]>]) -> (usize, usize, usize) {
+    solution
+        .iter()
+        .fold((0, 0, 0), |(equal, delete, insert), diff| match diff {
+            DiffRange::Equal(..) => (equal + diff.len(), delete, insert),
+            DiffRange::Delete(_) => (equal, delete + diff.len(), insert),
+            DiffRange::Insert(_) => (equal, delete, insert + diff.len()),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::Rng;
+
+    #[test]
+    fn compact() {
+        // A cheap, deterministic stand-in for the fuzzer's input
+        let mut rng = Rng(0x2545_f491);
+        for len in 0..200 {
+            let data: Vec<u8> = (0..len).map(|_| rng.next() as u8).collect();
+            super::compact(&data);
+        }
+    }
+}
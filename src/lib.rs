@@ -205,12 +205,41 @@
 
 mod apply;
 mod diff;
+#[cfg(any(fuzzing, test))]
+#[doc(hidden)]
+pub mod fuzz;
+mod map;
 mod merge;
 mod patch;
 mod range;
+#[cfg(feature = "regex")]
+mod semantic;
+#[cfg(all(feature = "serde", feature = "json"))]
+mod structured;
+mod token;
 mod utils;
 
-pub use apply::{apply, ApplyError};
-pub use diff::{create_patch, DiffOptions};
+pub use apply::{
+    apply, apply_bytes, apply_with_byte_offsets, ApplyError, ApplyWithRejectsResult, HunkTrace,
+    InMemoryApplyError, OutputLine, TraceLog, UndoOp, VerifyError,
+};
+#[cfg(feature = "unicode-segmentation")]
+pub use diff::diff_graphemes;
+pub use diff::{
+    create_patch, create_patch_cached, create_patch_concurrent, create_patch_from_edits,
+    diff_ignore_order, diff_ratio_fast, hamming_distance_lines, Algorithm, AlgorithmConfig,
+    CompactionStats, Diff, DiffCache, DiffOp, DiffOptions, DiffResult, EditError, PatchCache,
+};
+pub use map::{create_patch_from_map, MapApplyError, MapChange, MapPatch};
 pub use merge::{merge, ConflictStyle, MergeOptions};
-pub use patch::{Hunk, HunkRange, Line, ParsePatchError, Patch, PatchFormatter};
+pub use patch::{
+    ContextBlock, EditBlock, Hunk, HunkRange, Line, ParsePatchError, Patch, PatchBuf,
+    PatchFormatter, RichTextFormat,
+};
+#[cfg(feature = "regex")]
+pub use semantic::{create_patch_semantic, SemanticChange, SemanticChangeKind, SemanticPatch};
+#[cfg(all(feature = "serde", feature = "json"))]
+pub use structured::{apply_patch_to_struct, create_patch_for_struct, StructApplyError};
+#[cfg(feature = "unicode-segmentation")]
+pub use token::GraphemeClassifier;
+pub use token::{CharClassifier, LineClassifier, TokenClassifier, WordClassifier};
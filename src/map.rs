@@ -0,0 +1,217 @@
+//! Diffing of maps by key
+
+use std::{collections::BTreeMap, fmt};
+
+/// The change of the value of a single key, see [`MapPatch`]
+///
+/// [`MapPatch`]: struct.MapPatch.html
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MapChange<V> {
+    /// The key was added with this value
+    Added(V),
+    /// The key was removed, it had this value
+    Removed(V),
+    /// The value of the key changed
+    Modified {
+        /// The value in the original map
+        old: V,
+        /// The value in the modified map
+        new: V,
+    },
+}
+
+/// The differences between two maps, see [`create_patch_from_map`]
+///
+/// [`create_patch_from_map`]: fn.create_patch_from_map.html
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MapPatch<K, V> {
+    changes: BTreeMap<K, MapChange<V>>,
+}
+
+impl<K, V> MapPatch<K, V> {
+    /// Returns the changed keys along with their changes, in key order
+    pub fn changes(&self) -> &BTreeMap<K, MapChange<V>> {
+        &self.changes
+    }
+
+    /// Returns whether the maps the patch was created from are equal
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+}
+
+/// Create a patch of the keys which were added, removed or whose values changed between two maps
+///
+/// Unlike [`create_patch_for_struct`], this works on the entries of the maps rather than on
+/// lines of text, so it doesn't need the values to be serializable and the patch records the
+/// values themselves. It can be applied with [`Patch::apply_to_b_tree_map`].
+///
+/// ```
+/// use diffy::{create_patch_from_map, MapChange};
+/// use std::collections::BTreeMap;
+///
+/// let original: BTreeMap<_, _> = [("name", "diffy"), ("version", "0.1.0")].into();
+/// let modified: BTreeMap<_, _> = [("name", "diffy"), ("version", "0.2.0")].into();
+///
+/// let patch = create_patch_from_map(&original, &modified);
+/// assert_eq!(patch.changes().len(), 1);
+/// assert_eq!(
+///     patch.changes()["version"],
+///     MapChange::Modified { old: "0.1.0", new: "0.2.0" }
+/// );
+/// ```
+///
+/// [`create_patch_for_struct`]: fn.create_patch_for_struct.html
+/// [`Patch::apply_to_b_tree_map`]: struct.Patch.html#method.apply_to_b_tree_map
+pub fn create_patch_from_map<K, V>(
+    original: &BTreeMap<K, V>,
+    modified: &BTreeMap<K, V>,
+) -> MapPatch<K, V>
+where
+    K: Ord + Clone,
+    V: Clone + PartialEq,
+{
+    let mut changes = BTreeMap::new();
+
+    for (key, old) in original {
+        match modified.get(key) {
+            Some(new) if new == old => {}
+            Some(new) => {
+                let change = MapChange::Modified {
+                    old: old.clone(),
+                    new: new.clone(),
+                };
+                changes.insert(key.clone(), change);
+            }
+            None => {
+                changes.insert(key.clone(), MapChange::Removed(old.clone()));
+            }
+        }
+    }
+    for (key, new) in modified {
+        if !original.contains_key(key) {
+            changes.insert(key.clone(), MapChange::Added(new.clone()));
+        }
+    }
+
+    MapPatch { changes }
+}
+
+/// An error returned when [`Patch::apply_to_b_tree_map`] fails
+///
+/// [`Patch::apply_to_b_tree_map`]: struct.Patch.html#method.apply_to_b_tree_map
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MapApplyError<K> {
+    /// The value of the key in the map isn't the one the patch expects, e.g. a key the patch
+    /// removes has a different value in the map
+    KeyConflict(K),
+}
+
+impl<K: fmt::Debug> fmt::Display for MapApplyError<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MapApplyError::KeyConflict(key) => {
+                write!(f, "conflicting value for key {:?}", key)
+            }
+        }
+    }
+}
+
+impl<K: fmt::Debug> std::error::Error for MapApplyError<K> {}
+
+pub(crate) fn apply_to_b_tree_map<K, V>(
+    map: &BTreeMap<K, V>,
+    patch: &MapPatch<K, V>,
+) -> Result<BTreeMap<K, V>, MapApplyError<K>>
+where
+    K: Ord + Clone,
+    V: Clone + PartialEq,
+{
+    let mut result = map.clone();
+
+    for (key, change) in &patch.changes {
+        let conflict = || MapApplyError::KeyConflict(key.clone());
+        match change {
+            MapChange::Added(new) => match result.get(key) {
+                Some(value) if value != new => return Err(conflict()),
+                _ => {
+                    result.insert(key.clone(), new.clone());
+                }
+            },
+            MapChange::Removed(old) => match result.get(key) {
+                Some(value) if value != old => return Err(conflict()),
+                _ => {
+                    result.remove(key);
+                }
+            },
+            MapChange::Modified { old, new } => match result.get(key) {
+                Some(value) if value == old || value == new => {
+                    result.insert(key.clone(), new.clone());
+                }
+                _ => return Err(conflict()),
+            },
+        }
+    }
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Patch;
+
+    #[test]
+    fn map_round_trip() {
+        let original: BTreeMap<&str, u32> = [("keep", 1), ("change", 2), ("remove", 3)].into();
+        let modified: BTreeMap<&str, u32> = [("keep", 1), ("change", 20), ("add", 4)].into();
+
+        let patch = create_patch_from_map(&original, &modified);
+        let changes: Vec<_> = patch.changes().iter().collect();
+        assert_eq!(
+            changes,
+            [
+                (&"add", &MapChange::Added(4)),
+                (&"change", &MapChange::Modified { old: 2, new: 20 }),
+                (&"remove", &MapChange::Removed(3)),
+            ]
+        );
+        assert_eq!(
+            Patch::apply_to_b_tree_map(&original, &patch),
+            Ok(modified.clone())
+        );
+        assert!(create_patch_from_map(&original, &original).is_empty());
+
+        // Applying a patch again leaves the map unchanged
+        assert_eq!(
+            Patch::apply_to_b_tree_map(&modified, &patch),
+            Ok(modified.clone())
+        );
+
+        // A removed or changed key whose value differs in the map is a conflict
+        let mut map = original.clone();
+        map.insert("remove", 30);
+        assert_eq!(
+            Patch::apply_to_b_tree_map(&map, &patch),
+            Err(MapApplyError::KeyConflict("remove"))
+        );
+        let mut map = original.clone();
+        map.insert("change", 7);
+        assert_eq!(
+            Patch::apply_to_b_tree_map(&map, &patch),
+            Err(MapApplyError::KeyConflict("change"))
+        );
+        let mut map = original.clone();
+        map.remove("change");
+        assert_eq!(
+            Patch::apply_to_b_tree_map(&map, &patch),
+            Err(MapApplyError::KeyConflict("change"))
+        );
+        let mut map = original;
+        map.insert("add", 5);
+        assert_eq!(
+            Patch::apply_to_b_tree_map(&map, &patch),
+            Err(MapApplyError::KeyConflict("add"))
+        );
+    }
+}
@@ -0,0 +1,175 @@
+//! Decompose the hunks of a Patch into blocks of context and edited lines
+
+use super::{Line, Patch};
+
+/// A run of consecutive context lines within a hunk
+///
+/// See [`Patch::split_context_from_edits`].
+///
+/// [`Patch::split_context_from_edits`]: struct.Patch.html#method.split_context_from_edits
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ContextBlock<'a> {
+    hunk_index: usize,
+    old_line: usize,
+    new_line: usize,
+    lines: &'a [Line<'a>],
+}
+
+impl<'a> ContextBlock<'a> {
+    /// Returns the zero-based index of the hunk containing this block
+    pub fn hunk_index(&self) -> usize {
+        self.hunk_index
+    }
+
+    /// Returns the line number (starting at 1) of the block's first line in the old file
+    pub fn old_line(&self) -> usize {
+        self.old_line
+    }
+
+    /// Returns the line number (starting at 1) of the block's first line in the new file
+    pub fn new_line(&self) -> usize {
+        self.new_line
+    }
+
+    /// Returns the lines in the block, which are all `Line::Context`
+    pub fn lines(&self) -> &'a [Line<'a>] {
+        self.lines
+    }
+
+    // The content of the lines in the block
+    pub(super) fn contents(&self) -> impl Iterator<Item = &'a str> + 'a {
+        self.lines.iter().map(|line| match line {
+            Line::Context(l) | Line::Delete(l) | Line::Insert(l) => *l,
+        })
+    }
+}
+
+/// A run of consecutive deleted and inserted lines within a hunk
+///
+/// See [`Patch::split_context_from_edits`].
+///
+/// [`Patch::split_context_from_edits`]: struct.Patch.html#method.split_context_from_edits
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EditBlock<'a> {
+    hunk_index: usize,
+    old_line: usize,
+    new_line: usize,
+    lines: &'a [Line<'a>],
+}
+
+impl<'a> EditBlock<'a> {
+    /// Returns the zero-based index of the hunk containing this block
+    pub fn hunk_index(&self) -> usize {
+        self.hunk_index
+    }
+
+    /// Returns the line number (starting at 1) in the old file of the block's first deleted line,
+    /// or of the line the block's lines are inserted before if nothing is deleted
+    pub fn old_line(&self) -> usize {
+        self.old_line
+    }
+
+    /// Returns the line number (starting at 1) in the new file of the block's first inserted line,
+    /// or of the line following the deleted lines if nothing is inserted
+    pub fn new_line(&self) -> usize {
+        self.new_line
+    }
+
+    /// Returns the lines in the block, which are all `Line::Delete` or `Line::Insert`
+    pub fn lines(&self) -> &'a [Line<'a>] {
+        self.lines
+    }
+
+    /// Returns the content of the deleted lines in the block
+    pub fn deleted(&self) -> impl Iterator<Item = &'a str> + 'a {
+        self.lines.iter().filter_map(|line| match line {
+            Line::Delete(l) => Some(*l),
+            _ => None,
+        })
+    }
+
+    /// Returns the content of the inserted lines in the block
+    pub fn inserted(&self) -> impl Iterator<Item = &'a str> + 'a {
+        self.lines.iter().filter_map(|line| match line {
+            Line::Insert(l) => Some(*l),
+            _ => None,
+        })
+    }
+}
+
+// A block of a hunk, either of context lines or of edited lines
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Block<'a> {
+    Context(ContextBlock<'a>),
+    Edit(EditBlock<'a>),
+}
+
+impl Block<'_> {
+    pub(super) fn hunk_index(&self) -> usize {
+        match self {
+            Block::Context(block) => block.hunk_index,
+            Block::Edit(block) => block.hunk_index,
+        }
+    }
+}
+
+pub fn split_context_from_edits<'a>(
+    patch: &'a Patch<'a>,
+) -> (Vec<ContextBlock<'a>>, Vec<EditBlock<'a>>) {
+    let mut context_blocks = Vec::new();
+    let mut edit_blocks = Vec::new();
+
+    for block in blocks(patch) {
+        match block {
+            Block::Context(block) => context_blocks.push(block),
+            Block::Edit(block) => edit_blocks.push(block),
+        }
+    }
+
+    (context_blocks, edit_blocks)
+}
+
+// Split the hunks of a patch into blocks, in the order in which they appear in the patch
+pub(super) fn blocks<'a>(patch: &'a Patch<'a>) -> Vec<Block<'a>> {
+    let mut blocks = Vec::new();
+
+    for (hunk_index, hunk) in patch.hunks.iter().enumerate() {
+        let mut old_line = hunk.old_range.position() + 1;
+        let mut new_line = hunk.new_range.position() + 1;
+        let mut lines = &hunk.lines[..];
+
+        while let Some(first) = lines.first() {
+            let is_context = matches!(first, Line::Context(_));
+            let len = lines
+                .iter()
+                .position(|line| matches!(line, Line::Context(_)) != is_context)
+                .unwrap_or(lines.len());
+            let (block, rest) = lines.split_at(len);
+
+            if is_context {
+                blocks.push(Block::Context(ContextBlock {
+                    hunk_index,
+                    old_line,
+                    new_line,
+                    lines: block,
+                }));
+                old_line += len;
+                new_line += len;
+            } else {
+                let block = EditBlock {
+                    hunk_index,
+                    old_line,
+                    new_line,
+                    lines: block,
+                };
+                old_line += block.deleted().count();
+                new_line += block.inserted().count();
+                blocks.push(Block::Edit(block));
+            }
+
+            lines = rest;
+        }
+    }
+
+    blocks
+}
@@ -0,0 +1,219 @@
+//! An owned copy of a Patch
+
+use super::{Filename, GitIndex, Hunk, HunkRange, Line, Patch};
+use crate::utils::LineIter;
+use std::{borrow::Cow, ops};
+
+/// An owned copy of a [`Patch`], which doesn't borrow from the texts it was created from
+///
+/// See [`create_patch_cached`] and [`PatchCache`]. With the `serde` feature, a `PatchBuf` can be
+/// serialized and deserialized in the form described by [`Patch::json_schema`].
+///
+/// ```
+/// use diffy::{create_patch, PatchBuf};
+///
+/// let buf = {
+///     let original = String::from("a\nb\n");
+///     let modified = String::from("a\nc\n");
+///     PatchBuf::from(&create_patch(&original, &modified))
+/// };
+/// assert_eq!(
+///     buf.as_patch().to_string(),
+///     "--- original\n+++ modified\n@@ -1,2 +1,2 @@\n a\n-b\n+c\n"
+/// );
+/// ```
+///
+/// [`Patch`]: struct.Patch.html
+/// [`create_patch_cached`]: fn.create_patch_cached.html
+/// [`PatchCache`]: trait.PatchCache.html
+/// [`Patch::json_schema`]: struct.Patch.html#method.json_schema
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PatchBuf {
+    original: String,
+    modified: String,
+    hunks: Vec<HunkBuf>,
+    mode_change: Option<(u32, u32)>,
+    rename: Option<(String, String)>,
+    git_index: Option<GitIndex<'static>>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct HunkBuf {
+    old_range: HunkRange,
+    new_range: HunkRange,
+    function_context: Option<String>,
+    lines: Vec<LineBuf>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum LineBuf {
+    Context(String),
+    Delete(String),
+    Insert(String),
+}
+
+impl PatchBuf {
+    /// Returns a `Patch` borrowing from this `PatchBuf`
+    pub fn as_patch(&self) -> Patch<'_> {
+        let hunks = self
+            .hunks
+            .iter()
+            .map(|hunk| Hunk {
+                old_range: hunk.old_range,
+                new_range: hunk.new_range,
+                function_context: hunk.function_context.as_deref().map(Cow::Borrowed),
+                lines: hunk
+                    .lines
+                    .iter()
+                    .map(|line| match line {
+                        LineBuf::Context(l) => Line::Context(l),
+                        LineBuf::Delete(l) => Line::Delete(l),
+                        LineBuf::Insert(l) => Line::Insert(l),
+                    })
+                    .collect(),
+            })
+            .collect();
+
+        Patch {
+            original: Filename(Cow::Borrowed(&self.original)),
+            modified: Filename(Cow::Borrowed(&self.modified)),
+            hunks,
+            mode_change: self.mode_change,
+            rename: self
+                .rename
+                .as_ref()
+                .map(|(from, to)| (Cow::Borrowed(&**from), Cow::Borrowed(&**to))),
+            git_index: self.git_index.clone(),
+        }
+    }
+
+    // Rebuild the patch with its lines borrowed from `original` and `modified`. Returns `None` if
+    // the patch doesn't describe the differences between the texts, i.e. if the lines of its hunks
+    // don't match those of the texts or if the texts differ anywhere outside of the hunks.
+    pub(crate) fn borrow_from<'a>(
+        &self,
+        original: &'a str,
+        modified: &'a str,
+    ) -> Option<Patch<'a>> {
+        let original_lines: Vec<&'a str> = LineIter::new(original).collect();
+        let modified_lines: Vec<&'a str> = LineIter::new(modified).collect();
+
+        // Whether the lines between the end of the previous hunk and the given positions are
+        // unchanged
+        let unchanged = |old: ops::Range<usize>, new: ops::Range<usize>| match (
+            original_lines.get(old),
+            modified_lines.get(new),
+        ) {
+            (Some(old), Some(new)) => old == new,
+            _ => false,
+        };
+
+        let mut hunks = Vec::with_capacity(self.hunks.len());
+        let (mut old_pos, mut new_pos) = (0, 0);
+        for hunk in &self.hunks {
+            let (old_start, new_start) = (hunk.old_range.position(), hunk.new_range.position());
+            if old_start < old_pos
+                || new_start < new_pos
+                || !unchanged(old_pos..old_start, new_pos..new_start)
+            {
+                return None;
+            }
+            old_pos = old_start;
+            new_pos = new_start;
+            let mut lines = Vec::with_capacity(hunk.lines.len());
+
+            for line in &hunk.lines {
+                let line = match line {
+                    LineBuf::Context(l) => {
+                        let old = *original_lines.get(old_pos)?;
+                        if old != l || modified_lines.get(new_pos)? != l {
+                            return None;
+                        }
+                        old_pos += 1;
+                        new_pos += 1;
+                        Line::Context(old)
+                    }
+                    LineBuf::Delete(l) => {
+                        let old = *original_lines.get(old_pos)?;
+                        if old != l {
+                            return None;
+                        }
+                        old_pos += 1;
+                        Line::Delete(old)
+                    }
+                    LineBuf::Insert(l) => {
+                        let new = *modified_lines.get(new_pos)?;
+                        if new != l {
+                            return None;
+                        }
+                        new_pos += 1;
+                        Line::Insert(new)
+                    }
+                };
+                lines.push(line);
+            }
+
+            hunks.push(Hunk {
+                old_range: hunk.old_range,
+                new_range: hunk.new_range,
+                function_context: hunk.function_context.clone().map(Cow::Owned),
+                lines,
+            });
+        }
+
+        if !unchanged(old_pos..original_lines.len(), new_pos..modified_lines.len()) {
+            return None;
+        }
+
+        Some(Patch {
+            original: Filename(Cow::Owned(self.original.clone())),
+            modified: Filename(Cow::Owned(self.modified.clone())),
+            hunks,
+            mode_change: self.mode_change,
+            rename: self
+                .rename
+                .as_ref()
+                .map(|(from, to)| (Cow::Owned(from.clone()), Cow::Owned(to.clone()))),
+            git_index: self.git_index.clone(),
+        })
+    }
+}
+
+impl From<&Patch<'_>> for PatchBuf {
+    fn from(patch: &Patch<'_>) -> Self {
+        let hunks = patch
+            .hunks
+            .iter()
+            .map(|hunk| HunkBuf {
+                old_range: hunk.old_range,
+                new_range: hunk.new_range,
+                function_context: hunk.function_context.as_deref().map(str::to_owned),
+                lines: hunk
+                    .lines
+                    .iter()
+                    .map(|line| match *line {
+                        Line::Context(l) => LineBuf::Context(l.to_owned()),
+                        Line::Delete(l) => LineBuf::Delete(l.to_owned()),
+                        Line::Insert(l) => LineBuf::Insert(l.to_owned()),
+                    })
+                    .collect(),
+            })
+            .collect();
+
+        Self {
+            original: patch.original.0.clone().into_owned(),
+            modified: patch.modified.0.clone().into_owned(),
+            hunks,
+            mode_change: patch.mode_change,
+            rename: patch
+                .rename
+                .as_ref()
+                .map(|(from, to)| (from.clone().into_owned(), to.clone().into_owned())),
+            git_index: patch.git_index.as_ref().map(|index| GitIndex {
+                old: Cow::Owned(index.old.clone().into_owned()),
+                new: Cow::Owned(index.new.clone().into_owned()),
+                mode: index.mode,
+            }),
+        }
+    }
+}
@@ -0,0 +1,87 @@
+//! Describe a Patch in English prose
+
+use super::{Line, Patch};
+use crate::utils::LineIter;
+
+pub fn describe(patch: &Patch<'_>, original: &str) -> String {
+    let original_lines: Vec<&str> = LineIter::new(original).collect();
+    let mut changes = Vec::new();
+
+    for hunk in &patch.hunks {
+        // The 1-based line number in the old file of the next line of the hunk
+        let mut old_line = hunk.old_range.position() + 1;
+        let mut lines = hunk.lines.iter().peekable();
+
+        while let Some(line) = lines.next() {
+            if let Line::Context(_) = line {
+                old_line += 1;
+                continue;
+            }
+
+            // Collect the whole block of changed lines
+            let start = old_line;
+            let (mut deleted, mut inserted) = (0, 0);
+            let mut line = Some(line);
+            while let Some(changed) = line {
+                match changed {
+                    Line::Delete(_) => deleted += 1,
+                    Line::Insert(_) => inserted += 1,
+                    Line::Context(_) => unreachable!(),
+                }
+                line = lines.next_if(|line| !matches!(line, Line::Context(_)));
+            }
+            old_line += deleted;
+
+            let mut change = match (deleted, inserted) {
+                (0, inserted) => format!("adds {} at line {}", plural(inserted), start),
+                (deleted, 0) => format!("removes {} at line {}", plural(deleted), start),
+                (deleted, inserted) if deleted == inserted => {
+                    format!("replaces {} starting at line {}", plural(deleted), start)
+                }
+                (deleted, inserted) => format!(
+                    "replaces {} with {} starting at line {}",
+                    plural(deleted),
+                    plural(inserted),
+                    start
+                ),
+            };
+
+            let function = hunk
+                .function_context()
+                .or_else(|| function_header(&original_lines, start));
+            if let Some(function) = function {
+                change.push_str(&format!(" in `{}`", function.trim()));
+            }
+
+            changes.push(change);
+        }
+    }
+
+    if changes.is_empty() {
+        return "No changes.".to_owned();
+    }
+
+    let mut description = changes.join(", ");
+    description[..1].make_ascii_uppercase();
+    description.push('.');
+    description
+}
+
+fn plural(n: usize) -> String {
+    if n == 1 {
+        "1 line".to_owned()
+    } else {
+        format!("{} lines", n)
+    }
+}
+
+// Find the closest line before `line` which looks like the start of a function, using the same
+// heuristic as GNU diff's `--show-function-line`: lines starting with a letter, `_` or `$`.
+fn function_header<'a>(lines: &[&'a str], line: usize) -> Option<&'a str> {
+    let end = line.saturating_sub(1).min(lines.len());
+    lines[..end]
+        .iter()
+        .rev()
+        .copied()
+        .find(|line| line.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_' || c == '$'))
+}
@@ -0,0 +1,109 @@
+//! Render a Patch like git's `diff-highlight` script
+
+use super::{
+    blocks::{blocks, Block},
+    format::{hunk_header, patch_header},
+    side_by_side::{char_diff, plain, Segment},
+    Patch, NO_NEWLINE_AT_EOF,
+};
+use crate::diff::DiffOptions;
+use ansi_term::Style;
+use std::fmt::Write;
+
+pub fn to_diff_highlight_format(patch: &Patch<'_>) -> String {
+    let opts = DiffOptions::default();
+    let highlight = Style::new().bold().underline();
+
+    // The header is printed the same way as by `Display`
+    let mut output = patch_header(patch);
+
+    let mut blocks = blocks(patch).into_iter().peekable();
+    for (hunk_index, hunk) in patch.hunks.iter().enumerate() {
+        output.push_str(&hunk_header(hunk));
+        output.push('\n');
+
+        while let Some(block) = blocks.next_if(|block| block.hunk_index() == hunk_index) {
+            let block = match block {
+                Block::Context(block) => {
+                    for line in block.contents() {
+                        if line == "\n" {
+                            output.push('\n');
+                        } else {
+                            push_line(&mut output, ' ', &plain(line), highlight);
+                        }
+                    }
+                    continue;
+                }
+                Block::Edit(block) => block,
+            };
+
+            // Highlight the nth deleted line against the nth inserted line, if they're similar
+            let mut deleted: Vec<_> = block.deleted().map(plain).collect();
+            let mut inserted: Vec<_> = block.inserted().map(plain).collect();
+            for (old, new) in deleted.iter_mut().zip(&mut inserted) {
+                let (old_segments, new_segments) = char_diff(&opts, &old[0].0, &new[0].0);
+                if is_similar(&old_segments, &new_segments) {
+                    *old = old_segments;
+                    *new = new_segments;
+                }
+            }
+            for segments in &deleted {
+                push_line(&mut output, '-', segments, highlight);
+            }
+            for segments in &inserted {
+                push_line(&mut output, '+', segments, highlight);
+            }
+        }
+    }
+
+    output
+}
+
+// Whether at least half of the characters of two lines are unchanged, not counting their common
+// indentation
+fn is_similar(old: &[Segment], new: &[Segment]) -> bool {
+    let count = |segments: &[Segment]| -> usize {
+        segments.iter().map(|(text, _)| text.chars().count()).sum()
+    };
+    let unchanged: usize = old
+        .iter()
+        .filter(|(_, highlighted)| !highlighted)
+        .map(|(text, _)| text.chars().count())
+        .sum();
+    // The first segment is the same in both lines if it's unchanged
+    let indent = match old.first() {
+        Some((text, false)) => text.chars().take_while(|c| c.is_whitespace()).count(),
+        _ => 0,
+    };
+
+    let unchanged = unchanged - indent;
+    let total = count(old) + count(new) - 2 * indent;
+    total > 0 && 4 * unchanged >= total
+}
+
+// Push a line of a hunk, keeping its line ending out of any highlighted segment
+fn push_line(output: &mut String, marker: char, segments: &[Segment], highlight: Style) {
+    output.push(marker);
+    let mut newline = false;
+    for (text, highlighted) in segments {
+        let text = match text.strip_suffix('\n') {
+            Some(text) => {
+                newline = true;
+                text
+            }
+            None => text,
+        };
+        if text.is_empty() {
+            continue;
+        }
+        if *highlighted {
+            let _ = write!(output, "{}", highlight.paint(text));
+        } else {
+            output.push_str(text);
+        }
+    }
+    output.push('\n');
+    if !newline {
+        let _ = writeln!(output, "{}", NO_NEWLINE_AT_EOF);
+    }
+}
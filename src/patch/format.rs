@@ -1,4 +1,4 @@
-use super::{Hunk, Line, Patch, NO_NEWLINE_AT_EOF};
+use super::{strip_path_prefix, Filename, Hunk, Line, Patch, NO_NEWLINE_AT_EOF};
 use ansi_term::{Color, Style};
 use std::fmt::{Display, Formatter, Result};
 
@@ -6,6 +6,11 @@ use std::fmt::{Display, Formatter, Result};
 #[derive(Debug)]
 pub struct PatchFormatter {
     with_color: bool,
+    path_strip_level: usize,
+
+    context_marker: char,
+    delete_marker: char,
+    insert_marker: char,
 
     context: Style,
     delete: Style,
@@ -20,6 +25,11 @@ impl PatchFormatter {
     pub fn new() -> Self {
         Self {
             with_color: false,
+            path_strip_level: 0,
+
+            context_marker: ' ',
+            delete_marker: '-',
+            insert_marker: '+',
 
             context: Style::new(),
             delete: Color::Red.normal(),
@@ -36,12 +46,45 @@ impl PatchFormatter {
         self
     }
 
+    /// Set the characters used to mark context, deleted and inserted lines
+    ///
+    /// The defaults of `' '`, `'-'` and `'+'` are the ones used by the Unified Format. Other
+    /// markers can be useful when displaying a patch to a user but the output won't be able to
+    /// be parsed as a `Patch`.
+    pub fn with_line_markers(mut self, context: char, delete: char, insert: char) -> Self {
+        self.context_marker = context;
+        self.delete_marker = delete;
+        self.insert_marker = insert;
+        self
+    }
+
+    /// Strip the first `levels` components from the path of both file names when printing a
+    /// patch, like the `-p` flag of `patch(1)`
+    ///
+    /// This only affects the output, see [`Patch::strip_path_prefix`] for details on how paths
+    /// are stripped.
+    ///
+    /// ```
+    /// use diffy::{Patch, PatchFormatter};
+    ///
+    /// let patch = Patch::from_str("--- a/src/foo.rs\n+++ b/src/foo.rs\n").unwrap();
+    /// let f = PatchFormatter::new().with_path_strip_level(1);
+    /// assert_eq!(f.fmt_patch(&patch).to_string(), "--- src/foo.rs\n+++ src/foo.rs\n");
+    /// ```
+    ///
+    /// [`Patch::strip_path_prefix`]: struct.Patch.html#method.strip_path_prefix
+    pub fn with_path_strip_level(mut self, levels: usize) -> Self {
+        self.path_strip_level = levels;
+        self
+    }
+
     /// Returns a `Display` impl which can be used to print a Patch
     pub fn fmt_patch<'a>(&'a self, patch: &'a Patch<'a>) -> impl Display + 'a {
         PatchDisplay { f: self, patch }
     }
 
-    fn fmt_hunk<'a>(&'a self, hunk: &'a Hunk<'a>) -> impl Display + 'a {
+    /// Returns a `Display` impl which can be used to print a single Hunk, including its header
+    pub fn fmt_hunk<'a>(&'a self, hunk: &'a Hunk<'a>) -> impl Display + 'a {
         HunkDisplay { f: self, hunk }
     }
 
@@ -61,13 +104,27 @@ struct PatchDisplay<'a> {
     patch: &'a Patch<'a>,
 }
 
-impl Display for PatchDisplay<'_> {
+impl<'a> Display for PatchDisplay<'a> {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result {
         if self.f.with_color {
             write!(f, "{}", self.f.patch_header.prefix())?;
         }
-        writeln!(f, "--- {}", self.patch.original)?;
-        writeln!(f, "+++ {}", self.patch.modified)?;
+        if let Some((old_mode, new_mode)) = self.patch.mode_change {
+            writeln!(f, "old mode {:06o}", old_mode)?;
+            writeln!(f, "new mode {:06o}", new_mode)?;
+        }
+        if let Some((from, to)) = &self.patch.rename {
+            writeln!(f, "rename from {}", from)?;
+            writeln!(f, "rename to {}", to)?;
+        }
+        if let Some(index) = &self.patch.git_index {
+            writeln!(f, "{}", index)?;
+        }
+        let strip = |name: &'a Filename<'a>| {
+            Filename(strip_path_prefix(name, self.f.path_strip_level).into())
+        };
+        writeln!(f, "--- {}", strip(&self.patch.original))?;
+        writeln!(f, "+++ {}", strip(&self.patch.modified))?;
         if self.f.with_color {
             write!(f, "{}", self.f.patch_header.suffix())?;
         }
@@ -95,12 +152,12 @@ impl Display for HunkDisplay<'_> {
             write!(f, "{}", self.f.hunk_header.suffix())?;
         }
 
-        if let Some(ctx) = self.hunk.function_context {
+        if let Some(ctx) = self.hunk.function_context() {
             write!(f, " ")?;
             if self.f.with_color {
                 write!(f, "{}", self.f.function_context.prefix())?;
             }
-            write!(f, " {}", ctx)?;
+            write!(f, "{}", ctx)?;
             if self.f.with_color {
                 write!(f, "{}", self.f.function_context.suffix())?;
             }
@@ -123,9 +180,9 @@ struct LineDisplay<'a> {
 impl Display for LineDisplay<'_> {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result {
         let (sign, line, style) = match self.line {
-            Line::Context(line) => (' ', line, self.f.context),
-            Line::Delete(line) => ('-', line, self.f.delete),
-            Line::Insert(line) => ('+', line, self.f.insert),
+            Line::Context(line) => (self.f.context_marker, line, self.f.context),
+            Line::Delete(line) => (self.f.delete_marker, line, self.f.delete),
+            Line::Insert(line) => (self.f.insert_marker, line, self.f.insert),
         };
 
         if self.f.with_color {
@@ -150,3 +207,26 @@ impl Display for LineDisplay<'_> {
         Ok(())
     }
 }
+
+// The header of a patch as printed by `Display`, for the formats which render hunks differently
+pub(super) fn patch_header(patch: &Patch<'_>) -> String {
+    let header = Patch {
+        original: patch.original.clone(),
+        modified: patch.modified.clone(),
+        hunks: Vec::new(),
+        mode_change: patch.mode_change,
+        rename: patch.rename.clone(),
+        git_index: patch.git_index.clone(),
+    };
+    header.to_string()
+}
+
+// The `@@ -l,s +l,s @@` line of a hunk followed by its function context, without a newline
+pub(super) fn hunk_header(hunk: &Hunk<'_>) -> String {
+    let mut header = format!("@@ -{} +{} @@", hunk.old_range, hunk.new_range);
+    if let Some(ctx) = hunk.function_context() {
+        header.push(' ');
+        header.push_str(ctx);
+    }
+    header
+}
@@ -0,0 +1,124 @@
+//! A JSON Schema describing the JSON form of a Patch
+
+use serde_json::{json, Value};
+
+pub fn json_schema() -> Value {
+    let range_start = |side: &str| {
+        json!({
+            "type": "integer",
+            "minimum": 0,
+            "description": format!(
+                "The line number (starting at 1) of the first line of the hunk in the {} file, \
+                 or the line before the hunk if it's empty",
+                side
+            ),
+        })
+    };
+    let range_len = |side: &str| {
+        json!({
+            "type": "integer",
+            "minimum": 0,
+            "description": format!("The number of lines of the hunk in the {} file", side),
+        })
+    };
+
+    json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "title": "Patch",
+        "description": "A collection of hunks describing the differences between two files",
+        "type": "object",
+        "properties": {
+            "original": {
+                "type": "string",
+                "description": "The name of the old file",
+            },
+            "modified": {
+                "type": "string",
+                "description": "The name of the new file",
+            },
+            "hunks": {
+                "type": "array",
+                "items": { "$ref": "#/$defs/hunk" },
+            },
+            "mode_change": {
+                "description": "The permissions of the file before and after the patch, from \
+                                git's extended headers",
+                "type": "object",
+                "properties": {
+                    "old": { "type": "integer", "minimum": 0 },
+                    "new": { "type": "integer", "minimum": 0 },
+                },
+                "required": ["old", "new"],
+                "additionalProperties": false,
+            },
+            "rename": {
+                "description": "The old and new name of a renamed file, from git's extended \
+                                headers",
+                "type": "object",
+                "properties": {
+                    "from": { "type": "string" },
+                    "to": { "type": "string" },
+                },
+                "required": ["from", "to"],
+                "additionalProperties": false,
+            },
+            "git_index": {
+                "description": "The object names of the file before and after the patch, and \
+                                its mode, from git's index header",
+                "type": "object",
+                "properties": {
+                    "old": { "type": "string" },
+                    "new": { "type": "string" },
+                    "mode": { "type": "integer", "minimum": 0 },
+                },
+                "required": ["old", "new"],
+                "additionalProperties": false,
+            },
+        },
+        "required": ["original", "modified", "hunks"],
+        "additionalProperties": false,
+        "$defs": {
+            "hunk": {
+                "description": "A contiguous run of changes along with their context",
+                "type": "object",
+                "properties": {
+                    "old_start": range_start("old"),
+                    "old_len": range_len("old"),
+                    "new_start": range_start("new"),
+                    "new_len": range_len("new"),
+                    "function_context": {
+                        "type": "string",
+                        "description": "The text following the range information in the hunk \
+                                        header, usually the enclosing function",
+                    },
+                    "lines": {
+                        "type": "array",
+                        "items": { "$ref": "#/$defs/line" },
+                        "minItems": 1,
+                    },
+                },
+                "required": ["old_start", "old_len", "new_start", "new_len", "lines"],
+                "additionalProperties": false,
+            },
+            "line": {
+                "description": "A single line of a hunk",
+                "type": "object",
+                "properties": {
+                    "kind": {
+                        "enum": ["context", "delete", "insert"],
+                        "description": "Whether the line is present in both files, only in the \
+                                        old file or only in the new file",
+                    },
+                    "content": {
+                        "type": "string",
+                        "description": "The content of the line including its line ending, \
+                                        which is absent for the last line of a file without a \
+                                        trailing newline",
+                    },
+                },
+                "required": ["kind", "content"],
+                "additionalProperties": false,
+            },
+        },
+    })
+}
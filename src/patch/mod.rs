@@ -1,10 +1,42 @@
+mod blocks;
+mod buf;
+mod describe;
+mod diff_highlight;
 mod format;
+#[cfg(feature = "json")]
+mod json_schema;
 mod parse;
+mod rich_text;
+mod sanitize;
+#[cfg(feature = "serde")]
+mod schema;
+mod side_by_side;
+mod slack;
+mod template;
+mod word_diff;
+mod xml;
 
+#[cfg(test)]
+mod tests;
+
+pub use blocks::{ContextBlock, EditBlock};
+pub use buf::PatchBuf;
 pub use format::PatchFormatter;
 pub use parse::ParsePatchError;
+pub use rich_text::RichTextFormat;
 
-use std::{borrow::Cow, fmt, ops};
+use crate::{
+    apply::{self, ApplyError, ApplyWithRejectsResult, InMemoryApplyError, VerifyError},
+    diff::{Diff, DiffOptions},
+    map::{self, MapApplyError, MapPatch},
+    utils::LineIter,
+};
+use std::{
+    borrow::Cow,
+    cmp,
+    collections::{BTreeMap, HashMap},
+    fmt, ops,
+};
 
 const NO_NEWLINE_AT_EOF: &str = "\\ No newline at end of file";
 
@@ -14,6 +46,29 @@ pub struct Patch<'a> {
     original: Filename<'a>,
     modified: Filename<'a>,
     hunks: Vec<Hunk<'a>>,
+    // The permissions of the file before and after the patch, from git's extended headers
+    mode_change: Option<(u32, u32)>,
+    // The old and new name of the file, from git's `rename from` and `rename to` headers
+    rename: Option<(Cow<'a, str>, Cow<'a, str>)>,
+    // The object names of the file before and after the patch, from git's `index` header
+    git_index: Option<GitIndex<'a>>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct GitIndex<'a> {
+    old: Cow<'a, str>,
+    new: Cow<'a, str>,
+    mode: Option<u32>,
+}
+
+impl fmt::Display for GitIndex<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "index {}..{}", self.old, self.new)?;
+        if let Some(mode) = self.mode {
+            write!(f, " {:06o}", mode)?;
+        }
+        Ok(())
+    }
 }
 
 impl<'a> Patch<'a> {
@@ -26,6 +81,9 @@ impl<'a> Patch<'a> {
             original: Filename(original.into()),
             modified: Filename(modified.into()),
             hunks,
+            mode_change: None,
+            rename: None,
+            git_index: None,
         }
     }
 
@@ -53,6 +111,41 @@ impl<'a> Patch<'a> {
         parse::parse(s)
     }
 
+    /// Build a `Patch` from a list of [`Diff`]s between `original_lines` and `modified_lines`
+    ///
+    /// This separates computing a diff from building a patch out of it, allowing the diff to be
+    /// post-processed in between. Each `Diff` must consist of whole lines, which must match the
+    /// next lines of `original_lines` (for `Equal` and `Delete`) and `modified_lines` (for `Equal`
+    /// and `Insert`). The patch uses the default context length of 3 lines.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `diffs` doesn't match `original_lines` and `modified_lines` or doesn't cover all
+    /// of their lines.
+    ///
+    /// ```
+    /// use diffy::{Diff, Patch};
+    ///
+    /// let original = ["a\n", "b\n", "c\n"];
+    /// let modified = ["a\n", "B\n", "C\n"];
+    /// let diffs = [Diff::Equal("a\n"), Diff::Delete("b\nc\n"), Diff::Insert("B\nC\n")];
+    ///
+    /// let patch = Patch::from_diff_vec(&original, &modified, &diffs);
+    /// assert_eq!(
+    ///     patch.to_string(),
+    ///     "--- original\n+++ modified\n@@ -1,3 +1,3 @@\n a\n-b\n-c\n+B\n+C\n"
+    /// );
+    /// ```
+    ///
+    /// [`Diff`]: enum.Diff.html
+    pub fn from_diff_vec(
+        original_lines: &[&'a str],
+        modified_lines: &[&'a str],
+        diffs: &[Diff<'_, str>],
+    ) -> Patch<'a> {
+        DiffOptions::default().create_patch_from_diffs(original_lines, modified_lines, diffs)
+    }
+
     /// Return the name of the old file
     pub fn original(&self) -> &str {
         &self.original
@@ -64,9 +157,1337 @@ impl<'a> Patch<'a> {
     }
 
     /// Returns the hunks in the patch
-    pub fn hunks(&self) -> &[Hunk<'_>] {
+    pub fn hunks(&self) -> &[Hunk<'a>] {
         &self.hunks
     }
+
+    /// Returns the number of lines in the original file, as far as can be told from the patch
+    ///
+    /// This is where the hunk reaching furthest into the file, including its context, ends. If
+    /// no hunk reaches the end of the file, which is usually the case, the file has more lines
+    /// than that, so the result is only a lower bound. Returns `0` for a patch without hunks.
+    ///
+    /// ```
+    /// use diffy::create_patch;
+    ///
+    /// let original: String = (0..10).map(|i| format!("{}\n", i)).collect();
+    /// let modified = original.replace("\n4\n", "\nfour\n");
+    ///
+    /// let patch = create_patch(&original, &modified);
+    /// // The patch ends with the 3 lines of context after line 5
+    /// assert_eq!(patch.line_count_original(), 8);
+    /// assert_eq!(patch.line_count_modified(), 8);
+    /// ```
+    pub fn line_count_original(&self) -> usize {
+        line_count(self.hunks.iter().map(|hunk| hunk.old_range))
+    }
+
+    /// Returns the number of lines in the modified file, as far as can be told from the patch
+    ///
+    /// Like [`line_count_original`], this is only a lower bound.
+    ///
+    /// [`line_count_original`]: #method.line_count_original
+    pub fn line_count_modified(&self) -> usize {
+        line_count(self.hunks.iter().map(|hunk| hunk.new_range))
+    }
+
+    /// Returns the old and new name of the file if this patch renames it
+    ///
+    /// A patch parsed with git's `rename from` and `rename to` headers is a rename of the names
+    /// given by them. Otherwise a patch is only considered a rename if its header names look like
+    /// git's, i.e. start with `a/` and `b/`, and differ after stripping those prefixes, and if it
+    /// keeps at least half of the lines of the file as far as they are covered by its hunks. This
+    /// matches git's default similarity threshold for detecting renames, a patch which changes
+    /// most of a file describes a new file rather than a renamed one. The patch produced by
+    /// [`create_patch`] is never a rename since it uses the same names for both files.
+    ///
+    /// ```
+    /// use diffy::{create_patch, Patch};
+    ///
+    /// let patch = Patch::from_str("--- a/old.rs\n+++ b/new.rs\n").unwrap();
+    /// assert_eq!(patch.rename_from_to(), Some(("a/old.rs", "b/new.rs")));
+    ///
+    /// let s = "\
+    /// diff --git a/old.rs b/new.rs
+    /// similarity index 100%
+    /// rename from old.rs
+    /// rename to new.rs
+    /// ";
+    /// let patch = Patch::from_str(s).unwrap();
+    /// assert_eq!(patch.rename_from_to(), Some(("old.rs", "new.rs")));
+    ///
+    /// let patch = Patch::from_str("--- a/lib.rs\n+++ b/lib.rs\n").unwrap();
+    /// assert_eq!(patch.rename_from_to(), None);
+    ///
+    /// let patch = Patch::from_str("--- lib.rs.orig\n+++ lib.rs\n").unwrap();
+    /// assert_eq!(patch.rename_from_to(), None);
+    ///
+    /// let patch = create_patch("a\n", "b\n");
+    /// assert_eq!(patch.rename_from_to(), None);
+    /// ```
+    ///
+    /// [`create_patch`]: fn.create_patch.html
+    pub fn rename_from_to(&self) -> Option<(&str, &str)> {
+        if let Some((from, to)) = &self.rename {
+            return Some((from, to));
+        }
+
+        let (Some(original), Some(modified)) = (
+            self.original.strip_prefix("a/"),
+            self.modified.strip_prefix("b/"),
+        ) else {
+            return None;
+        };
+        let deleted = self
+            .hunks
+            .iter()
+            .flat_map(|hunk| &hunk.lines)
+            .filter(|line| matches!(line, Line::Delete(_)))
+            .count();
+        let kept = self.line_count_original() - deleted;
+
+        if original != modified && deleted <= kept {
+            Some((self.original(), self.modified()))
+        } else {
+            None
+        }
+    }
+
+    /// Returns whether this patch renames a file, see [`rename_from_to`]
+    ///
+    /// [`rename_from_to`]: #method.rename_from_to
+    pub fn is_rename(&self) -> bool {
+        self.rename_from_to().is_some()
+    }
+
+    /// Returns whether this patch only changes the permissions of a file
+    ///
+    /// This is the case for a patch without hunks which has git's `old mode` and `new mode`
+    /// headers with different modes, and which doesn't rename the file.
+    ///
+    /// ```
+    /// use diffy::Patch;
+    ///
+    /// let s = "\
+    /// diff --git a/run.sh b/run.sh
+    /// old mode 100644
+    /// new mode 100755
+    /// ";
+    /// let patch = Patch::from_str(s).unwrap();
+    /// assert!(patch.is_mode_change());
+    /// assert!(!patch.is_rename());
+    /// ```
+    pub fn is_mode_change(&self) -> bool {
+        matches!(self.mode_change, Some((old, new)) if old != new)
+            && self.hunks.is_empty()
+            && !self.is_rename()
+    }
+
+    /// Returns the modes of the file before and after the patch, from git's `old mode` and
+    /// `new mode` headers
+    ///
+    /// ```
+    /// use diffy::Patch;
+    ///
+    /// let s = "\
+    /// diff --git a/run.sh b/run.sh
+    /// old mode 100644
+    /// new mode 100755
+    /// ";
+    /// let patch = Patch::from_str(s).unwrap();
+    /// assert_eq!(patch.file_mode_change(), Some((0o100644, 0o100755)));
+    ///
+    /// let patch = Patch::from_str("--- a/run.sh\n+++ b/run.sh\n").unwrap();
+    /// assert_eq!(patch.file_mode_change(), None);
+    /// ```
+    pub fn file_mode_change(&self) -> Option<(u32, u32)> {
+        self.mode_change
+    }
+
+    /// Set the modes of the file before and after the patch
+    ///
+    /// The modes are printed as git's `old mode` and `new mode` headers, in octal, before the
+    /// file names when displaying the patch.
+    ///
+    /// ```
+    /// use diffy::create_patch;
+    ///
+    /// let mut patch = create_patch("a\n", "b\n");
+    /// patch.set_file_mode_change(0o100644, 0o100755);
+    /// assert_eq!(
+    ///     patch.to_string(),
+    ///     "\
+    /// old mode 100644
+    /// new mode 100755
+    /// --- original
+    /// +++ modified
+    /// @@ -1 +1 @@
+    /// -a
+    /// +b
+    /// "
+    /// );
+    /// ```
+    pub fn set_file_mode_change(&mut self, old_mode: u32, new_mode: u32) -> &mut Self {
+        self.mode_change = Some((old_mode, new_mode));
+        self
+    }
+
+    /// Format the `index <old-sha>..<new-sha> <mode>` extended header line used in git diffs
+    ///
+    /// The mode is written in octal, like git does. The returned line doesn't include a trailing
+    /// newline.
+    ///
+    /// ```
+    /// use diffy::Patch;
+    ///
+    /// assert_eq!(
+    ///     Patch::to_git_index_line("1a2b3c4", "5d6e7f8", 0o100644),
+    ///     "index 1a2b3c4..5d6e7f8 100644"
+    /// );
+    /// ```
+    pub fn to_git_index_line(old_sha: &str, new_sha: &str, mode: u32) -> String {
+        GitIndex {
+            old: old_sha.into(),
+            new: new_sha.into(),
+            mode: Some(mode),
+        }
+        .to_string()
+    }
+
+    /// Returns a copy of this patch with git's `index` header set to the given object names and
+    /// mode
+    ///
+    /// The header is printed before the file names when displaying the patch, see
+    /// [`to_git_index_line`].
+    ///
+    /// ```
+    /// use diffy::create_patch;
+    ///
+    /// let patch = create_patch("a\n", "b\n").with_git_index("1a2b3c4", "5d6e7f8", 0o100644);
+    /// assert_eq!(patch.git_index(), Some(("1a2b3c4", "5d6e7f8", Some(0o100644))));
+    /// assert_eq!(
+    ///     patch.to_string(),
+    ///     "\
+    /// index 1a2b3c4..5d6e7f8 100644
+    /// --- original
+    /// +++ modified
+    /// @@ -1 +1 @@
+    /// -a
+    /// +b
+    /// "
+    /// );
+    /// ```
+    ///
+    /// [`to_git_index_line`]: #method.to_git_index_line
+    pub fn with_git_index(&self, old_sha: &str, new_sha: &str, mode: u32) -> Patch<'a> {
+        let mut patch = self.clone();
+        patch.git_index = Some(GitIndex {
+            old: old_sha.to_owned().into(),
+            new: new_sha.to_owned().into(),
+            mode: Some(mode),
+        });
+        patch
+    }
+
+    /// Returns the object names of the file before and after the patch, along with its mode,
+    /// from git's `index` header
+    ///
+    /// Git only includes the mode if it isn't changed by the patch.
+    ///
+    /// ```
+    /// use diffy::Patch;
+    ///
+    /// let s = "\
+    /// diff --git a/lib.rs b/lib.rs
+    /// index 1a2b3c4..5d6e7f8 100644
+    /// --- a/lib.rs
+    /// +++ b/lib.rs
+    /// ";
+    /// let patch = Patch::from_str(s).unwrap();
+    /// assert_eq!(patch.git_index(), Some(("1a2b3c4", "5d6e7f8", Some(0o100644))));
+    /// ```
+    pub fn git_index(&self) -> Option<(&str, &str, Option<u32>)> {
+        self.git_index
+            .as_ref()
+            .map(|index| (&*index.old, &*index.new, index.mode))
+    }
+
+    /// Verify that applying this patch to `original` produces exactly `modified`
+    ///
+    /// ```
+    /// use diffy::{create_patch, VerifyError};
+    ///
+    /// let patch = create_patch("a\nb\nc\n", "a\nB\nc\n");
+    /// assert!(patch.verify_against("a\nb\nc\n", "a\nB\nc\n").is_ok());
+    ///
+    /// match patch.verify_against("a\nb\nc\n", "a\nB\nC\n") {
+    ///     Err(VerifyError::Mismatch { line, expected, actual }) => {
+    ///         assert_eq!(line, 3);
+    ///         assert_eq!(expected.as_deref(), Some("C\n"));
+    ///         assert_eq!(actual.as_deref(), Some("c\n"));
+    ///     }
+    ///     _ => panic!("expected a mismatch"),
+    /// }
+    /// ```
+    pub fn verify_against(&self, original: &str, modified: &str) -> Result<(), VerifyError> {
+        apply::verify(self, original, modified)
+    }
+
+    /// Apply as many hunks of this patch as possible to `original`, collecting those which fail
+    ///
+    /// Like `patch(1)`, hunks which can't be applied are skipped, leaving the original content
+    /// in their place, and returned along with their index so they can be written to a reject
+    /// file or resolved manually.
+    ///
+    /// ```
+    /// use diffy::DiffOptions;
+    ///
+    /// let original = "a\nb\nc\nd\ne\nf\ng\nh\n";
+    /// let modified = "A\nb\nc\nd\ne\nf\ng\nH\n";
+    /// let patch = DiffOptions::new().set_context_len(1).create_patch(original, modified);
+    ///
+    /// // The first hunk no longer applies
+    /// let result = patch.apply_with_rejects("x\ny\nc\nd\ne\nf\ng\nh\n");
+    /// assert_eq!(result.applied, "x\ny\nc\nd\ne\nf\ng\nH\n");
+    /// assert_eq!(result.rejected.len(), 1);
+    /// assert_eq!(result.rejected[0].0, 0);
+    /// ```
+    pub fn apply_with_rejects(&self, original: &str) -> ApplyWithRejectsResult<'a> {
+        apply::apply_with_rejects(original, self)
+    }
+
+    /// Apply a set of patches to files stored in memory, modifying the files only if every patch
+    /// applies
+    ///
+    /// `files` maps file names to their content. Each patch is applied to the file named by its
+    /// [`original`] name and the result is stored under its [`modified`] name, so renames are
+    /// supported. An original name of `/dev/null` creates a new file and a modified name of
+    /// `/dev/null` deletes the file. Creating or renaming a file to a name which already exists,
+    /// and patching a file which doesn't exist, are errors. The patches are applied in order, so
+    /// several of them can change the same file.
+    ///
+    /// The names are used as they are, so [`strip_path_prefix`] may be needed first to remove
+    /// prefixes like `a/` and `b/` from the paths in git style patches. If any patch fails to
+    /// apply an [`InMemoryApplyError`] is returned and `files` is left untouched.
+    ///
+    /// ```
+    /// use diffy::Patch;
+    /// use std::collections::HashMap;
+    ///
+    /// let mut files = HashMap::new();
+    /// files.insert("a.txt".to_owned(), "a\n".to_owned());
+    /// files.insert("b.txt".to_owned(), "b\n".to_owned());
+    ///
+    /// let patches = [
+    ///     Patch::from_str("--- a.txt\n+++ a.txt\n@@ -1 +1 @@\n-a\n+A\n").unwrap(),
+    ///     Patch::from_str("--- b.txt\n+++ b.txt\n@@ -1 +1 @@\n-x\n+B\n").unwrap(),
+    /// ];
+    ///
+    /// // The second patch fails, so nothing changes
+    /// assert!(Patch::apply_in_memory_atomically(&mut files, &patches).is_err());
+    /// assert_eq!(files["a.txt"], "a\n");
+    ///
+    /// Patch::apply_in_memory_atomically(&mut files, &patches[..1]).unwrap();
+    /// assert_eq!(files["a.txt"], "A\n");
+    /// ```
+    ///
+    /// [`original`]: struct.Patch.html#method.original
+    /// [`modified`]: struct.Patch.html#method.modified
+    /// [`strip_path_prefix`]: struct.Patch.html#method.strip_path_prefix
+    /// [`InMemoryApplyError`]: enum.InMemoryApplyError.html
+    pub fn apply_in_memory_atomically(
+        files: &mut HashMap<String, String>,
+        patches: &[Patch<'_>],
+    ) -> Result<(), InMemoryApplyError> {
+        apply::apply_in_memory_atomically(files, patches)
+    }
+
+    /// Apply a [`MapPatch`] created by [`create_patch_from_map`] to `map`
+    ///
+    /// Added keys are inserted, removed keys are removed and changed keys get their new value.
+    /// A key which the patch removes or changes but which has a different value in `map` than
+    /// the one the patch was created from is a conflict, as is an added key which exists with a
+    /// different value, and [`MapApplyError::KeyConflict`] is returned for the first such key.
+    /// Changes which are already present in `map` are accepted, so applying a patch twice gives
+    /// the same result as applying it once.
+    ///
+    /// ```
+    /// use diffy::{create_patch_from_map, MapApplyError, Patch};
+    /// use std::collections::BTreeMap;
+    ///
+    /// let original: BTreeMap<_, _> = [("a", 1), ("b", 2)].into();
+    /// let modified: BTreeMap<_, _> = [("a", 10)].into();
+    /// let patch = create_patch_from_map(&original, &modified);
+    ///
+    /// assert_eq!(Patch::apply_to_b_tree_map(&original, &patch), Ok(modified));
+    ///
+    /// // "b" was changed after the patch was created
+    /// let changed: BTreeMap<_, _> = [("a", 1), ("b", 3)].into();
+    /// assert_eq!(
+    ///     Patch::apply_to_b_tree_map(&changed, &patch),
+    ///     Err(MapApplyError::KeyConflict("b"))
+    /// );
+    /// ```
+    ///
+    /// [`MapPatch`]: struct.MapPatch.html
+    /// [`create_patch_from_map`]: fn.create_patch_from_map.html
+    /// [`MapApplyError::KeyConflict`]: enum.MapApplyError.html#variant.KeyConflict
+    pub fn apply_to_b_tree_map<K, V>(
+        map: &BTreeMap<K, V>,
+        patch: &MapPatch<K, V>,
+    ) -> Result<BTreeMap<K, V>, MapApplyError<K>>
+    where
+        K: Ord + Clone,
+        V: Clone + PartialEq,
+    {
+        map::apply_to_b_tree_map(map, patch)
+    }
+
+    /// Apply `patch` to `original`, calling `callback` with each line of the result in order
+    ///
+    /// Besides the lines of the patched text, which are either [`OutputLine::Unchanged`] or
+    /// [`OutputLine::Added`], the callback is also called with each [`OutputLine::Removed`]
+    /// line of `original`, in the order the lines appear in the hunks. This allows processing
+    /// the result incrementally, e.g. to report progress, without collecting it into a `String`.
+    ///
+    /// The callback is called with each line as soon as it is settled: the lines before a hunk
+    /// and the lines of a hunk are passed to it once the hunk has been located, and before the
+    /// next hunk is searched for. Therefore each hunk has to apply after the lines changed by the
+    /// previous one. If a hunk fails to apply, its error is returned after the callback has been
+    /// called with the lines before it.
+    ///
+    /// ```
+    /// use diffy::{create_patch, OutputLine, Patch};
+    ///
+    /// let patch = create_patch("a\nb\nc\n", "a\nB\nc\n");
+    ///
+    /// let mut lines = Vec::new();
+    /// Patch::apply_line_by_line_callback("a\nb\nc\n", &patch, |line| lines.push(line)).unwrap();
+    /// assert_eq!(
+    ///     lines,
+    ///     [
+    ///         OutputLine::Unchanged(1, "a\n"),
+    ///         OutputLine::Removed(2, "b\n"),
+    ///         OutputLine::Added("B\n"),
+    ///         OutputLine::Unchanged(3, "c\n"),
+    ///     ]
+    /// );
+    /// ```
+    ///
+    /// [`OutputLine::Unchanged`]: enum.OutputLine.html#variant.Unchanged
+    /// [`OutputLine::Added`]: enum.OutputLine.html#variant.Added
+    /// [`OutputLine::Removed`]: enum.OutputLine.html#variant.Removed
+    pub fn apply_line_by_line_callback<'b, F>(
+        original: &'b str,
+        patch: &Patch<'b>,
+        callback: F,
+    ) -> Result<(), ApplyError>
+    where
+        F: FnMut(apply::OutputLine<'b>),
+    {
+        apply::apply_line_by_line_callback(original, patch, callback)
+    }
+
+    /// Apply `patch` to `original`, also recording where each hunk was applied
+    ///
+    /// The returned [`TraceLog`] describes, for each hunk, where it was expected to apply, how
+    /// many lines of `original` it had to match and where it was applied, or that it couldn't be
+    /// applied. Unlike the error returned when applying fails, it also covers the hunks which
+    /// applied, which helps with finding out why a patch doesn't apply as expected.
+    ///
+    /// ```
+    /// use diffy::{create_patch, Patch};
+    ///
+    /// let patch = create_patch("a\nb\nc\n", "a\nB\nc\n");
+    ///
+    /// let (result, trace) = Patch::apply_with_trace("x\na\nb\nc\n", &patch);
+    /// assert_eq!(result.unwrap(), "x\na\nB\nc\n");
+    /// assert_eq!(
+    ///     trace.to_string(),
+    ///     "hunk 1: applied at line 2 with offset +1, matching 3 lines\n"
+    /// );
+    ///
+    /// let (result, trace) = Patch::apply_with_trace("a\nc\n", &patch);
+    /// assert!(result.is_err());
+    /// assert_eq!(
+    ///     trace.to_string(),
+    ///     "hunk 1: failed to match 3 lines at or around line 1\n"
+    /// );
+    /// ```
+    ///
+    /// [`TraceLog`]: struct.TraceLog.html
+    pub fn apply_with_trace(
+        original: &str,
+        patch: &Patch<'_>,
+    ) -> (Result<String, ApplyError>, apply::TraceLog) {
+        apply::apply_with_trace(original, patch)
+    }
+
+    /// Apply `patch` to `original`, tolerating context lines which no longer match
+    ///
+    /// Each hunk is applied at the position within `max_offset` lines of where it's expected at
+    /// which the most of its context lines match `original`. Up to `fuzz` context lines may
+    /// differ, but the deleted lines always have to match. Context lines which differ are kept
+    /// as they are in `original`. The expected position of a hunk accounts for how far the
+    /// previous hunk was moved, so a patch whose hunks all moved by the same number of lines only
+    /// needs a small `max_offset`.
+    ///
+    /// This is useful for applying old patches to files which have since been changed in other
+    /// places.
+    ///
+    /// ```
+    /// use diffy::{create_patch, Patch};
+    ///
+    /// let patch = create_patch("a\nb\nc\nd\ne\n", "a\nb\nC\nd\ne\n");
+    /// let original = "x\na\nB\nc\nd\ne\n";
+    ///
+    /// // The line before the change was modified, and the change moved down by a line
+    /// assert!(Patch::apply_fuzzy_scan(original, &patch, 0, 1).is_err());
+    /// assert_eq!(
+    ///     Patch::apply_fuzzy_scan(original, &patch, 1, 1).unwrap(),
+    ///     "x\na\nB\nC\nd\ne\n"
+    /// );
+    /// ```
+    pub fn apply_fuzzy_scan(
+        original: &str,
+        patch: &Patch<'_>,
+        fuzz: usize,
+        max_offset: usize,
+    ) -> Result<String, ApplyError> {
+        apply::apply_fuzzy_scan(original, patch, fuzz, max_offset)
+    }
+
+    /// Apply `patch` to `original`, also returning a stack of operations which undo it
+    ///
+    /// Undoing the operations in reverse order, starting with the last one, turns the result back
+    /// into `original`. Each operation covers a run of removed and inserted lines, and its
+    /// position is a line index into the result. The operations therefore have to be undone on
+    /// the unmodified result: once other lines have been inserted into or removed from it, the
+    /// positions no longer refer to the right lines, unless the caller adjusts them for those
+    /// edits.
+    ///
+    /// ```
+    /// use diffy::{create_patch, Patch};
+    ///
+    /// let original = "a\nb\nc\nd\n";
+    /// let patch = create_patch(original, "a\nB\nc\n");
+    ///
+    /// let (result, undo_stack) = Patch::apply_with_undo_stack(original, &patch).unwrap();
+    /// assert_eq!(result, "a\nB\nc\n");
+    ///
+    /// let mut lines: Vec<String> = result.lines().map(|line| format!("{}\n", line)).collect();
+    /// for op in undo_stack.iter().rev() {
+    ///     lines.splice(
+    ///         op.position..op.position + op.remove_lines,
+    ///         op.insert_lines.iter().cloned(),
+    ///     );
+    /// }
+    /// assert_eq!(lines.concat(), original);
+    /// ```
+    pub fn apply_with_undo_stack(
+        original: &str,
+        patch: &Patch<'_>,
+    ) -> Result<(String, Vec<apply::UndoOp>), ApplyError> {
+        apply::apply_with_undo_stack(original, patch)
+    }
+
+    /// Apply this patch in reverse, recovering the original text from `modified`
+    ///
+    /// This is equivalent to applying a reversed copy of the patch without having to construct
+    /// one. The search for each hunk's context and inserted lines starts at the start of its
+    /// range in the old file, and moves outwards from there until they match. The inserted lines
+    /// are then replaced with the deleted lines.
+    ///
+    /// ```
+    /// use diffy::create_patch;
+    ///
+    /// let original = "a\nb\nc\n";
+    /// let modified = "a\nB\nc\nd\n";
+    /// let patch = create_patch(original, modified);
+    ///
+    /// assert_eq!(patch.reverse_apply(modified).unwrap(), original);
+    /// ```
+    pub fn reverse_apply(&self, modified: &str) -> Result<String, ApplyError> {
+        apply::reverse_apply(modified, self)
+    }
+
+    /// Apply this patch to a [`Rope`] in place
+    ///
+    /// Lines are matched and replaced using the rope's line indexing, so unlike [`apply`] this
+    /// doesn't need to copy the unchanged parts of the text. The rope is left unmodified if the
+    /// first hunk fails to apply, but may be partially patched if a later hunk fails.
+    ///
+    /// Lines are split on `\n` when diffing, so the rope should be configured to only treat
+    /// `\n` as a line break. diffy disables ropey's `unicode_lines` and `cr_lines` features but
+    /// they may be enabled by other crates in the dependency graph.
+    ///
+    /// ```
+    /// use diffy::create_patch;
+    /// use ropey::Rope;
+    ///
+    /// let patch = create_patch("a\nb\nc\n", "a\nB\nc\nd\n");
+    ///
+    /// let mut rope = Rope::from_str("a\nb\nc\n");
+    /// patch.apply_to_rope(&mut rope).unwrap();
+    /// assert_eq!(rope.to_string(), "a\nB\nc\nd\n");
+    /// ```
+    ///
+    /// [`Rope`]: https://docs.rs/ropey/1/ropey/struct.Rope.html
+    /// [`apply`]: fn.apply.html
+    #[cfg(feature = "ropey")]
+    pub fn apply_to_rope(&self, rope: &mut ropey::Rope) -> Result<(), ApplyError> {
+        apply::apply_to_rope(rope, self)
+    }
+
+    /// Apply this patch to an empty base image
+    ///
+    /// This is useful for patches which create a new file. The inserted lines of each hunk are
+    /// appended in order and any context lines are ignored since there is nothing for them to
+    /// match against. Returns an error if the patch deletes any lines.
+    ///
+    /// ```
+    /// use diffy::create_patch;
+    ///
+    /// let patch = create_patch("", "a\nb\n");
+    /// assert_eq!(patch.apply_to_scratch().unwrap(), "a\nb\n");
+    ///
+    /// let patch = create_patch("a\n", "b\n");
+    /// assert!(patch.apply_to_scratch().is_err());
+    /// ```
+    pub fn apply_to_scratch(&self) -> Result<String, ApplyError> {
+        apply::apply_to_scratch(self)
+    }
+
+    /// Returns the hunks whose range in the old file is within `window` lines of `line`
+    ///
+    /// `line` is a line number in the old file, starting at 1 like the ranges in hunk headers.
+    /// The hunks are sorted by their distance to `line`, closest first, with hunks containing
+    /// `line` having a distance of `0`. Hunks at the same distance are kept in patch order.
+    ///
+    /// ```
+    /// use diffy::create_patch;
+    ///
+    /// let original: String = (1..=30).map(|i| format!("{}\n", i)).collect();
+    /// let modified = original.replace("\n5\n", "\nfive\n").replace("\n25\n", "\n");
+    /// let patch = create_patch(&original, &modified);
+    ///
+    /// let nearby = patch.hunks_around_line(20, 2);
+    /// assert_eq!(nearby.len(), 1);
+    /// assert_eq!(nearby[0].old_range().start(), 22);
+    ///
+    /// assert_eq!(patch.hunks_around_line(15, 1).len(), 0);
+    /// assert_eq!(patch.hunks_around_line(15, 10).len(), 2);
+    /// ```
+    pub fn hunks_around_line(&self, line: usize, window: usize) -> Vec<&Hunk<'a>> {
+        let distance = |hunk: &Hunk<'_>| {
+            let range = hunk.old_range;
+            let last = cmp::max(range.start, range.end().saturating_sub(1));
+            if line < range.start {
+                range.start - line
+            } else {
+                line.saturating_sub(last)
+            }
+        };
+
+        let mut hunks: Vec<(usize, &Hunk<'a>)> = self
+            .hunks
+            .iter()
+            .map(|hunk| (distance(hunk), hunk))
+            .filter(|(distance, _)| *distance <= window)
+            .collect();
+        hunks.sort_by_key(|(distance, _)| *distance);
+
+        hunks.into_iter().map(|(_, hunk)| hunk).collect()
+    }
+
+    /// Recompute the ranges of every hunk from the lines it contains
+    ///
+    /// The length of each hunk's old and new range is set to the number of lines it contains
+    /// from the old and new file respectively. The start of each hunk's new range is then
+    /// recomputed from the start of its old range, accounting for the lines added or removed by
+    /// the hunks before it. This repairs patches whose hunks were assembled by hand, e.g. by
+    /// selecting a subset of the hunks of another patch.
+    pub fn normalize_hunks(&mut self) {
+        // The net number of lines added by the hunks seen so far
+        let mut offset = 0isize;
+
+        for hunk in &mut self.hunks {
+            let (old_len, new_len) = hunk_lines_count(&hunk.lines);
+
+            // Empty ranges refer to the line before the hunk so convert to 0-based positions
+            let old_pos = if old_len > 0 {
+                hunk.old_range.start.saturating_sub(1)
+            } else {
+                hunk.old_range.start
+            };
+            let new_pos = (old_pos as isize + offset).max(0) as usize;
+
+            hunk.old_range = HunkRange::from_position(old_pos, old_len);
+            hunk.new_range = HunkRange::from_position(new_pos, new_len);
+
+            offset += new_len as isize - old_len as isize;
+        }
+    }
+
+    /// Returns a copy of this patch with its hunks sorted by the number of lines they change,
+    /// largest first
+    ///
+    /// This is meant for displaying the most significant changes first, e.g. in a code review
+    /// tool, and the result generally can't be applied, since hunks need to be in order for
+    /// that. Hunks which change the same number of lines keep their relative order.
+    ///
+    /// ```
+    /// use diffy::create_patch;
+    ///
+    /// let original = "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\n";
+    /// let modified = "A\nb\nc\nd\ne\nf\ng\nh\nI\nJ\n";
+    ///
+    /// let patch = create_patch(original, modified).reorder_hunks_by_impact();
+    /// assert_eq!(patch.hunks()[0].old_range().start(), 6);
+    /// assert_eq!(patch.hunks()[1].old_range().start(), 1);
+    /// ```
+    pub fn reorder_hunks_by_impact(&self) -> Patch<'a> {
+        let changed_lines = |hunk: &Hunk<'_>| {
+            hunk.lines
+                .iter()
+                .filter(|line| !matches!(line, Line::Context(_)))
+                .count()
+        };
+        self.sorted_by(|a, b| changed_lines(b).cmp(&changed_lines(a)))
+    }
+
+    /// Returns a copy of this patch with its hunks sorted using the comparison function `f`
+    ///
+    /// The sort is stable, so hunks which compare equal keep their relative order. See
+    /// [`reorder_hunks_by_impact`] for why the result generally can't be applied.
+    ///
+    /// [`reorder_hunks_by_impact`]: #method.reorder_hunks_by_impact
+    pub fn sorted_by<F>(&self, f: F) -> Patch<'a>
+    where
+        F: Fn(&Hunk<'a>, &Hunk<'a>) -> cmp::Ordering,
+    {
+        let mut hunks = self.hunks.clone();
+        hunks.sort_by(f);
+        self.with_hunks(hunks)
+    }
+
+    /// Returns a copy of this patch with both file names transformed by `f`
+    ///
+    /// ```
+    /// use diffy::Patch;
+    ///
+    /// let patch = Patch::from_str("--- a/file\n+++ b/file\n").unwrap();
+    /// let patch = patch.rewrite_filenames(|name| format!("src/{}", name));
+    /// assert_eq!(patch.original(), "src/a/file");
+    /// assert_eq!(patch.modified(), "src/b/file");
+    /// ```
+    pub fn rewrite_filenames<F>(&self, f: F) -> Patch<'a>
+    where
+        F: Fn(&str) -> String,
+    {
+        Patch {
+            original: Filename(f(&self.original).into()),
+            modified: Filename(f(&self.modified).into()),
+            hunks: self.hunks.clone(),
+            mode_change: self.mode_change,
+            rename: self.rename.clone(),
+            git_index: self.git_index.clone(),
+        }
+    }
+
+    /// Returns a copy of this patch with the first `levels` components stripped from the path of
+    /// both file names, similar to the `-p` flag of `patch(1)`
+    ///
+    /// Consecutive slashes are treated as a single separator and the final component of a path
+    /// is never stripped. `/dev/null`, which is used as the name of a file which doesn't exist,
+    /// is left untouched.
+    ///
+    /// ```
+    /// use diffy::Patch;
+    ///
+    /// let patch = Patch::from_str("--- a/src/lib.rs\n+++ b/src/lib.rs\n").unwrap();
+    /// let patch = patch.strip_path_prefix(1);
+    /// assert_eq!(patch.original(), "src/lib.rs");
+    /// assert_eq!(patch.modified(), "src/lib.rs");
+    /// ```
+    pub fn strip_path_prefix(&self, levels: usize) -> Patch<'a> {
+        self.rewrite_filenames(|name| strip_path_prefix(name, levels).to_owned())
+    }
+
+    /// Produce the complement of this patch
+    ///
+    /// Given the `original` and `modified` texts this patch was selected from (e.g. a subset of
+    /// the hunks of a patch between the two), returns a patch which, when applied to `modified`,
+    /// reverts all of the changes which are *not* part of this patch. Applying the resulting
+    /// patch to `modified` produces the same text as applying this patch to `original`.
+    ///
+    /// Returns an error if this patch can't be applied to `original`.
+    ///
+    /// ```
+    /// use diffy::{apply, create_patch, Patch};
+    ///
+    /// let original = "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\n";
+    /// let staged = "A\nb\nc\nd\ne\nf\ng\nh\ni\nj\n";
+    /// let modified = "A\nb\nc\nd\ne\nf\ng\nh\ni\nJ\n";
+    ///
+    /// // A patch containing only the first of the two changes
+    /// let selection = create_patch(original, staged);
+    ///
+    /// let complement = selection.invert_selection(original, modified).unwrap();
+    /// assert_eq!(apply(modified, &complement).unwrap(), staged);
+    /// ```
+    pub fn invert_selection(
+        &self,
+        original: &'a str,
+        modified: &'a str,
+    ) -> Result<Patch<'a>, ApplyError> {
+        let selected = apply::apply_to_lines(original, self)?;
+        let modified_lines: Vec<&str> = LineIter::new(modified).collect();
+
+        let mut patch = DiffOptions::default()
+            .create_patch_from_lines(&modified_lines, &selected)
+            .into_inner();
+        patch.original = self.modified.clone();
+        patch.modified = self.original.clone();
+
+        Ok(patch)
+    }
+
+    /// Render the patch as an XML document
+    ///
+    /// Each line of a hunk is rendered as a `<context>`, `<delete>` or `<insert>` element
+    /// without its trailing newline. A line which is the last line of a file without a trailing
+    /// newline is marked with a `no_newline_at_eof="true"` attribute. Control characters which
+    /// can't be represented in XML 1.0 are replaced with `U+FFFD`.
+    ///
+    /// ```
+    /// use diffy::create_patch;
+    ///
+    /// let patch = create_patch("a\nb & c\n", "a\nb < c\n");
+    /// let expected = r#"<?xml version="1.0" encoding="UTF-8"?>
+    /// <patch original="original" modified="modified">
+    ///   <hunk old_start="1" old_len="2" new_start="1" new_len="2">
+    ///     <context>a</context>
+    ///     <delete>b &amp; c</delete>
+    ///     <insert>b &lt; c</insert>
+    ///   </hunk>
+    /// </patch>
+    /// "#;
+    /// assert_eq!(patch.to_xml(), expected);
+    /// ```
+    pub fn to_xml(&self) -> String {
+        xml::to_xml(self)
+    }
+
+    /// Returns a JSON Schema document describing the JSON form of a `Patch`
+    ///
+    /// With the `serde` feature, `Patch` and [`PatchBuf`] implement `Serialize`, and `PatchBuf`
+    /// implements `Deserialize`, using this form. It has the same structure as [`to_xml`]: the
+    /// names of the files and the hunks, each made up of its ranges, optional function context
+    /// and lines. Unlike in the XML document, lines keep their line endings. Git's mode, rename
+    /// and index headers are included if the patch has them. This is useful for documenting and validating
+    /// patches exchanged as JSON, or for generating types for them in other languages.
+    ///
+    /// Requires the `json` feature.
+    ///
+    /// ```
+    /// use diffy::Patch;
+    ///
+    /// let schema = Patch::json_schema();
+    /// assert_eq!(schema["title"], "Patch");
+    /// assert_eq!(
+    ///     schema["$defs"]["line"]["properties"]["kind"]["enum"],
+    ///     serde_json::json!(["context", "delete", "insert"])
+    /// );
+    /// ```
+    ///
+    /// [`PatchBuf`]: struct.PatchBuf.html
+    /// [`to_xml`]: #method.to_xml
+    #[cfg(feature = "json")]
+    pub fn json_schema() -> serde_json::Value {
+        json_schema::json_schema()
+    }
+
+    /// Render the patch in one of the formats of [`RichTextFormat`]
+    ///
+    /// Deleted lines are shown in red, inserted lines in green and hunk headers in blue, except
+    /// for [`RichTextFormat::Plain`] which is the same as the `Display` output.
+    ///
+    /// ```
+    /// use diffy::{create_patch, RichTextFormat};
+    ///
+    /// let patch = create_patch("a\nb\n", "a\n<b>\n");
+    /// let expected = r#"<pre class="diff">
+    /// <span style="font-weight: bold">--- original</span>
+    /// <span style="font-weight: bold">+++ modified</span>
+    /// <span style="color: #0086b3">@@ -1,2 +1,2 @@</span>
+    ///  a
+    /// <span style="color: #cb2431; background-color: #ffeef0">-b</span>
+    /// <span style="color: #22863a; background-color: #f0fff4">+&lt;b&gt;</span>
+    /// </pre>
+    /// "#;
+    /// assert_eq!(patch.to_rich_text(RichTextFormat::Html), expected);
+    /// assert_eq!(patch.to_rich_text(RichTextFormat::Plain), patch.to_string());
+    /// ```
+    ///
+    /// [`RichTextFormat`]: enum.RichTextFormat.html
+    /// [`RichTextFormat::Plain`]: enum.RichTextFormat.html#variant.Plain
+    pub fn to_rich_text(&self, format: RichTextFormat) -> String {
+        rich_text::to_rich_text(self, format)
+    }
+
+    /// Clip this patch to the region of `original` which contains changes
+    ///
+    /// The returned patch has exactly `padding` lines of context, taken from `original`, before
+    /// its first change and after its last change, or fewer at the start and end of the file. Its
+    /// line numbers are relative to the start of the region, which is returned as a range of
+    /// zero-based line indices into `original`. This is useful for showing just the relevant part
+    /// of a large file.
+    ///
+    /// `original` should be the text the patch was created from. A patch without hunks returns
+    /// an empty patch and an empty range.
+    ///
+    /// ```
+    /// use diffy::create_patch;
+    ///
+    /// let original: String = (1..=100).map(|i| format!("{}\n", i)).collect();
+    /// let modified = original.replace("\n50\n", "\nfifty\n");
+    /// let patch = create_patch(&original, &modified);
+    ///
+    /// let (clipped, region) = patch.truncate_to_changed_region(&original, 1);
+    /// assert_eq!(region, 48..51);
+    /// assert_eq!(clipped.to_string(), "\
+    /// --- original
+    /// +++ modified
+    /// @@ -1,3 +1,3 @@
+    ///  49
+    /// -50
+    /// +fifty
+    ///  51
+    /// ");
+    /// ```
+    pub fn truncate_to_changed_region(
+        &self,
+        original: &'a str,
+        padding: usize,
+    ) -> (Patch<'a>, ops::Range<usize>) {
+        let context_len = |lines: &mut dyn Iterator<Item = &Line<'a>>| {
+            lines
+                .take_while(|line| matches!(line, Line::Context(_)))
+                .count()
+        };
+
+        let (first, last) = match (self.hunks.first(), self.hunks.last()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return (self.with_hunks(Vec::new()), 0..0),
+        };
+        let lines: Vec<&'a str> = LineIter::new(original).collect();
+
+        // The first and last changed lines of the old file, as 0-based positions
+        let first_change = first.old_range.position() + context_len(&mut first.lines.iter());
+        let last_change_end = last.old_range.position() + last.old_range.len
+            - context_len(&mut last.lines.iter().rev());
+        let region = first_change.saturating_sub(padding)
+            ..cmp::min(last_change_end + padding, lines.len()).max(last_change_end);
+        // The start of the region in the new file, which is offset from the old one by the lines
+        // inserted or deleted before it
+        let leading_len = lines.get(region.start..first_change).map_or(0, <[_]>::len);
+        let new_region_start =
+            first.new_range.position() + context_len(&mut first.lines.iter()) - leading_len;
+
+        let last_index = self.hunks.len() - 1;
+        let hunks = self
+            .hunks
+            .iter()
+            .enumerate()
+            .map(|(i, hunk)| {
+                let mut old_pos = hunk.old_range.position();
+                let mut new_pos = hunk.new_range.position();
+                let mut hunk_lines = &hunk.lines[..];
+                let mut leading = Vec::new();
+                let mut trailing = Vec::new();
+
+                if i == 0 {
+                    let skip = context_len(&mut hunk_lines.iter());
+                    hunk_lines = &hunk_lines[skip..];
+                    old_pos += skip;
+                    new_pos += skip;
+
+                    leading.extend(
+                        lines
+                            .get(region.start..old_pos)
+                            .unwrap_or_default()
+                            .iter()
+                            .copied()
+                            .map(Line::Context),
+                    );
+                    old_pos -= leading.len();
+                    new_pos -= leading.len();
+                }
+                if i == last_index {
+                    let skip = context_len(&mut hunk_lines.iter().rev());
+                    hunk_lines = &hunk_lines[..hunk_lines.len() - skip];
+                    trailing.extend(
+                        lines
+                            .get(last_change_end..region.end)
+                            .unwrap_or_default()
+                            .iter()
+                            .copied()
+                            .map(Line::Context),
+                    );
+                }
+
+                let hunk_lines: Vec<Line<'a>> = leading
+                    .into_iter()
+                    .chain(hunk_lines.iter().copied())
+                    .chain(trailing)
+                    .collect();
+                let (old_len, new_len) = hunk_lines_count(&hunk_lines);
+
+                Hunk::new(
+                    HunkRange::from_position(old_pos - region.start, old_len),
+                    HunkRange::from_position(new_pos - new_region_start, new_len),
+                    hunk.function_context.clone(),
+                    hunk_lines,
+                )
+            })
+            .collect();
+
+        (self.with_hunks(hunks), region)
+    }
+
+    fn with_hunks(&self, hunks: Vec<Hunk<'a>>) -> Patch<'a> {
+        Patch {
+            original: self.original.clone(),
+            modified: self.modified.clone(),
+            hunks,
+            mode_change: self.mode_change,
+            rename: self.rename.clone(),
+            git_index: self.git_index.clone(),
+        }
+    }
+
+    /// Split the lines of each hunk into blocks of context lines and blocks of edited lines
+    ///
+    /// A [`ContextBlock`] is a run of consecutive context lines and an [`EditBlock`] is a run of
+    /// consecutive deleted and inserted lines. The blocks of each kind are returned in patch
+    /// order and the two kinds alternate within a hunk, which can be reconstructed using their
+    /// hunk index and line numbers.
+    ///
+    /// ```
+    /// use diffy::create_patch;
+    ///
+    /// let patch = create_patch("a\nb\nc\n", "a\nB\nc\n");
+    /// let (context, edits) = patch.split_context_from_edits();
+    ///
+    /// assert_eq!(context.len(), 2);
+    /// assert_eq!(edits.len(), 1);
+    /// assert_eq!(edits[0].old_line(), 2);
+    /// assert_eq!(edits[0].deleted().collect::<Vec<_>>(), ["b\n"]);
+    /// assert_eq!(edits[0].inserted().collect::<Vec<_>>(), ["B\n"]);
+    /// ```
+    ///
+    /// [`ContextBlock`]: struct.ContextBlock.html
+    /// [`EditBlock`]: struct.EditBlock.html
+    pub fn split_context_from_edits(&self) -> (Vec<ContextBlock<'_>>, Vec<EditBlock<'_>>) {
+        blocks::split_context_from_edits(self)
+    }
+
+    /// Render a text template which refers to this patch and the text it applies to
+    ///
+    /// The following tokens in `template` are replaced:
+    ///
+    /// * `{{original}}`: `original`, the text this patch applies to
+    /// * `{{modified}}`: the result of applying this patch to `original`
+    /// * `{{original_name}}` and `{{modified_name}}`: the filenames in the patch header
+    /// * `{{patch}}`: the patch itself
+    /// * `{{hunk:N}}`: the `N`th hunk of the patch, starting at 1
+    ///
+    /// Any other text, including unknown tokens, is copied to the output unchanged. Returns an
+    /// error if the template refers to the modified text and the patch fails to apply.
+    ///
+    /// ```
+    /// use diffy::create_patch;
+    ///
+    /// let patch = create_patch("Hello\n", "Hello, World!\n");
+    /// let template = "Changed {{original_name}} from:\n{{original}}to:\n{{modified}}";
+    ///
+    /// assert_eq!(
+    ///     patch.apply_template(template, "Hello\n").unwrap(),
+    ///     "Changed original from:\nHello\nto:\nHello, World!\n"
+    /// );
+    /// ```
+    pub fn apply_template(&self, template: &str, original: &str) -> Result<String, ApplyError> {
+        template::apply_template(self, template, original)
+    }
+
+    /// Produce a short English description of the changes made by this patch
+    ///
+    /// Each block of changed lines is described using its line number in `original`, the text
+    /// the patch applies to. If a hunk has no function context, the closest line before the
+    /// change in `original` which starts with a letter, `_` or `$` is used as the name of the
+    /// enclosing function, like GNU diff's `--show-function-line`.
+    ///
+    /// ```
+    /// use diffy::create_patch;
+    ///
+    /// let original = "fn main() {\n    let a = 1;\n    let b = 2;\n    println!(\"{}\", a + b);\n}\n";
+    /// let modified = "fn main() {\n    let a = 3;\n    let b = 2;\n    println!(\"{}\", a + b);\n}\n";
+    /// let patch = create_patch(original, modified);
+    ///
+    /// assert_eq!(
+    ///     patch.describe(original),
+    ///     "Replaces 1 line starting at line 2 in `fn main() {`."
+    /// );
+    /// ```
+    pub fn describe(&self, original: &str) -> String {
+        describe::describe(self, original)
+    }
+
+    /// Render this patch as a word diff, like `git diff --word-diff=plain`
+    ///
+    /// Within each block of changed lines, the first deleted line is paired with the first
+    /// inserted line and so on. Each pair is diffed again word by word, using [`WordClassifier`],
+    /// and the words which changed are shown inline as `{-old-}` and `{+new+}`. Lines without a
+    /// counterpart are marked as a whole. Context lines are shown as they are, without a leading
+    /// space, and every line of the output ends with a newline.
+    ///
+    /// ```
+    /// use diffy::create_patch;
+    ///
+    /// let original = "The quick brown fox\njumps over the lazy dog.\n";
+    /// let modified = "The quick red fox\njumps over the lazy dog.\n";
+    /// let patch = create_patch(original, modified);
+    ///
+    /// assert_eq!(
+    ///     patch.to_word_diff_display(),
+    ///     "\
+    /// --- original
+    /// +++ modified
+    /// @@ -1,2 +1,2 @@
+    /// The quick {-brown-}{+red+} fox
+    /// jumps over the lazy dog.
+    /// "
+    /// );
+    /// ```
+    ///
+    /// [`WordClassifier`]: struct.WordClassifier.html
+    pub fn to_word_diff_display(&self) -> String {
+        word_diff::to_word_diff_display(self)
+    }
+
+    /// Format this patch as a Slack message
+    ///
+    /// The patch is placed in a code block, where added and removed lines keep their `+` and `-`
+    /// markers. Slack's mrkdwn has no way to color text, so unlike [`PatchFormatter::with_color`]
+    /// the lines can't be shown in green and red. `&`, `<` and `>` are escaped as Slack requires.
+    ///
+    /// Messages are limited to 4000 characters. If the patch doesn't fit, only its first lines
+    /// are included, followed by `...and N more lines` after the code block.
+    ///
+    /// ```
+    /// use diffy::create_patch;
+    ///
+    /// let patch = create_patch("a < b\n", "a > b\n");
+    /// assert_eq!(
+    ///     patch.to_slack_message(),
+    ///     "```\n--- original\n+++ modified\n@@ -1 +1 @@\n-a &lt; b\n+a &gt; b\n```"
+    /// );
+    /// ```
+    ///
+    /// [`PatchFormatter::with_color`]: struct.PatchFormatter.html#method.with_color
+    pub fn to_slack_message(&self) -> String {
+        slack::to_slack_message(self)
+    }
+
+    /// Render this patch in two columns for a terminal which is `width` characters wide
+    ///
+    /// The old lines are shown in the left column and the new lines in the right one, using
+    /// ANSI escape codes for color. Deleted lines only show up on the left and inserted lines only
+    /// on the right. Within each block of changes, the nth deleted line is shown next to the nth
+    /// inserted line, with the characters which differ between them highlighted. Like `sdiff`,
+    /// the columns are separated by `|` for changed lines, `<` for deleted lines and `>` for
+    /// inserted lines.
+    ///
+    /// Tabs are expanded and lines which are too long for their column are truncated. Widths
+    /// are counted in `char`s rather than terminal columns, so wide characters like CJK
+    /// ideographs and emoji, which take up two columns, and combining characters, which take up
+    /// none, throw off the alignment of the columns.
+    ///
+    /// ```
+    /// use diffy::create_patch;
+    ///
+    /// let patch = create_patch("same\nold\n", "same\nnew\ninserted\n");
+    /// let output = patch.to_terminal_side_by_side(40);
+    ///
+    /// let lines: Vec<_> = output.lines().collect();
+    /// assert_eq!(lines.len(), 5);
+    /// // Unchanged lines aren't colored
+    /// assert_eq!(lines[2], format!("{:18}   {}", "same", "same"));
+    /// ```
+    pub fn to_terminal_side_by_side(&self, width: usize) -> String {
+        side_by_side::to_terminal_side_by_side(self, width)
+    }
+
+    /// Render this patch like git's `diff-highlight` script, highlighting the characters which
+    /// changed within modified lines
+    ///
+    /// The output is the same as when displaying the patch, except that within each block of
+    /// changes, the nth deleted line is compared to the nth inserted line. If at least half of
+    /// their characters are unchanged, the characters which differ are highlighted in both lines
+    /// using bold and underlined ANSI escape codes. Lines which are too different aren't
+    /// highlighted at all, since highlighting almost all of a line doesn't help.
+    ///
+    /// ```
+    /// use diffy::create_patch;
+    ///
+    /// let patch = create_patch("let x = 1;\n", "let x = 2;\n");
+    /// assert_eq!(
+    ///     patch.to_diff_highlight_format(),
+    ///     "\
+    /// --- original
+    /// +++ modified
+    /// @@ -1 +1 @@
+    /// -let x = \u{1b}[1;4m1\u{1b}[0m;
+    /// +let x = \u{1b}[1;4m2\u{1b}[0m;
+    /// "
+    /// );
+    /// ```
+    pub fn to_diff_highlight_format(&self) -> String {
+        diff_highlight::to_diff_highlight_format(self)
+    }
+
+    /// Create a normalized copy of this patch which is safe to store, e.g. in version control or
+    /// a public database
+    ///
+    /// Absolute paths are made relative, dropping the home directory (and with it the user's
+    /// name) if the path is inside of one. CRLF line endings are converted to LF and byte order
+    /// marks are stripped from the first line of the file. Timestamps are never stored in a
+    /// `Patch`, since they are dropped when parsing the file headers.
+    ///
+    /// Since the lines of a `Patch` borrow from the text they were created from, the normalized
+    /// lines can't be returned as a `Patch<'static>`. Instead the copy is returned as a
+    /// [`PatchBuf`], which owns them.
+    ///
+    /// ```
+    /// use diffy::Patch;
+    ///
+    /// let patch = Patch::from_str(
+    ///     "--- /home/alice/project/file\t2024-01-01 12:00:00\n\
+    ///      +++ /home/alice/project/file\t2024-01-02 12:00:00\n\
+    ///      @@ -1 +1 @@\n\
+    ///      -old\r\n\
+    ///      +new\r\n",
+    /// )
+    /// .unwrap();
+    /// assert_eq!(
+    ///     patch.sanitize_for_storage().as_patch().to_string(),
+    ///     "--- project/file\n+++ project/file\n@@ -1 +1 @@\n-old\n+new\n"
+    /// );
+    /// ```
+    ///
+    /// [`PatchBuf`]: struct.PatchBuf.html
+    pub fn sanitize_for_storage(&self) -> PatchBuf {
+        sanitize::sanitize_for_storage(self)
+    }
+
+    /// Returns the total number of bytes of text stored in this patch
+    ///
+    /// This is the length of the filenames plus the length of each hunk's function context and
+    /// lines, and is the same whether the text is borrowed or owned. It doesn't include the size
+    /// of the patch's own data structures.
+    ///
+    /// ```
+    /// use diffy::create_patch;
+    ///
+    /// let patch = create_patch("a\nb\n", "a\nc\n");
+    /// // "original" + "modified" + "a\n" + "b\n" + "c\n"
+    /// assert_eq!(patch.total_byte_size(), 8 + 8 + 2 + 2 + 2);
+    /// ```
+    pub fn total_byte_size(&self) -> usize {
+        let hunks: usize = self
+            .hunks
+            .iter()
+            .map(|hunk| {
+                let lines: usize = hunk
+                    .lines
+                    .iter()
+                    .map(|line| match line {
+                        Line::Context(l) | Line::Delete(l) | Line::Insert(l) => l.len(),
+                    })
+                    .sum();
+                hunk.function_context().map_or(0, str::len) + lines
+            })
+            .sum();
+
+        self.original.len() + self.modified.len() + hunks
+    }
+
+    /// Estimate the size of this patch relative to the texts it describes
+    ///
+    /// Returns an estimate of the compressed size of the patch divided by the combined size of
+    /// the original and modified texts. The compressed size is estimated as the Shannon entropy
+    /// of the byte frequencies in the deleted and inserted lines, multiplied by their length, and
+    /// is at least one byte if anything changed. Context lines are assumed to be free since the
+    /// recipient of a patch already has them. As a patch doesn't contain the parts of the texts
+    /// outside of its hunks, the sizes of the texts are those of the lines covered by the hunks.
+    ///
+    /// The ratio is between `0.0` and `1.0`, and is `0.0` if the patch doesn't contain any
+    /// changes. Low ratios indicate that the files are similar and sending the patch is
+    /// worthwhile, while higher ratios suggest sending the full file instead.
+    ///
+    /// ```
+    /// use diffy::create_patch;
+    ///
+    /// let original: String = (0..100).map(|i| format!("line {}\n", i)).collect();
+    /// let similar = original.replace("line 50\n", "line fifty\n");
+    /// let different: String = (0..100).map(|i| format!("{} enil\n", i)).collect();
+    ///
+    /// let small = create_patch(&original, &similar).estimate_compression_ratio();
+    /// let large = create_patch(&original, &different).estimate_compression_ratio();
+    /// assert!(small < large);
+    /// assert!(0.0 < small && large <= 1.0);
+    /// ```
+    pub fn estimate_compression_ratio(&self) -> f64 {
+        let mut frequencies = [0usize; 256];
+        let mut changed_bytes = 0;
+        let mut text_bytes = 0;
+
+        for line in self.hunks.iter().flat_map(|hunk| &hunk.lines) {
+            match line {
+                // Context lines are part of both texts
+                Line::Context(l) => text_bytes += 2 * l.len(),
+                Line::Delete(l) | Line::Insert(l) => {
+                    text_bytes += l.len();
+                    changed_bytes += l.len();
+                    for &byte in l.as_bytes() {
+                        frequencies[byte as usize] += 1;
+                    }
+                }
+            }
+        }
+
+        if changed_bytes == 0 {
+            return 0.0;
+        }
+
+        let len = changed_bytes as f64;
+        let entropy: f64 = frequencies
+            .iter()
+            .filter(|&&count| count > 0)
+            .map(|&count| {
+                let p = count as f64 / len;
+                -p * p.log2()
+            })
+            .sum();
+        let compressed_bytes = (entropy * len / 8.0).max(1.0);
+
+        compressed_bytes / text_bytes as f64
+    }
+
+    pub(crate) fn hunks_mut(&mut self) -> &mut Vec<Hunk<'a>> {
+        &mut self.hunks
+    }
 }
 
 impl fmt::Display for Patch<'_> {
@@ -75,6 +1496,21 @@ impl fmt::Display for Patch<'_> {
     }
 }
 
+fn strip_path_prefix(path: &str, levels: usize) -> &str {
+    if path == "/dev/null" {
+        return path;
+    }
+
+    let mut path = path;
+    for _ in 0..levels {
+        match path.find('/') {
+            Some(idx) => path = path[idx + 1..].trim_start_matches('/'),
+            None => break,
+        }
+    }
+    path
+}
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 struct Filename<'a>(Cow<'a, str>);
 
@@ -126,11 +1562,19 @@ pub struct Hunk<'a> {
     old_range: HunkRange,
     new_range: HunkRange,
 
-    function_context: Option<&'a str>,
+    function_context: Option<Cow<'a, str>>,
 
     lines: Vec<Line<'a>>,
 }
 
+// The number of lines up to the end of the range which reaches furthest
+fn line_count(ranges: impl Iterator<Item = HunkRange>) -> usize {
+    ranges
+        .map(|range| range.position() + range.len())
+        .max()
+        .unwrap_or(0)
+}
+
 fn hunk_lines_count(lines: &[Line<'_>]) -> (usize, usize) {
     lines.iter().fold((0, 0), |count, line| match line {
         Line::Context(_) => (count.0 + 1, count.1 + 1),
@@ -143,7 +1587,7 @@ impl<'a> Hunk<'a> {
     pub(crate) fn new(
         old_range: HunkRange,
         new_range: HunkRange,
-        function_context: Option<&'a str>,
+        function_context: Option<Cow<'a, str>>,
         lines: Vec<Line<'a>>,
     ) -> Self {
         let (old_count, new_count) = hunk_lines_count(&lines);
@@ -174,10 +1618,163 @@ impl<'a> Hunk<'a> {
         self.function_context.as_deref()
     }
 
+    pub(crate) fn set_function_context(&mut self, function_context: Option<String>) {
+        self.function_context = function_context.map(Cow::Owned);
+    }
+
     /// Returns the lines in the hunk
     pub fn lines(&self) -> &[Line<'a>] {
         &self.lines
     }
+
+    /// Render the hunk on its own, preceded by a `---`/`+++` header using the provided file names
+    ///
+    /// This is useful for displaying a patch one hunk at a time. Use [`PatchFormatter::fmt_hunk`]
+    /// to control how the hunk itself is rendered.
+    ///
+    /// ```
+    /// use diffy::create_patch;
+    ///
+    /// let patch = create_patch("a\nb\n", "a\nc\n");
+    /// let expected = "\
+    /// --- a/file
+    /// +++ b/file
+    /// @@ -1,2 +1,2 @@
+    ///  a
+    /// -b
+    /// +c
+    /// ";
+    /// assert_eq!(patch.hunks()[0].to_display_string("a/file", "b/file"), expected);
+    /// ```
+    ///
+    /// [`PatchFormatter::fmt_hunk`]: struct.PatchFormatter.html#method.fmt_hunk
+    pub fn to_display_string(&self, old_prefix: &str, new_prefix: &str) -> String {
+        format!(
+            "--- {}\n+++ {}\n{}",
+            Filename(old_prefix.into()),
+            Filename(new_prefix.into()),
+            self
+        )
+    }
+
+    /// Split the hunk into two hunks before the line at `line_index`
+    ///
+    /// The first hunk contains `lines()[..line_index]` and the second `lines()[line_index..]`,
+    /// each with ranges matching the lines they contain. Returns `None` if either hunk would be
+    /// empty or if the split point isn't next to a context line, i.e. it would split up a block
+    /// of deleted and inserted lines.
+    ///
+    /// ```
+    /// use diffy::create_patch;
+    ///
+    /// let patch = create_patch("a\nb\nc\nd\n", "A\nb\nc\nD\n");
+    /// let hunk = &patch.hunks()[0];
+    /// assert_eq!(hunk.to_string(), "@@ -1,4 +1,4 @@\n-a\n+A\n b\n c\n-d\n+D\n");
+    ///
+    /// let (first, second) = hunk.split_at_line(3).unwrap();
+    /// assert_eq!(first.to_string(), "@@ -1,2 +1,2 @@\n-a\n+A\n b\n");
+    /// assert_eq!(second.to_string(), "@@ -3,2 +3,2 @@\n c\n-d\n+D\n");
+    ///
+    /// assert!(hunk.split_at_line(1).is_none());
+    /// ```
+    pub fn split_at_line(&self, line_index: usize) -> Option<(Hunk<'a>, Hunk<'a>)> {
+        if line_index == 0 || line_index >= self.lines.len() {
+            return None;
+        }
+
+        let (first, second) = self.lines.split_at(line_index);
+        let at_context = matches!(first.last(), Some(Line::Context(_)))
+            || matches!(second.first(), Some(Line::Context(_)));
+        if !at_context {
+            return None;
+        }
+
+        let (old_len, new_len) = hunk_lines_count(first);
+        let old_pos = self.old_range.position();
+        let new_pos = self.new_range.position();
+
+        let first = Hunk::new(
+            HunkRange::from_position(old_pos, old_len),
+            HunkRange::from_position(new_pos, new_len),
+            self.function_context.clone(),
+            first.to_vec(),
+        );
+        let second = Hunk::new(
+            HunkRange::from_position(old_pos + old_len, self.old_range.len - old_len),
+            HunkRange::from_position(new_pos + new_len, self.new_range.len - new_len),
+            self.function_context.clone(),
+            second.to_vec(),
+        );
+
+        Some((first, second))
+    }
+
+    /// Add up to `lines` more lines of context before and after this hunk
+    ///
+    /// `original_lines` and `modified_lines` are the lines, including their trailing newlines,
+    /// of the complete texts the hunk was created from. The new context lines are looked up in
+    /// them, so fewer lines are added at the beginning and end of the texts. No lines are added
+    /// before or after a hunk whose range lies beyond the end of the texts.
+    ///
+    /// ```
+    /// use diffy::DiffOptions;
+    ///
+    /// let original = "a\nb\nc\nd\n";
+    /// let modified = "a\nb\nC\nd\n";
+    /// let patch = DiffOptions::new()
+    ///     .set_context_len(0)
+    ///     .create_patch(original, modified);
+    ///
+    /// let original_lines: Vec<&str> = original.split_inclusive('\n').collect();
+    /// let modified_lines: Vec<&str> = modified.split_inclusive('\n').collect();
+    /// let hunk = patch.hunks()[0].expand_context(&original_lines, &modified_lines, 2);
+    /// assert_eq!(hunk.to_string(), "@@ -1,4 +1,4 @@\n a\n b\n-c\n+C\n d\n");
+    /// ```
+    pub fn expand_context(
+        &self,
+        original_lines: &[&'a str],
+        modified_lines: &[&'a str],
+        lines: usize,
+    ) -> Hunk<'a> {
+        let old_pos = self.old_range.position();
+        let new_pos = self.new_range.position();
+        let old_end = old_pos + self.old_range.len;
+        let new_end = new_pos + self.new_range.len;
+
+        let before = lines.min(old_pos).min(new_pos);
+        let after = lines
+            .min(original_lines.len().saturating_sub(old_end))
+            .min(modified_lines.len().saturating_sub(new_end));
+        // A hunk which doesn't fit in the texts gets no context
+        let leading = original_lines
+            .get(old_pos - before..old_pos)
+            .unwrap_or_default();
+        let trailing = original_lines
+            .get(old_end..old_end + after)
+            .unwrap_or_default();
+        let (before, after) = (leading.len(), trailing.len());
+
+        let hunk_lines: Vec<Line<'a>> = leading
+            .iter()
+            .copied()
+            .map(Line::Context)
+            .chain(self.lines.iter().copied())
+            .chain(trailing.iter().copied().map(Line::Context))
+            .collect();
+
+        Hunk::new(
+            HunkRange::from_position(old_pos - before, self.old_range.len + before + after),
+            HunkRange::from_position(new_pos - before, self.new_range.len + before + after),
+            self.function_context.clone(),
+            hunk_lines,
+        )
+    }
+}
+
+impl fmt::Display for Hunk<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", PatchFormatter::new().fmt_hunk(self))
+    }
 }
 
 /// The range of lines in a file for a particular `Hunk`.
@@ -194,6 +1791,21 @@ impl HunkRange {
         Self { start, len }
     }
 
+    // Create a range from the 0-based position of its first line. Empty ranges refer to the line
+    // before the position instead.
+    pub(crate) fn from_position(pos: usize, len: usize) -> Self {
+        Self::new(if len > 0 { pos + 1 } else { pos }, len)
+    }
+
+    // The 0-based position of the range's first line
+    pub(crate) fn position(&self) -> usize {
+        if self.len > 0 {
+            self.start.saturating_sub(1)
+        } else {
+            self.start
+        }
+    }
+
     /// Returns the range as a `ops::Range`
     pub fn range(&self) -> ops::Range<usize> {
         self.start..self.end()
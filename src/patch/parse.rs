@@ -1,6 +1,6 @@
 //! Parse a Patch
 
-use super::{Filename, Hunk, HunkRange, Line, NO_NEWLINE_AT_EOF};
+use super::{Filename, GitIndex, Hunk, HunkRange, Line, NO_NEWLINE_AT_EOF};
 use crate::{patch::Patch, utils::LineIter};
 use std::{borrow::Cow, fmt};
 
@@ -57,26 +57,121 @@ pub fn parse<'a>(input: &'a str) -> Result<Patch<'a>> {
     let header = patch_header(&mut parser)?;
     let hunks = hunks(&mut parser)?;
 
-    Ok(Patch::new(header.0, header.1, hunks))
+    let mut patch = Patch::new(header.original, header.modified, hunks);
+    patch.mode_change = header.mode_change;
+    patch.rename = header.rename;
+    patch.git_index = header.git_index;
+    Ok(patch)
 }
 
-fn patch_header<'a>(parser: &mut Parser<'a>) -> Result<(Cow<'a, str>, Cow<'a, str>)> {
-    skip_header_preamble(parser)?;
-    let filename1 = parse_filename("--- ", parser.next()?)?;
-    let filename2 = parse_filename("+++ ", parser.next()?)?;
-    Ok((filename1, filename2))
+struct Header<'a> {
+    original: Cow<'a, str>,
+    modified: Cow<'a, str>,
+    mode_change: Option<(u32, u32)>,
+    rename: Option<(Cow<'a, str>, Cow<'a, str>)>,
+    git_index: Option<GitIndex<'a>>,
 }
 
-// Skip to the first "--- " line, skipping any preamble lines like "diff --git", etc.
-fn skip_header_preamble<'a>(parser: &mut Parser<'a>) -> Result<()> {
+fn patch_header<'a>(parser: &mut Parser<'a>) -> Result<Header<'a>> {
+    let preamble = header_preamble(parser)?;
+    let mode_change = preamble.old_mode.zip(preamble.new_mode);
+    let rename = preamble.rename_from.zip(preamble.rename_to);
+
+    // Git omits the "---" and "+++" lines when only the name or mode of a file changes
+    if parser.peek().is_none() {
+        let names = rename.or(preamble.git_names.filter(|_| mode_change.is_some()));
+        if let Some((original, modified)) = names {
+            return Ok(Header {
+                original: original.into(),
+                modified: modified.into(),
+                mode_change,
+                rename: rename.map(|(from, to)| (from.into(), to.into())),
+                git_index: preamble.git_index,
+            });
+        }
+    }
+
+    let original = parse_filename("--- ", parser.next()?)?;
+    let modified = parse_filename("+++ ", parser.next()?)?;
+    Ok(Header {
+        original,
+        modified,
+        mode_change,
+        rename: rename.map(|(from, to)| (from.into(), to.into())),
+        git_index: preamble.git_index,
+    })
+}
+
+// The information found in git's extended header lines
+#[derive(Default)]
+struct Preamble<'a> {
+    git_names: Option<(&'a str, &'a str)>,
+    rename_from: Option<&'a str>,
+    rename_to: Option<&'a str>,
+    old_mode: Option<u32>,
+    new_mode: Option<u32>,
+    git_index: Option<GitIndex<'a>>,
+}
+
+// Skip to the first "--- " line, skipping any preamble lines like "diff --git", etc. Git's
+// extended header lines describing renames and mode changes are recorded. Lines which only start
+// like one of them, e.g. in the commit message of an email patch, are skipped like any other line.
+fn header_preamble<'a>(parser: &mut Parser<'a>) -> Result<Preamble<'a>> {
+    let mut preamble = Preamble::default();
+
     while let Some(line) = parser.peek() {
         if line.starts_with("--- ") {
             break;
         }
-        parser.next()?;
+        let line = parser.next()?;
+        let line = line.strip_suffix('\n').unwrap_or(line);
+
+        if let Some(names) = line.strip_prefix("diff --git ") {
+            // Unquoted names are separated by " b/", which is ambiguous if the names contain it
+            preamble.git_names = names
+                .find(" b/")
+                .map(|idx| (&names[..idx], &names[idx + 1..]));
+        } else if let Some(name) = line.strip_prefix("rename from ") {
+            preamble.rename_from = Some(name);
+        } else if let Some(name) = line.strip_prefix("rename to ") {
+            preamble.rename_to = Some(name);
+        } else if let Some(mode) = line.strip_prefix("old mode ") {
+            if let Ok(mode) = mode_bits(mode) {
+                preamble.old_mode = Some(mode);
+            }
+        } else if let Some(mode) = line.strip_prefix("new mode ") {
+            if let Ok(mode) = mode_bits(mode) {
+                preamble.new_mode = Some(mode);
+            }
+        } else if let Some(index) = line.strip_prefix("index ") {
+            if let Ok(index) = git_index(index) {
+                preamble.git_index = Some(index);
+            }
+        }
     }
 
-    Ok(())
+    Ok(preamble)
+}
+
+// Parse the `<old-sha>..<new-sha>` and optional mode of an `index` line
+fn git_index(s: &str) -> Result<GitIndex<'_>> {
+    let (shas, mode) = match s.find(' ') {
+        Some(idx) => (&s[..idx], Some(mode_bits(&s[idx + 1..])?)),
+        None => (s, None),
+    };
+    let idx = shas
+        .find("..")
+        .ok_or_else(|| ParsePatchError::new("can't parse index line"))?;
+
+    Ok(GitIndex {
+        old: shas[..idx].into(),
+        new: shas[idx + 2..].into(),
+        mode,
+    })
+}
+
+fn mode_bits(s: &str) -> Result<u32> {
+    u32::from_str_radix(s, 8).map_err(|_| ParsePatchError::new("can't parse file mode"))
 }
 
 fn parse_filename<'a>(prefix: &str, line: &'a str) -> Result<Cow<'a, str>> {
@@ -132,8 +227,8 @@ fn escaped_filename(escaped: &str) -> Result<Cow<'_, str>> {
 }
 
 fn strip_prefix<'a>(s: &'a str, prefix: &str) -> Result<&'a str> {
-    if s.starts_with(prefix) {
-        Ok(&s[prefix.len()..])
+    if let Some(stripped) = s.strip_prefix(prefix) {
+        Ok(stripped)
     } else {
         let e = format!("prefix doesn't match: prefix: {:?} input: {:?}", prefix, s);
         Err(ParsePatchError::new(e))
@@ -175,15 +270,22 @@ fn hunk<'a>(parser: &mut Parser<'a>) -> Result<Hunk<'a>> {
         return Err(ParsePatchError::new("Hunk header does not match hunk"));
     }
 
-    Ok(Hunk::new(range1, range2, function_context, lines))
+    Ok(Hunk::new(
+        range1,
+        range2,
+        function_context.map(Cow::Borrowed),
+        lines,
+    ))
 }
 
-fn hunk_header<'a>(input: &'a str) -> Result<(HunkRange, HunkRange, Option<&'a str>)> {
+fn hunk_header(input: &str) -> Result<(HunkRange, HunkRange, Option<&str>)> {
     let input = strip_prefix(input, "@@ ")?;
 
     let (ranges, function_context) = split_at_exclusive(input, " @@")
         .map_err(|_| ParsePatchError::new("hunk header unterminated"))?;
-    let function_context = strip_prefix(function_context, " ").ok();
+    let function_context = strip_prefix(function_context, " ")
+        .ok()
+        .map(|ctx| ctx.trim_end_matches('\n'));
 
     let (range1, range2) = split_at_exclusive(ranges, " ")?;
     let range1 = range(strip_prefix(range1, "-")?)?;
@@ -230,20 +332,20 @@ fn hunk_lines<'a>(parser: &mut Parser<'a>) -> Result<Vec<Line<'a>>> {
             break;
         } else if no_newline_context {
             return Err(ParsePatchError::new("expected end of hunk"));
-        } else if line.starts_with(' ') {
-            Line::Context(&line[1..])
+        } else if let Some(line) = line.strip_prefix(' ') {
+            Line::Context(line)
         } else if *line == "\n" {
             Line::Context(line)
-        } else if line.starts_with('-') {
+        } else if let Some(line) = line.strip_prefix('-') {
             if no_newline_delete {
                 return Err(ParsePatchError::new("expected no more deleted lines"));
             }
-            Line::Delete(&line[1..])
-        } else if line.starts_with('+') {
+            Line::Delete(line)
+        } else if let Some(line) = line.strip_prefix('+') {
             if no_newline_insert {
                 return Err(ParsePatchError::new("expected no more inserted lines"));
             }
-            Line::Insert(&line[1..])
+            Line::Insert(line)
         } else if line.starts_with(NO_NEWLINE_AT_EOF) {
             let last_line = lines.pop().ok_or_else(|| {
                 ParsePatchError::new("unexpected 'No newline at end of file' line")
@@ -273,9 +375,9 @@ fn hunk_lines<'a>(parser: &mut Parser<'a>) -> Result<Vec<Line<'a>>> {
     Ok(lines)
 }
 
-fn strip_newline<'a>(s: &'a str) -> Result<&'a str> {
-    if s.ends_with('\n') {
-        Ok(&s[..s.len() - 1])
+fn strip_newline(s: &str) -> Result<&str> {
+    if let Some(stripped) = s.strip_suffix('\n') {
+        Ok(stripped)
     } else {
         Err(ParsePatchError::new("missing newline"))
     }
@@ -0,0 +1,153 @@
+//! Render a Patch in one of several rich text formats
+
+use super::{Patch, PatchFormatter};
+use crate::utils::LineIter;
+use std::fmt::Write;
+
+/// The output formats supported by [`Patch::to_rich_text`]
+///
+/// [`Patch::to_rich_text`]: struct.Patch.html#method.to_rich_text
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RichTextFormat {
+    /// An HTML `<pre>` element with inline styles coloring each line
+    Html,
+    /// A Rich Text Format document with colored text, e.g. for pasting into a word processor
+    Rtf,
+    /// Text with ANSI escape codes, the output of [`PatchFormatter::with_color`]
+    ///
+    /// [`PatchFormatter::with_color`]: struct.PatchFormatter.html#method.with_color
+    AnsiTerminal,
+    /// The unified diff format, the same as the `Display` output of a `Patch`
+    Plain,
+}
+
+// The kind of each line of a formatted patch, which determines how it is styled
+#[derive(Clone, Copy)]
+enum Kind {
+    FileHeader,
+    HunkHeader,
+    Context,
+    Delete,
+    Insert,
+}
+
+pub fn to_rich_text(patch: &Patch<'_>, format: RichTextFormat) -> String {
+    match format {
+        RichTextFormat::Html => to_html(patch),
+        RichTextFormat::Rtf => to_rtf(patch),
+        RichTextFormat::AnsiTerminal => PatchFormatter::new()
+            .with_color()
+            .fmt_patch(patch)
+            .to_string(),
+        RichTextFormat::Plain => patch.to_string(),
+    }
+}
+
+// Split the unified diff output of a patch into lines, without their newlines, along with their
+// kind
+fn lines(text: &str) -> impl Iterator<Item = (Kind, &str)> {
+    // Everything before the first hunk is part of the file header
+    let mut in_header = true;
+    LineIter::new(text).map(move |line| {
+        in_header &= !line.starts_with("@@");
+        let kind = if in_header {
+            Kind::FileHeader
+        } else if line.starts_with("@@") {
+            Kind::HunkHeader
+        } else if line.starts_with('-') {
+            Kind::Delete
+        } else if line.starts_with('+') {
+            Kind::Insert
+        } else {
+            Kind::Context
+        };
+        (kind, line.strip_suffix('\n').unwrap_or(line))
+    })
+}
+
+fn to_html(patch: &Patch<'_>) -> String {
+    let text = patch.to_string();
+    let mut html = String::from("<pre class=\"diff\">\n");
+
+    for (kind, line) in lines(&text) {
+        let style = match kind {
+            Kind::FileHeader => "font-weight: bold",
+            Kind::HunkHeader => "color: #0086b3",
+            Kind::Delete => "color: #cb2431; background-color: #ffeef0",
+            Kind::Insert => "color: #22863a; background-color: #f0fff4",
+            Kind::Context => {
+                let _ = writeln!(html, "{}", escape_html(line));
+                continue;
+            }
+        };
+        let _ = writeln!(
+            html,
+            "<span style=\"{}\">{}</span>",
+            style,
+            escape_html(line)
+        );
+    }
+    html.push_str("</pre>\n");
+
+    html
+}
+
+fn escape_html(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn to_rtf(patch: &Patch<'_>) -> String {
+    let text = patch.to_string();
+    // Colors 1, 2 and 3 of the table are used for deleted lines, inserted lines and hunk headers
+    let mut rtf = String::from(
+        "{\\rtf1\\ansi\\deff0{\\fonttbl{\\f0\\fmodern Courier New;}}\
+         {\\colortbl;\\red203\\green36\\blue49;\\red34\\green134\\blue58;\\red0\\green134\\blue179;}\
+         \\f0\\fs20\n",
+    );
+
+    for (kind, line) in lines(&text) {
+        let style = match kind {
+            Kind::FileHeader => "\\b ",
+            Kind::HunkHeader => "\\cf3 ",
+            Kind::Delete => "\\cf1 ",
+            Kind::Insert => "\\cf2 ",
+            Kind::Context => "",
+        };
+        let _ = writeln!(rtf, "{{{}{}}}\\line", style, escape_rtf(line));
+    }
+    rtf.push('}');
+
+    rtf
+}
+
+// Escape the characters with a special meaning in RTF and encode anything outside of ASCII as
+// UTF-16 code units, which RTF represents as signed 16-bit numbers
+fn escape_rtf(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '\\' | '{' | '}' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '\t' => escaped.push_str("\\tab "),
+            c if c.is_ascii() && !c.is_ascii_control() => escaped.push(c),
+            c => {
+                for unit in c.encode_utf16(&mut [0; 2]) {
+                    let _ = write!(escaped, "\\u{}?", *unit as i16);
+                }
+            }
+        }
+    }
+    escaped
+}
@@ -0,0 +1,116 @@
+//! Normalize a Patch so it can be stored without leaking information about the machine it was
+//! created on
+
+use super::{Filename, Hunk, Line, Patch, PatchBuf};
+use std::borrow::Cow;
+
+// Directories which contain the name of the current user
+const HOME_PREFIXES: &[&str] = &["/home/", "/Users/", "C:/Users/", "C:\\Users\\"];
+
+pub fn sanitize_for_storage(patch: &Patch<'_>) -> PatchBuf {
+    // Normalize the contents of every line first, since the sanitized hunks need to borrow them
+    let contents: Vec<Vec<String>> = patch
+        .hunks
+        .iter()
+        .map(|hunk| {
+            let mut old_line = hunk.old_range.position();
+            let mut new_line = hunk.new_range.position();
+            hunk.lines
+                .iter()
+                .map(|line| match *line {
+                    Line::Context(text) => {
+                        let first = old_line == 0 || new_line == 0;
+                        old_line += 1;
+                        new_line += 1;
+                        sanitize_line(text, first)
+                    }
+                    Line::Delete(text) => {
+                        old_line += 1;
+                        sanitize_line(text, old_line == 1)
+                    }
+                    Line::Insert(text) => {
+                        new_line += 1;
+                        sanitize_line(text, new_line == 1)
+                    }
+                })
+                .collect()
+        })
+        .collect();
+
+    let hunks = patch
+        .hunks
+        .iter()
+        .zip(&contents)
+        .map(|(hunk, texts)| Hunk {
+            old_range: hunk.old_range,
+            new_range: hunk.new_range,
+            function_context: hunk.function_context.clone(),
+            lines: hunk
+                .lines
+                .iter()
+                .zip(texts)
+                .map(|(line, text)| match line {
+                    Line::Context(_) => Line::Context(text),
+                    Line::Delete(_) => Line::Delete(text),
+                    Line::Insert(_) => Line::Insert(text),
+                })
+                .collect(),
+        })
+        .collect();
+
+    let sanitized = Patch {
+        original: Filename(sanitize_path(&patch.original).into()),
+        modified: Filename(sanitize_path(&patch.modified).into()),
+        hunks,
+        mode_change: patch.mode_change,
+        rename: patch.rename.as_ref().map(|(from, to)| {
+            (
+                Cow::Owned(sanitize_path(from)),
+                Cow::Owned(sanitize_path(to)),
+            )
+        }),
+        git_index: patch.git_index.clone(),
+    };
+    PatchBuf::from(&sanitized)
+}
+
+// Strip a byte order mark from the first line of a file and convert a CRLF line ending to LF
+fn sanitize_line(line: &str, first: bool) -> String {
+    let line = if first {
+        line.strip_prefix('\u{feff}').unwrap_or(line)
+    } else {
+        line
+    };
+    match line.strip_suffix("\r\n") {
+        Some(line) => format!("{}\n", line),
+        None => line.to_owned(),
+    }
+}
+
+// Turn an absolute path into a relative one, dropping the home directory if the path is inside
+// of it
+fn sanitize_path(path: &str) -> String {
+    if path == "/dev/null" {
+        return path.to_owned();
+    }
+
+    let mut path = path;
+    for prefix in HOME_PREFIXES {
+        if let Some(rest) = path.strip_prefix(prefix) {
+            // Skip the user name
+            path = rest
+                .find(['/', '\\'].as_ref())
+                .map_or("", |idx| &rest[idx..]);
+            break;
+        }
+    }
+    if let Some(rest) = path.strip_prefix("~") {
+        path = rest;
+    }
+    // Drop a Windows drive letter
+    if path.len() >= 2 && path.as_bytes()[1] == b':' && path.as_bytes()[0].is_ascii_alphabetic() {
+        path = &path[2..];
+    }
+
+    path.trim_start_matches(['/', '\\'].as_ref()).to_owned()
+}
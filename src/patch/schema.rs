@@ -0,0 +1,173 @@
+//! The serde implementations for the JSON form of a Patch, see `json_schema` for its description
+
+use super::{buf::PatchBuf, GitIndex, Hunk, HunkRange, Line, Patch};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::borrow::Cow;
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PatchJson<'a> {
+    original: Cow<'a, str>,
+    modified: Cow<'a, str>,
+    hunks: Vec<HunkJson<'a>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    mode_change: Option<ModeChangeJson>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    rename: Option<RenameJson<'a>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    git_index: Option<GitIndexJson<'a>>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct HunkJson<'a> {
+    old_start: usize,
+    old_len: usize,
+    new_start: usize,
+    new_len: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    function_context: Option<Cow<'a, str>>,
+    lines: Vec<LineJson<'a>>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LineJson<'a> {
+    kind: LineKind,
+    content: Cow<'a, str>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum LineKind {
+    Context,
+    Delete,
+    Insert,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ModeChangeJson {
+    old: u32,
+    new: u32,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RenameJson<'a> {
+    from: Cow<'a, str>,
+    to: Cow<'a, str>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct GitIndexJson<'a> {
+    old: Cow<'a, str>,
+    new: Cow<'a, str>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    mode: Option<u32>,
+}
+
+impl<'a> From<&'a Patch<'_>> for PatchJson<'a> {
+    fn from(patch: &'a Patch<'_>) -> Self {
+        let hunks = patch
+            .hunks
+            .iter()
+            .map(|hunk| HunkJson {
+                old_start: hunk.old_range.start,
+                old_len: hunk.old_range.len,
+                new_start: hunk.new_range.start,
+                new_len: hunk.new_range.len,
+                function_context: hunk.function_context().map(Cow::Borrowed),
+                lines: hunk
+                    .lines
+                    .iter()
+                    .map(|line| {
+                        let (kind, content) = match *line {
+                            Line::Context(l) => (LineKind::Context, l),
+                            Line::Delete(l) => (LineKind::Delete, l),
+                            Line::Insert(l) => (LineKind::Insert, l),
+                        };
+                        LineJson {
+                            kind,
+                            content: Cow::Borrowed(content),
+                        }
+                    })
+                    .collect(),
+            })
+            .collect();
+
+        Self {
+            original: Cow::Borrowed(&patch.original),
+            modified: Cow::Borrowed(&patch.modified),
+            hunks,
+            mode_change: patch
+                .mode_change
+                .map(|(old, new)| ModeChangeJson { old, new }),
+            rename: patch.rename.as_ref().map(|(from, to)| RenameJson {
+                from: Cow::Borrowed(from),
+                to: Cow::Borrowed(to),
+            }),
+            git_index: patch.git_index.as_ref().map(|index| GitIndexJson {
+                old: Cow::Borrowed(&index.old),
+                new: Cow::Borrowed(&index.new),
+                mode: index.mode,
+            }),
+        }
+    }
+}
+
+impl Serialize for Patch<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        PatchJson::from(self).serialize(serializer)
+    }
+}
+
+impl Serialize for PatchBuf {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.as_patch().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for PatchBuf {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let json = PatchJson::deserialize(deserializer)?;
+
+        let mut hunks = Vec::with_capacity(json.hunks.len());
+        for hunk in &json.hunks {
+            let lines: Vec<Line<'_>> = hunk
+                .lines
+                .iter()
+                .map(|line| match line.kind {
+                    LineKind::Context => Line::Context(&line.content),
+                    LineKind::Delete => Line::Delete(&line.content),
+                    LineKind::Insert => Line::Insert(&line.content),
+                })
+                .collect();
+            if super::hunk_lines_count(&lines) != (hunk.old_len, hunk.new_len) {
+                return Err(de::Error::custom(
+                    "hunk lengths don't match the number of its lines",
+                ));
+            }
+            hunks.push(Hunk {
+                old_range: HunkRange::new(hunk.old_start, hunk.old_len),
+                new_range: HunkRange::new(hunk.new_start, hunk.new_len),
+                function_context: hunk.function_context.as_deref().map(Cow::Borrowed),
+                lines,
+            });
+        }
+
+        let mut patch = Patch::new(&*json.original, &*json.modified, hunks);
+        patch.mode_change = json.mode_change.map(|mode| (mode.old, mode.new));
+        patch.rename = json
+            .rename
+            .as_ref()
+            .map(|rename| (Cow::Borrowed(&*rename.from), Cow::Borrowed(&*rename.to)));
+        patch.git_index = json.git_index.as_ref().map(|index| GitIndex {
+            old: Cow::Borrowed(&index.old),
+            new: Cow::Borrowed(&index.new),
+            mode: index.mode,
+        });
+        Ok(PatchBuf::from(&patch))
+    }
+}
@@ -0,0 +1,186 @@
+//! Render a Patch in two columns for display in a terminal
+
+use super::{
+    blocks::{blocks, Block},
+    format::hunk_header,
+    Patch,
+};
+use crate::{
+    diff::DiffOptions,
+    range::DiffRange,
+    token::{CharClassifier, TokenClassifier},
+};
+use ansi_term::{Color, Style};
+use std::{fmt::Write, iter};
+
+// The number of columns between tab stops
+const TAB_WIDTH: usize = 8;
+
+// A piece of the text of a line, and whether it is highlighted as changed
+pub(super) type Segment = (String, bool);
+
+pub fn to_terminal_side_by_side(patch: &Patch<'_>, width: usize) -> String {
+    // Each row is made up of the two columns separated by a marker like " | "
+    let column = width.saturating_sub(3) / 2;
+    let opts = DiffOptions::default();
+    let mut output = String::new();
+
+    let header = Style::new().bold();
+    push_row(
+        &mut output,
+        column,
+        ' ',
+        (&plain(&format!("--- {}", patch.original)), header, header),
+        (&plain(&format!("+++ {}", patch.modified)), header, header),
+    );
+
+    let mut blocks = blocks(patch).into_iter().peekable();
+    for (hunk_index, hunk) in patch.hunks.iter().enumerate() {
+        let cyan = Color::Cyan.normal();
+        output.push_str(&cell(&plain(&hunk_header(hunk)), width, cyan, cyan, false));
+        output.push('\n');
+
+        while let Some(block) = blocks.next_if(|block| block.hunk_index() == hunk_index) {
+            let block = match block {
+                Block::Context(block) => {
+                    let context = Style::new();
+                    for line in block.contents() {
+                        push_row(
+                            &mut output,
+                            column,
+                            ' ',
+                            (&plain(line), context, context),
+                            (&plain(line), context, context),
+                        );
+                    }
+                    continue;
+                }
+                Block::Edit(block) => block,
+            };
+
+            // Show the nth deleted line next to the nth inserted line
+            let deleted: Vec<_> = block.deleted().collect();
+            let inserted: Vec<_> = block.inserted().collect();
+            let red = (Color::Red.normal(), Color::Red.reverse());
+            let green = (Color::Green.normal(), Color::Green.reverse());
+            for i in 0..deleted.len().max(inserted.len()) {
+                match (deleted.get(i), inserted.get(i)) {
+                    (Some(old), Some(new)) => {
+                        let (old, new) = char_diff(&opts, old, new);
+                        push_row(
+                            &mut output,
+                            column,
+                            '|',
+                            (&old, red.0, red.1),
+                            (&new, green.0, green.1),
+                        );
+                    }
+                    (Some(old), None) => {
+                        let blank = Style::new();
+                        push_row(
+                            &mut output,
+                            column,
+                            '<',
+                            (&plain(old), red.0, red.1),
+                            (&[], blank, blank),
+                        );
+                    }
+                    (None, Some(new)) => {
+                        let blank = Style::new();
+                        push_row(
+                            &mut output,
+                            column,
+                            '>',
+                            (&[], blank, blank),
+                            (&plain(new), green.0, green.1),
+                        );
+                    }
+                    (None, None) => unreachable!(),
+                }
+            }
+        }
+    }
+
+    output
+}
+
+// A single segment which isn't highlighted
+pub(super) fn plain(text: &str) -> Vec<Segment> {
+    vec![(text.to_owned(), false)]
+}
+
+// Split two lines into segments, highlighting the characters which differ between them
+pub(super) fn char_diff(opts: &DiffOptions, old: &str, new: &str) -> (Vec<Segment>, Vec<Segment>) {
+    let old_chars = CharClassifier.split_into_tokens(old);
+    let new_chars = CharClassifier.split_into_tokens(new);
+
+    let (mut old, mut new) = (Vec::new(), Vec::new());
+    for diff in opts.diff_slice(&old_chars, &new_chars) {
+        match diff {
+            DiffRange::Equal(o, n) => {
+                old.push((o.as_slice().concat(), false));
+                new.push((n.as_slice().concat(), false));
+            }
+            DiffRange::Delete(o) => old.push((o.as_slice().concat(), true)),
+            DiffRange::Insert(n) => new.push((n.as_slice().concat(), true)),
+        }
+    }
+    (old, new)
+}
+
+// Push a row made up of the left and right cells, each given as its segments along with the
+// styles of the plain and highlighted segments
+fn push_row(
+    output: &mut String,
+    column: usize,
+    marker: char,
+    left: (&[Segment], Style, Style),
+    right: (&[Segment], Style, Style),
+) {
+    output.push_str(&cell(left.0, column, left.1, left.2, true));
+    let _ = write!(output, " {} ", marker);
+    output.push_str(&cell(right.0, column, right.1, right.2, false));
+    // Don't leave trailing whitespace if the right cell is empty
+    let len = output.trim_end_matches(' ').len();
+    output.truncate(len);
+    output.push('\n');
+}
+
+// Render segments into a cell which is at most `width` characters wide, expanding tabs and
+// truncating anything which doesn't fit. If `pad` is set, the cell is padded with spaces to be
+// exactly `width` characters wide. Every other `char` is counted as one column, regardless of its
+// display width.
+fn cell(segments: &[Segment], width: usize, plain: Style, highlight: Style, pad: bool) -> String {
+    let mut output = String::new();
+    let mut used = 0;
+
+    'segments: for (text, highlighted) in segments {
+        let mut run = String::new();
+        for c in text.chars() {
+            let (c, len) = match c {
+                '\n' | '\r' => continue,
+                '\t' => (' ', TAB_WIDTH - used % TAB_WIDTH),
+                c => (c, 1),
+            };
+            if used + len > width {
+                push_styled(&mut output, &run, *highlighted, plain, highlight);
+                break 'segments;
+            }
+            run.extend(iter::repeat(c).take(len));
+            used += len;
+        }
+        push_styled(&mut output, &run, *highlighted, plain, highlight);
+    }
+
+    if pad {
+        output.extend(iter::repeat(' ').take(width - used));
+    }
+    output
+}
+
+fn push_styled(output: &mut String, text: &str, highlighted: bool, plain: Style, highlight: Style) {
+    if !text.is_empty() {
+        let style = if highlighted { highlight } else { plain };
+        let _ = write!(output, "{}", style.paint(text));
+    }
+}
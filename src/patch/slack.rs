@@ -0,0 +1,48 @@
+//! Format a Patch as a Slack message
+
+use super::Patch;
+use crate::utils::LineIter;
+
+// The maximum number of characters in a Slack message
+const MESSAGE_LIMIT: usize = 4000;
+
+const CODE_BLOCK: &str = "```";
+
+pub fn to_slack_message(patch: &Patch<'_>) -> String {
+    let text = patch.to_string();
+    let lines: Vec<String> = LineIter::new(text.as_str()).map(escape).collect();
+
+    let mut message = format!("{}\n", CODE_BLOCK);
+    let mut len = message.chars().count();
+    let mut included = 0;
+
+    for line in &lines {
+        let remaining = lines.len() - included - 1;
+        let needed = line.chars().count() + CODE_BLOCK.len() + suffix(remaining).len();
+        if len + needed > MESSAGE_LIMIT {
+            break;
+        }
+        message.push_str(line);
+        len += line.chars().count();
+        included += 1;
+    }
+
+    message.push_str(CODE_BLOCK);
+    message.push_str(&suffix(lines.len() - included));
+    message
+}
+
+fn suffix(omitted: usize) -> String {
+    match omitted {
+        0 => String::new(),
+        1 => "\n...and 1 more line".to_owned(),
+        n => format!("\n...and {} more lines", n),
+    }
+}
+
+// Slack requires `&`, `<` and `>` to be escaped, even in code blocks
+fn escape(line: &str) -> String {
+    line.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
@@ -0,0 +1,54 @@
+//! Render text templates which refer to the contents of a Patch
+
+use super::Patch;
+use crate::apply::{apply, ApplyError};
+
+pub fn apply_template(
+    patch: &Patch<'_>,
+    template: &str,
+    original: &str,
+) -> Result<String, ApplyError> {
+    let mut output = String::with_capacity(template.len());
+    // Only apply the patch if the template refers to the modified text
+    let mut modified = None;
+    let mut rest = template;
+
+    while let Some(start) = rest.find("{{") {
+        let end = match rest[start..].find("}}") {
+            Some(end) => start + end,
+            None => break,
+        };
+        output.push_str(&rest[..start]);
+
+        let token = &rest[start + 2..end];
+        match token {
+            "original" => output.push_str(original),
+            "modified" => {
+                if modified.is_none() {
+                    modified = Some(apply(original, patch)?);
+                }
+                output.push_str(modified.as_deref().unwrap_or_default());
+            }
+            "original_name" => output.push_str(patch.original()),
+            "modified_name" => output.push_str(patch.modified()),
+            "patch" => output.push_str(&patch.to_string()),
+            _ => match hunk(patch, token) {
+                Some(hunk) => output.push_str(&hunk),
+                // Leave unknown tokens untouched
+                None => output.push_str(&rest[start..end + 2]),
+            },
+        }
+
+        rest = &rest[end + 2..];
+    }
+    output.push_str(rest);
+
+    Ok(output)
+}
+
+// Render the hunk referred to by a `hunk:N` token, where `N` starts at 1
+fn hunk(patch: &Patch<'_>, token: &str) -> Option<String> {
+    let n: usize = token.strip_prefix("hunk:")?.parse().ok()?;
+    let hunk = patch.hunks.get(n.checked_sub(1)?)?;
+    Some(hunk.to_string())
+}
@@ -0,0 +1,974 @@
+use super::*;
+
+#[test]
+fn strip_path_prefix() {
+    let cases = [
+        ("a/src/lib.rs", 0, "a/src/lib.rs"),
+        ("a/src/lib.rs", 1, "src/lib.rs"),
+        ("a/src/lib.rs", 2, "lib.rs"),
+        ("a/src/lib.rs", 5, "lib.rs"),
+        ("/usr/src/lib.rs", 1, "usr/src/lib.rs"),
+        ("a//src/lib.rs", 1, "src/lib.rs"),
+        ("/dev/null", 1, "/dev/null"),
+    ];
+
+    for &(path, levels, expected) in &cases {
+        let patch = Patch::new(path, path, Vec::new());
+        let stripped = patch.strip_path_prefix(levels);
+        assert_eq!(stripped.original(), expected, "{} -p{}", path, levels);
+        assert_eq!(stripped.modified(), expected, "{} -p{}", path, levels);
+    }
+}
+
+#[test]
+fn rewrite_filenames() {
+    let s = "\
+--- a/file
++++ b/file
+@@ -1 +1 @@
+-old
++new
+";
+    let patch = Patch::from_str(s).unwrap();
+    let rewritten = patch.rewrite_filenames(|name| name.replace('/', "_"));
+
+    assert_eq!(rewritten.original(), "a_file");
+    assert_eq!(rewritten.modified(), "b_file");
+    assert_eq!(rewritten.hunks(), patch.hunks());
+}
+
+#[test]
+fn normalize_hunks() {
+    let original = "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\n";
+    let modified = "A\nA\nb\nc\nd\ne\nf\ng\nh\ni\n";
+    let full = crate::diff::DiffOptions::default()
+        .set_context_len(1)
+        .create_patch(original, modified);
+    assert_eq!(full.hunks().len(), 2);
+
+    // Selecting only the second hunk leaves its new range offset by the first hunk
+    let mut selection = Patch::new("original", "modified", vec![full.hunks()[1].clone()]);
+    assert_eq!(selection.hunks()[0].new_range(), HunkRange::new(10, 1));
+    selection.normalize_hunks();
+    assert_eq!(selection.hunks()[0].old_range(), HunkRange::new(9, 2));
+    assert_eq!(selection.hunks()[0].new_range(), HunkRange::new(9, 1));
+    selection
+        .verify_against(original, "a\nb\nc\nd\ne\nf\ng\nh\ni\n")
+        .unwrap();
+
+    // Ranges whose lengths don't match the lines they contain are fixed up
+    let mut patch = Patch::new(
+        "original",
+        "modified",
+        vec![
+            Hunk {
+                old_range: HunkRange::new(1, 5),
+                new_range: HunkRange::new(1, 5),
+                function_context: None,
+                lines: vec![Line::Delete("a\n"), Line::Context("b\n")],
+            },
+            Hunk {
+                old_range: HunkRange::new(3, 0),
+                new_range: HunkRange::new(7, 7),
+                function_context: None,
+                lines: vec![Line::Insert("x\n")],
+            },
+        ],
+    );
+    patch.normalize_hunks();
+    assert_eq!(patch.hunks()[0].old_range(), HunkRange::new(1, 2));
+    assert_eq!(patch.hunks()[0].new_range(), HunkRange::new(1, 1));
+    assert_eq!(patch.hunks()[1].old_range(), HunkRange::new(3, 0));
+    assert_eq!(patch.hunks()[1].new_range(), HunkRange::new(3, 1));
+    patch.verify_against("a\nb\nc\n", "b\nc\nx\n").unwrap();
+
+    // Normalizing a consistent patch is a no-op
+    let mut normalized = full.clone();
+    normalized.normalize_hunks();
+    assert_eq!(normalized, full);
+}
+
+#[test]
+fn hunk_display() {
+    let s = "\
+--- a/file
++++ b/file
+@@ -1,3 +1,3 @@ fn main() {
+ a
+
+-b
++c
+";
+    let patch = Patch::from_str(s).unwrap();
+    let hunk = &patch.hunks()[0];
+
+    assert_eq!(hunk.to_string(), &s[22..]);
+    assert_eq!(hunk.to_display_string("a/file", "b/file"), s);
+    assert_eq!(
+        hunk.to_display_string("old\tname", "new"),
+        format!("--- \"old\\\tname\"\n+++ new\n{}", hunk)
+    );
+
+    let f = PatchFormatter::new().with_line_markers('=', '<', '>');
+    let expected = "\
+@@ -1,3 +1,3 @@ fn main() {
+=a
+=
+<b
+>c
+";
+    assert_eq!(f.fmt_hunk(hunk).to_string(), expected);
+}
+
+#[test]
+fn hunks_around_line() {
+    let s = "\
+--- original
++++ modified
+@@ -2,3 +2,2 @@
+ b
+-c
+ d
+@@ -10,0 +10,1 @@
++x
+@@ -20,2 +20,2 @@
+-t
++T
+ u
+";
+    let patch = Patch::from_str(s).unwrap();
+    let starts = |hunks: Vec<&Hunk<'_>>| -> Vec<usize> {
+        hunks.iter().map(|hunk| hunk.old_range().start()).collect()
+    };
+
+    assert_eq!(starts(patch.hunks_around_line(3, 0)), [2]);
+    assert_eq!(starts(patch.hunks_around_line(6, 1)), Vec::<usize>::new());
+    assert_eq!(starts(patch.hunks_around_line(6, 2)), [2]);
+    assert_eq!(starts(patch.hunks_around_line(11, 1)), [10]);
+    // Closest first, ties broken by the order in the patch
+    assert_eq!(starts(patch.hunks_around_line(16, 6)), [20, 10]);
+    assert_eq!(starts(patch.hunks_around_line(15, 5)), [10, 20]);
+    assert_eq!(starts(patch.hunks_around_line(7, 3)), [2, 10]);
+    assert_eq!(starts(patch.hunks_around_line(30, 100)), [20, 10, 2]);
+}
+
+#[test]
+fn split_at_line() {
+    let original = "a\nb\nc\nd\ne\n";
+    let modified = "b\nc\nC\nd\ne\nf\n";
+    let patch = crate::create_patch(original, modified);
+    assert_eq!(patch.hunks().len(), 1);
+    let hunk = &patch.hunks()[0];
+
+    // Every valid split point produces hunks which apply in sequence
+    let mut splits = 0;
+    for i in 0..=hunk.lines().len() {
+        if let Some((first, second)) = hunk.split_at_line(i) {
+            splits += 1;
+            assert_eq!(first.lines().len(), i);
+            assert_eq!(second.lines(), &hunk.lines()[i..]);
+            assert_eq!(first.old_range().len() + second.old_range().len(), 5);
+            assert_eq!(first.new_range().len() + second.new_range().len(), 6);
+
+            let split = Patch::new("original", "modified", vec![first, second]);
+            assert_eq!(crate::apply(original, &split).unwrap(), modified);
+        }
+    }
+    // The hunk is `-a b c +C d e +f` and every line after the first borders a context line
+    assert_eq!(splits, 6);
+
+    // Splitting between a deletion and an insertion isn't allowed
+    let patch = crate::create_patch("a\nb\n", "a\nB\n");
+    let hunk = &patch.hunks()[0];
+    assert!(hunk.split_at_line(2).is_none());
+    let (first, second) = hunk.split_at_line(1).unwrap();
+    assert_eq!(first.to_string(), "@@ -1 +1 @@\n a\n");
+    assert_eq!(second.to_string(), "@@ -2 +2 @@\n-b\n+B\n");
+
+    // Empty ranges refer to the preceding line
+    let patch = crate::create_patch("a\n", "a\nb\n");
+    let (first, second) = patch.hunks()[0].split_at_line(1).unwrap();
+    assert_eq!(first.to_string(), "@@ -1 +1 @@\n a\n");
+    assert_eq!(second.to_string(), "@@ -1,0 +2 @@\n+b\n");
+}
+
+#[test]
+fn estimate_compression_ratio() {
+    let patch = crate::create_patch("a\nb\n", "a\nb\n");
+    assert_eq!(patch.estimate_compression_ratio(), 0.0);
+
+    // A single repeated byte has no entropy so the estimate is clamped to one byte
+    let patch = DiffOptions::zero_context().create_patch("", "aaaa");
+    assert_eq!(patch.estimate_compression_ratio(), 1.0 / 4.0);
+
+    // 8 distinct bytes with equal frequency need 3 bits each, so the patch is 3 bytes
+    let patch = DiffOptions::zero_context().create_patch("", "abcdefgh");
+    assert_eq!(patch.estimate_compression_ratio(), 3.0 / 8.0);
+
+    // The changed bytes "ab\nba\n" are 3 distinct bytes with equal frequency, while both texts
+    // are 11 bytes long
+    let original = "context\nab\n";
+    let modified = "context\nba\n";
+    let ratio = crate::create_patch(original, modified).estimate_compression_ratio();
+    let expected = 6.0 * 3f64.log2() / 8.0 / 22.0;
+    assert!((ratio - expected).abs() < 1e-12);
+
+    // Context lines lower the ratio without adding to the compressed size
+    let without_context = DiffOptions::zero_context()
+        .create_patch(original, modified)
+        .estimate_compression_ratio();
+    assert!((without_context - 6.0 * 3f64.log2() / 8.0 / 6.0).abs() < 1e-12);
+    assert!(ratio < without_context);
+}
+
+#[test]
+fn describe() {
+    let original: String = (1..=40).map(|i| format!("  line {}\n", i)).collect();
+    let modified = original
+        .replace(
+            "  line 10\n  line 11\n  line 12\n",
+            "  LINE 10\n  LINE 11\n  LINE 12\n",
+        )
+        .replace("  line 24\n", "  line 24\n  new a\n  new b\n")
+        .replace("  line 30\n", "");
+    let patch = crate::create_patch(&original, &modified);
+    assert_eq!(
+        patch.describe(&original),
+        "Replaces 3 lines starting at line 10, adds 2 lines at line 25, \
+         removes 1 line at line 30."
+    );
+
+    let original = "\
+fn one() {
+    1
+}
+
+fn two() {
+    2
+}
+";
+    let modified = "\
+fn one() {
+    1
+}
+
+fn two() {
+    let x = 2;
+    x
+}
+";
+    let patch = crate::create_patch(original, modified);
+    assert_eq!(
+        patch.describe(original),
+        "Replaces 1 line with 2 lines starting at line 6 in `fn two() {`."
+    );
+
+    let patch = crate::create_patch("a\n", "a\n");
+    assert_eq!(patch.describe("a\n"), "No changes.");
+}
+
+#[test]
+fn apply_template() {
+    let original = "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\n";
+    let modified = "A\nb\nc\nd\ne\nf\ng\nh\ni\nJ\n";
+    let patch = DiffOptions::default()
+        .set_context_len(1)
+        .create_patch(original, modified);
+
+    let template = "{{hunk:2}}{{hunk:1}}{{hunk:3}}{{hunk:0}}{{unknown}}{{modified_name";
+    assert_eq!(
+        patch.apply_template(template, original).unwrap(),
+        "@@ -9,2 +9,2 @@\n i\n-j\n+J\n@@ -1,2 +1,2 @@\n-a\n+A\n b\n\
+         {{hunk:3}}{{hunk:0}}{{unknown}}{{modified_name"
+    );
+    assert_eq!(
+        patch.apply_template("{{patch}}", "").unwrap(),
+        patch.to_string()
+    );
+
+    // The patch is only applied if the modified text is needed
+    assert_eq!(
+        patch.apply_template("[{{original}}]", "x\n").unwrap(),
+        "[x\n]"
+    );
+    assert!(patch.apply_template("{{modified}}", "x\n").is_err());
+    assert_eq!(
+        patch
+            .apply_template("{{modified}}{{modified}}", original)
+            .unwrap(),
+        modified.repeat(2)
+    );
+}
+
+#[test]
+fn total_byte_size() {
+    let s = "\
+--- a/file
++++ b/file
+@@ -1,2 +1,2 @@ fn main()
+ a
+-bb
++ccc
+";
+    let patch = Patch::from_str(s).unwrap();
+    assert_eq!(patch.total_byte_size(), 6 + 6 + 9 + 2 + 3 + 4);
+
+    let patch = Patch::new("", "", Vec::new());
+    assert_eq!(patch.total_byte_size(), 0);
+}
+
+#[test]
+fn split_context_from_edits() {
+    let original = "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\n";
+    let modified = "A\nb\nc\nd\nf\ng\nh\ni\nj\nk\n";
+    let patch = DiffOptions::default()
+        .set_context_len(1)
+        .create_patch(original, modified);
+    assert_eq!(patch.hunks().len(), 3);
+
+    let (context, edits) = patch.split_context_from_edits();
+    let context: Vec<_> = context
+        .iter()
+        .map(|block| {
+            (
+                block.hunk_index(),
+                block.old_line(),
+                block.new_line(),
+                block.lines().len(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        context,
+        [(0, 2, 2, 1), (1, 4, 4, 1), (1, 6, 5, 1), (2, 10, 9, 1)]
+    );
+
+    let edits: Vec<_> = edits
+        .iter()
+        .map(|block| {
+            (
+                block.hunk_index(),
+                block.old_line(),
+                block.new_line(),
+                block.deleted().collect::<String>(),
+                block.inserted().collect::<String>(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        edits,
+        [
+            (0, 1, 1, "a\n".to_owned(), "A\n".to_owned()),
+            (1, 5, 5, "e\n".to_owned(), String::new()),
+            (2, 11, 10, String::new(), "k\n".to_owned()),
+        ]
+    );
+}
+
+#[test]
+fn truncate_to_changed_region() {
+    let original: String = (0..30).map(|i| format!("{}\n", i)).collect();
+    let modified = original
+        .replace("\n5\n", "\nfive\n")
+        .replace("\n20\n", "\n")
+        .replace("\n26\n", "\n26\nnew\n");
+    let patch = crate::create_patch(&original, &modified);
+    assert_eq!(patch.hunks().len(), 3);
+
+    // The clipped patch turns the region of the original file into the same region of the
+    // modified file, which has as many lines as the original
+    let old_lines: Vec<&str> = LineIter::new(original.as_str()).collect();
+    let new_lines: Vec<&str> = LineIter::new(modified.as_str()).collect();
+    assert_eq!(old_lines.len(), new_lines.len());
+    let check = |padding, expected: ops::Range<usize>| {
+        let (clipped, region) = patch.truncate_to_changed_region(&original, padding);
+        assert_eq!(region, expected);
+        let old = old_lines[region.clone()].concat();
+        let new = new_lines[region].concat();
+        assert_eq!(crate::apply(&old, &clipped).unwrap(), new);
+        clipped
+    };
+
+    let clipped = check(0, 5..27);
+    assert_eq!(clipped.hunks()[0].lines()[0], Line::Delete("5\n"));
+    assert_eq!(
+        clipped.hunks()[2].lines().last(),
+        Some(&Line::Insert("new\n"))
+    );
+    assert_eq!(clipped.hunks()[0].old_range(), HunkRange::new(1, 4));
+
+    let clipped = check(2, 3..29);
+    assert_eq!(clipped.hunks()[0].old_range(), HunkRange::new(1, 6));
+    assert_eq!(clipped.hunks()[1].old_range().start(), 15);
+    assert_eq!(clipped.hunks()[2].lines().len(), 6);
+
+    // Padding is limited by the start and end of the file
+    check(10, 0..30);
+
+    let empty = crate::create_patch(&original, &original);
+    let (clipped, region) = empty.truncate_to_changed_region(&original, 3);
+    assert!(clipped.hunks().is_empty());
+    assert_eq!(region, 0..0);
+
+    // The new file may be offset from the old one by changes before the hunks
+    let original: String = (1..=120).map(|i| format!("{}\n", i)).collect();
+    let s = "--- a\n+++ b\n@@ -100,3 +90,3 @@\n 100\n-101\n+new\n 102\n";
+    let patch = Patch::from_str(s).unwrap();
+    let (clipped, region) = patch.truncate_to_changed_region(&original, 0);
+    assert_eq!(region, 100..101);
+    assert_eq!(clipped.hunks()[0].old_range(), HunkRange::new(1, 1));
+    assert_eq!(clipped.hunks()[0].new_range(), HunkRange::new(1, 1));
+    let (clipped, region) = patch.truncate_to_changed_region(&original, 2);
+    assert_eq!(region, 98..103);
+    assert_eq!(clipped.hunks()[0].old_range(), HunkRange::new(1, 5));
+    assert_eq!(clipped.hunks()[0].new_range(), HunkRange::new(1, 5));
+    let old = LineIter::new(original.as_str()).collect::<Vec<_>>()[region].concat();
+    assert_eq!(
+        crate::apply(&old, &clipped).unwrap(),
+        "99\n100\nnew\n102\n103\n"
+    );
+}
+
+#[test]
+fn to_word_diff_display() {
+    let original = "\
+header
+one two three
+four five
+removed line
+footer";
+    let modified = "\
+header
+one 2 three
+four five six
+footer
+added";
+    let patch = crate::create_patch(original, modified);
+    let expected = "\
+--- original
++++ modified
+@@ -1,5 +1,5 @@
+header
+one {-two-}{+2+} three
+four five{+ six+}
+{-removed line-}{+footer+}
+{-footer-}{+added+}
+";
+    assert_eq!(patch.to_word_diff_display(), expected);
+
+    // Mode and index lines are part of the header just like when displaying the patch
+    let mut patch = patch.with_git_index("1a2b3c4", "5d6e7f8", 0o100755);
+    patch.set_file_mode_change(0o100644, 0o100755);
+    let expected = format!(
+        "old mode 100644\nnew mode 100755\nindex 1a2b3c4..5d6e7f8 100755\n{}",
+        expected
+    );
+    assert_eq!(patch.to_word_diff_display(), expected);
+}
+
+#[test]
+fn to_slack_message() {
+    let original: String = (0..2000).map(|i| format!("{}\n", i)).collect();
+    let patch = crate::create_patch("", &original);
+    let message = patch.to_slack_message();
+
+    assert!(message.chars().count() <= 4000);
+    assert!(message.starts_with("```\n--- original\n+++ modified\n@@ -0,0 +1,2000 @@\n+0\n"));
+    // Only whole lines are included
+    let (code, rest) = message.rsplit_once("```").unwrap();
+    assert!(code.ends_with('\n'));
+    let included = code.lines().count() - 1;
+    assert_eq!(rest, format!("\n...and {} more lines", 2003 - included));
+
+    // Small patches are included in full
+    let patch = crate::create_patch("a\n", "b\n");
+    assert_eq!(
+        patch.to_slack_message(),
+        "```\n--- original\n+++ modified\n@@ -1 +1 @@\n-a\n+b\n```"
+    );
+}
+
+#[test]
+fn path_strip_level() {
+    let patch = Patch::from_str("--- /dev/null\n+++ b/dir/new file\n@@ -0,0 +1 @@\n+a\n").unwrap();
+    let f = PatchFormatter::new().with_path_strip_level(2);
+    assert_eq!(
+        f.fmt_patch(&patch).to_string(),
+        "--- /dev/null\n+++ new file\n@@ -0,0 +1 @@\n+a\n"
+    );
+    // The patch itself is unchanged
+    assert_eq!(patch.modified(), "b/dir/new file");
+}
+
+#[test]
+fn expand_context() {
+    let original = "a\nb\nc\nd\ne\n";
+    let modified = "a\nb\nx\nc\nd\ne\n";
+    let old_lines: Vec<&str> = LineIter::new(original).collect();
+    let new_lines: Vec<&str> = LineIter::new(modified).collect();
+    let patch = crate::DiffOptions::new()
+        .set_context_len(0)
+        .create_patch(original, modified);
+    let hunk = &patch.hunks()[0];
+    assert_eq!(hunk.to_string(), "@@ -2,0 +3 @@\n+x\n");
+
+    let expanded = hunk.expand_context(&old_lines, &new_lines, 1);
+    assert_eq!(expanded.to_string(), "@@ -2,2 +2,3 @@\n b\n+x\n c\n");
+
+    // Context is limited by the start and end of the texts
+    let expanded = hunk.expand_context(&old_lines, &new_lines, 5);
+    assert_eq!(expanded.old_range(), HunkRange::new(1, 5));
+    assert_eq!(expanded.new_range(), HunkRange::new(1, 6));
+    let patch = Patch::new("original", "modified", vec![expanded]);
+    assert_eq!(crate::apply(original, &patch).unwrap(), modified);
+
+    assert_eq!(&hunk.expand_context(&old_lines, &new_lines, 0), hunk);
+
+    // A hunk beyond the end of the texts gets no context instead of panicking
+    assert_eq!(
+        &hunk.expand_context(&old_lines[..1], &new_lines[..1], 2),
+        hunk
+    );
+    assert_eq!(&hunk.expand_context(&[], &[], 2), hunk);
+}
+
+#[test]
+fn to_rich_text() {
+    let patch = crate::create_patch("{a}\n", "caf\u{e9} \u{1f600}\\\n");
+
+    let rtf = patch.to_rich_text(RichTextFormat::Rtf);
+    assert!(rtf.starts_with("{\\rtf1\\ansi"));
+    assert!(rtf.ends_with('}'));
+    let body = &rtf[rtf.find("\\fs20\n").unwrap() + 6..];
+    assert_eq!(
+        body,
+        "\
+{\\b --- original}\\line
+{\\b +++ modified}\\line
+{\\cf3 @@ -1 +1 @@}\\line
+{\\cf1 -\\{a\\}}\\line
+{\\cf2 +caf\\u233? \\u-10179?\\u-8704?\\\\}\\line
+}"
+    );
+
+    let ansi = patch.to_rich_text(RichTextFormat::AnsiTerminal);
+    assert_eq!(
+        ansi,
+        PatchFormatter::new()
+            .with_color()
+            .fmt_patch(&patch)
+            .to_string()
+    );
+    assert!(ansi.contains("\u{1b}[31m-{a}\n"));
+}
+
+#[test]
+fn rename_and_mode_change() {
+    // Creating or deleting a file isn't a rename
+    let patch = Patch::from_str("--- /dev/null\n+++ b/new\n@@ -0,0 +1 @@\n+a\n").unwrap();
+    assert!(!patch.is_rename());
+    let patch = Patch::from_str("--- old\n+++ /dev/null\n@@ -1 +0,0 @@\n-a\n").unwrap();
+    assert!(!patch.is_rename());
+
+    // A rename with content changes
+    let s = "\
+diff --git a/src/old.rs b/src/new.rs
+similarity index 90%
+rename from src/old.rs
+rename to src/new.rs
+--- a/src/old.rs
++++ b/src/new.rs
+@@ -1 +1 @@
+-a
++b
+";
+    let patch = Patch::from_str(s).unwrap();
+    assert_eq!(patch.rename_from_to(), Some(("src/old.rs", "src/new.rs")));
+    assert!(!patch.is_mode_change());
+    let text = patch.to_string();
+    assert_eq!(Patch::from_str(&text).unwrap(), patch);
+
+    // Without git's headers, only a patch with git's names which keeps most of the file renames it
+    let patch = crate::create_patch("a\n", "b\n");
+    assert!(!patch.is_rename());
+    let patch = Patch::from_str("--- foo.orig\n+++ foo\n@@ -1,2 +1,2 @@\n-a\n+b\n c\n").unwrap();
+    assert!(!patch.is_rename());
+    let s = "--- a/old.rs\n+++ b/new.rs\n@@ -1,3 +1,3 @@\n-a\n+b\n c\n d\n";
+    let patch = Patch::from_str(s).unwrap();
+    assert_eq!(patch.rename_from_to(), Some(("a/old.rs", "b/new.rs")));
+    let s = "--- a/old.rs\n+++ b/new.rs\n@@ -1,2 +1,2 @@\n-a\n-b\n+c\n+d\n";
+    let patch = Patch::from_str(s).unwrap();
+    assert!(!patch.is_rename());
+
+    // A mode change along with content changes
+    let s = "\
+diff --git a/run.sh b/run.sh
+old mode 100644
+new mode 100755
+--- a/run.sh
++++ b/run.sh
+@@ -1 +1 @@
+-a
++b
+";
+    let patch = Patch::from_str(s).unwrap();
+    assert!(!patch.is_mode_change());
+    assert!(!patch.is_rename());
+    assert_eq!(patch.file_mode_change(), Some((0o100644, 0o100755)));
+
+    // Setting the modes of a patch without hunks turns it into a mode change
+    let mut patch = Patch::from_str("--- a/run.sh\n+++ b/run.sh\n").unwrap();
+    assert_eq!(patch.file_mode_change(), None);
+    assert!(!patch.is_mode_change());
+    patch.set_file_mode_change(0o100755, 0o100644);
+    assert!(patch.is_mode_change());
+    let text = patch.to_string();
+    let reparsed = Patch::from_str(&text).unwrap();
+    assert_eq!(reparsed.file_mode_change(), Some((0o100755, 0o100644)));
+    assert!(reparsed.is_mode_change());
+
+    // Lines which don't contain a mode are part of the preamble, like the commit message of an
+    // email patch
+    let s = "\
+From 1234567 Mon Sep 17 00:00:00 2001
+Subject: [PATCH] Fix the permissions check
+
+old mode handling is unchanged
+new mode 1007x5 isn't a mode
+---
+diff --git a/run.sh b/run.sh
+--- a/run.sh
++++ b/run.sh
+@@ -1 +1 @@
+-a
++b
+";
+    let patch = Patch::from_str(s).unwrap();
+    assert_eq!(patch.file_mode_change(), None);
+    assert_eq!(patch.hunks().len(), 1);
+    // A header without file names is still an error
+    assert!(Patch::from_str("diff --git a/run.sh b/run.sh\nindex 1234..5678\n").is_err());
+}
+
+#[test]
+fn sanitize_for_storage() {
+    let s = "\
+--- \"C:\\\\Users\\\\bob\\\\project\\\\file.txt\"
++++ /dev/null
+@@ -1,3 +0,0 @@
+-\u{feff}first\r
+-second\u{feff}\r
+-third
+\\ No newline at end of file
+";
+    let patch = Patch::from_str(s).unwrap();
+    let expected = "\
+--- \"project\\\\file.txt\"
++++ /dev/null
+@@ -1,3 +0,0 @@
+-first
+-second\u{feff}
+-third
+\\ No newline at end of file
+";
+    let sanitized = patch.sanitize_for_storage();
+    assert_eq!(sanitized.as_patch().to_string(), expected);
+    assert_eq!(sanitized.as_patch().original(), "project\\file.txt");
+    assert_eq!(
+        sanitized.as_patch().hunks()[0].lines(),
+        [
+            Line::Delete("first\n"),
+            Line::Delete("second\u{feff}\n"),
+            Line::Delete("third"),
+        ]
+    );
+
+    // The byte order mark is only stripped from the first line of the file
+    let patch = Patch::from_str("--- ~/a\n+++ /srv/a\n@@ -2 +2 @@\n-\u{feff}x\n+y\n").unwrap();
+    assert_eq!(
+        patch.sanitize_for_storage().as_patch().to_string(),
+        "--- a\n+++ srv/a\n@@ -2 +2 @@\n-\u{feff}x\n+y\n"
+    );
+}
+
+#[test]
+fn git_index() {
+    let s = "\
+diff --git a/src/lib.rs b/src/lib.rs
+old mode 100644
+new mode 100755
+index 83db48f..bf269f4
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -1 +1 @@
+-a
++b
+";
+    let patch = Patch::from_str(s).unwrap();
+    assert_eq!(patch.git_index(), Some(("83db48f", "bf269f4", None)));
+    // The mode and index lines are kept when displaying the patch
+    let text = patch.to_string();
+    assert_eq!(text, s[s.find("old mode").unwrap()..]);
+    let reparsed = Patch::from_str(&text).unwrap();
+    assert_eq!(reparsed.git_index(), patch.git_index());
+    assert_eq!(reparsed.file_mode_change(), Some((0o100644, 0o100755)));
+
+    // The index line is part of the file header
+    let html = patch.to_rich_text(RichTextFormat::Html);
+    assert!(html.contains("<span style=\"font-weight: bold\">+++ b/src/lib.rs</span>\n"));
+
+    let patch = patch.with_git_index("0000000", "bf269f4", 0o100644);
+    assert_eq!(
+        patch.git_index(),
+        Some(("0000000", "bf269f4", Some(0o100644)))
+    );
+    assert!(patch
+        .to_string()
+        .starts_with("old mode 100644\nnew mode 100755\nindex 0000000..bf269f4 100644\n"));
+
+    assert_eq!(Patch::from_str("--- a\n+++ b\n").unwrap().git_index(), None);
+    // Lines which don't contain object names are part of the preamble
+    let s = "\
+Subject: [PATCH] Speed up lookups
+
+index of every entry is now cached
+index 83db48f
+index 83db48f..bf269f4 10064x
+--- a
++++ b
+";
+    let patch = Patch::from_str(s).unwrap();
+    assert_eq!(patch.git_index(), None);
+    assert_eq!(patch.to_string(), "--- a\n+++ b\n");
+}
+
+#[test]
+fn reorder_hunks_by_impact() {
+    let original: String = (0..30).map(|i| format!("{}\n", i)).collect();
+    let modified = original
+        .replace("\n2\n", "\ntwo\n")
+        .replace("\n12\n13\n", "\ntwelve\nthirteen\n")
+        .replace("\n22\n", "\ntwenty-two\n");
+    let patch = crate::create_patch(&original, &modified);
+
+    let starts = |patch: &Patch<'_>| -> Vec<usize> {
+        patch
+            .hunks()
+            .iter()
+            .map(|hunk| hunk.old_range().start())
+            .collect()
+    };
+    assert_eq!(starts(&patch), [1, 10, 20]);
+    // Ties keep their order
+    assert_eq!(starts(&patch.reorder_hunks_by_impact()), [10, 1, 20]);
+    assert_eq!(
+        starts(&patch.sorted_by(|a, b| b.old_range().start().cmp(&a.old_range().start()))),
+        [20, 10, 1]
+    );
+    // Only the order of the hunks changes
+    let sorted = patch.reorder_hunks_by_impact();
+    assert_eq!(sorted.original(), patch.original());
+    assert_eq!(sorted.hunks()[0], patch.hunks()[1]);
+}
+
+#[test]
+fn to_terminal_side_by_side() {
+    let original = "\tab\nline a\nthis line is too long\nend\n";
+    let modified = "\tab\nline b\nend\nnew\n";
+    let patch = crate::create_patch(original, modified);
+
+    // Each column is 10 characters wide
+    let expected = "\
+\u{1b}[1m--- origin\u{1b}[0m   \u{1b}[1m+++ modifi\u{1b}[0m
+\u{1b}[36m@@ -1,4 +1,4 @@\u{1b}[0m
+        ab           ab
+\u{1b}[31mline \u{1b}[0m\u{1b}[7;31ma\u{1b}[0m     | \u{1b}[32mline \u{1b}[0m\u{1b}[7;32mb\u{1b}[0m
+\u{1b}[31mthis line \u{1b}[0m <
+end          end
+           > \u{1b}[32mnew\u{1b}[0m
+";
+    assert_eq!(patch.to_terminal_side_by_side(23), expected);
+
+    // Nothing but the separators fits
+    let output = patch.to_terminal_side_by_side(0);
+    assert_eq!(output.lines().nth(3), Some(" |"));
+}
+
+#[test]
+fn line_count() {
+    let original = "a\nb\nc\n";
+    let cases = [
+        ("a\nb\nc\n", 0, 0),
+        ("a\nb\nc\nd\n", 3, 4),
+        ("b\nc\n", 3, 2),
+        ("", 3, 0),
+        ("x\na\nb\nc\n", 3, 4),
+    ];
+    for (modified, original_count, modified_count) in cases {
+        let patch = crate::create_patch(original, modified);
+        assert_eq!(
+            patch.line_count_original(),
+            original_count,
+            "{:?}",
+            modified
+        );
+        assert_eq!(
+            patch.line_count_modified(),
+            modified_count,
+            "{:?}",
+            modified
+        );
+    }
+
+    // Only appending is known to reach the end of the file without context
+    let patch = crate::DiffOptions::new()
+        .set_context_len(0)
+        .create_patch("a\nb\nc\n", "a\nb\nc\nd\n");
+    assert_eq!(patch.line_count_original(), 3);
+    let patch = crate::DiffOptions::new()
+        .set_context_len(0)
+        .create_patch("a\nb\nc\n", "A\nb\nc\n");
+    assert_eq!(patch.line_count_original(), 1);
+
+    // The hunk reaching furthest is used, even if the hunks aren't in order
+    let original: String = (0..20).map(|i| format!("{}\n", i)).collect();
+    let modified = original.replace("\n2\n", "\n\n").replace("\n17\n", "\n\n");
+    let patch = crate::create_patch(&original, &modified)
+        .sorted_by(|a, b| b.old_range().start().cmp(&a.old_range().start()));
+    assert_eq!(patch.line_count_original(), 20);
+}
+
+#[test]
+fn to_diff_highlight_format() {
+    let original = "fn main() {\n    let x = 1;\n\n    completely different\n}";
+    let modified = "fn main() {\n    let y = 1;\n\n    nothing alike here\n}\n";
+    let patch =
+        crate::create_patch(original, modified).with_git_index("1a2b3c4", "5d6e7f8", 0o100644);
+
+    let expected = "\
+index 1a2b3c4..5d6e7f8 100644
+--- original
++++ modified
+@@ -1,5 +1,5 @@
+ fn main() {
+-    let \u{1b}[1;4mx\u{1b}[0m = 1;
++    let \u{1b}[1;4my\u{1b}[0m = 1;
+
+-    completely different
+-}
+\\ No newline at end of file
++    nothing alike here
++}
+";
+    let output = patch.to_diff_highlight_format();
+    assert_eq!(output, expected);
+
+    // Without the escape codes, this is the same as displaying the patch
+    let stripped = output.replace("\u{1b}[1;4m", "").replace("\u{1b}[0m", "");
+    assert_eq!(stripped, patch.to_string());
+}
+
+#[cfg(all(feature = "serde", feature = "json"))]
+#[test]
+fn serialize_matches_json_schema() {
+    use serde_json::{json, Value};
+
+    // Check `value` against the subset of JSON Schema used by `Patch::json_schema`
+    fn validate(schema: &Value, root: &Value, value: &Value, path: &str) {
+        if let Some(reference) = schema["$ref"].as_str() {
+            let name = reference.trim_start_matches("#/$defs/");
+            return validate(&root["$defs"][name], root, value, path);
+        }
+        match schema["type"].as_str() {
+            Some("object") => {
+                let object = value.as_object().unwrap_or_else(|| panic!("{}", path));
+                let properties = schema["properties"].as_object().unwrap();
+                for required in schema["required"].as_array().into_iter().flatten() {
+                    let required = required.as_str().unwrap();
+                    assert!(object.contains_key(required), "{}.{}", path, required);
+                }
+                for (key, value) in object {
+                    let property = properties
+                        .get(key)
+                        .unwrap_or_else(|| panic!("{}.{} isn't allowed", path, key));
+                    validate(property, root, value, &format!("{}.{}", path, key));
+                }
+            }
+            Some("array") => {
+                let items = value.as_array().unwrap_or_else(|| panic!("{}", path));
+                let min_items = schema["minItems"].as_u64().unwrap_or(0);
+                assert!(items.len() as u64 >= min_items, "{}", path);
+                for (i, item) in items.iter().enumerate() {
+                    validate(&schema["items"], root, item, &format!("{}[{}]", path, i));
+                }
+            }
+            Some("string") => assert!(value.is_string(), "{}", path),
+            Some("integer") => {
+                let n = value.as_i64().unwrap_or_else(|| panic!("{}", path));
+                assert!(
+                    n >= schema["minimum"].as_i64().unwrap_or(i64::MIN),
+                    "{}",
+                    path
+                );
+            }
+            _ => {
+                let allowed = schema["enum"].as_array().unwrap();
+                assert!(allowed.contains(value), "{}", path);
+            }
+        }
+    }
+
+    let schema = Patch::json_schema();
+    let original = "fn main() {\n    a();\n}\nb\nc\nd\ne\nf\ng\nh\n";
+    let modified = "fn main() {\n    A();\n}\nb\nc\nd\ne\nf\ng\nh";
+    let mut opts = DiffOptions::new();
+    opts.set_context_len(1)
+        .set_hunk_label_generator(|_, _| Some("fn main() {".to_owned()));
+    let patch = opts.create_patch(original, modified);
+    let git = Patch::from_str(
+        "diff --git a/run.sh b/run.sh\nold mode 100644\nnew mode 100755\n\
+         index 83db48f..bf269f4\n--- a/run.sh\n+++ b/run.sh\n@@ -0,0 +1 @@\n+x\n",
+    )
+    .unwrap();
+
+    for patch in [&patch, &git, &Patch::new("a", "b", Vec::new())] {
+        let value = serde_json::to_value(patch).unwrap();
+        validate(&schema, &schema, &value, "patch");
+
+        // The JSON form can be read back into a `PatchBuf`
+        let buf: PatchBuf = serde_json::from_value(value).unwrap();
+        assert_eq!(&buf.as_patch(), patch);
+        assert_eq!(
+            serde_json::to_string(&buf).unwrap(),
+            serde_json::to_string(patch).unwrap()
+        );
+    }
+
+    let value = serde_json::to_value(&patch).unwrap();
+    assert_eq!(
+        value["hunks"][0],
+        json!({
+            "old_start": 1,
+            "old_len": 3,
+            "new_start": 1,
+            "new_len": 3,
+            "function_context": "fn main() {",
+            "lines": [
+                { "kind": "context", "content": "fn main() {\n" },
+                { "kind": "delete", "content": "    a();\n" },
+                { "kind": "insert", "content": "    A();\n" },
+                { "kind": "context", "content": "}\n" },
+            ],
+        })
+    );
+    assert_eq!(value["hunks"][1]["lines"][2]["content"], "h");
+    assert_eq!(
+        serde_json::to_value(&git).unwrap()["git_index"],
+        json!({ "old": "83db48f", "new": "bf269f4" })
+    );
+
+    // Hunks whose lengths don't match their lines are rejected
+    let mut value = value;
+    value["hunks"][0]["old_len"] = json!(4);
+    assert!(serde_json::from_value::<PatchBuf>(value).is_err());
+}
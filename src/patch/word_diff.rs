@@ -0,0 +1,110 @@
+//! Render a Patch as a word diff
+
+use super::{
+    blocks::{blocks, Block},
+    format::{hunk_header, patch_header},
+    Patch,
+};
+use crate::{
+    diff::DiffOptions,
+    range::DiffRange,
+    token::{TokenClassifier, WordClassifier},
+};
+use std::{cmp, fmt::Write};
+
+pub fn to_word_diff_display(patch: &Patch<'_>) -> String {
+    let opts = DiffOptions::default();
+
+    // The header is printed the same way as by `Display`
+    let mut output = patch_header(patch);
+
+    let mut blocks = blocks(patch).into_iter().peekable();
+    for (hunk_index, hunk) in patch.hunks.iter().enumerate() {
+        output.push_str(&hunk_header(hunk));
+        output.push('\n');
+
+        while let Some(block) = blocks.next_if(|block| block.hunk_index() == hunk_index) {
+            let block = match block {
+                Block::Context(block) => {
+                    for line in block.contents() {
+                        push_line(&mut output, line);
+                    }
+                    continue;
+                }
+                Block::Edit(block) => block,
+            };
+
+            // Diff the nth deleted line against the nth inserted line
+            let deleted: Vec<_> = block.deleted().map(strip_newline).collect();
+            let inserted: Vec<_> = block.inserted().map(strip_newline).collect();
+            let pairs = cmp::min(deleted.len(), inserted.len());
+            for (old, new) in deleted.iter().zip(&inserted) {
+                let old_words = WordClassifier.split_into_tokens(old);
+                let new_words = WordClassifier.split_into_tokens(new);
+                push_word_diff(&mut output, &opts.diff_slice(&old_words, &new_words));
+                output.push('\n');
+            }
+            for old in &deleted[pairs..] {
+                push_marked(&mut output, old, '-');
+                output.push('\n');
+            }
+            for new in &inserted[pairs..] {
+                push_marked(&mut output, new, '+');
+                output.push('\n');
+            }
+        }
+    }
+
+    output
+}
+
+// Push a line, terminating it with a newline if it is the last line of a file without one
+fn push_line(output: &mut String, line: &str) {
+    output.push_str(line);
+    if !line.ends_with('\n') {
+        output.push('\n');
+    }
+}
+
+fn strip_newline(line: &str) -> &str {
+    line.strip_suffix('\n').unwrap_or(line)
+}
+
+// Push the words of a diff between two lines, showing changed words inline. Whitespace between
+// two changes is included in both of them, so that e.g. replacing a phrase shows up as a single
+// change.
+fn push_word_diff(output: &mut String, diffs: &[DiffRange<'_, '_, [&str]>]) {
+    let (mut deleted, mut inserted) = (String::new(), String::new());
+
+    for (i, diff) in diffs.iter().enumerate() {
+        match diff {
+            DiffRange::Delete(range) => deleted.extend(range.as_slice().iter().copied()),
+            DiffRange::Insert(range) => inserted.extend(range.as_slice().iter().copied()),
+            DiffRange::Equal(range, _) => {
+                let text = range.as_slice().concat();
+                let between_changes = (!deleted.is_empty() || !inserted.is_empty())
+                    && i + 1 < diffs.len()
+                    && text.chars().all(char::is_whitespace);
+                if between_changes {
+                    deleted.push_str(&text);
+                    inserted.push_str(&text);
+                } else {
+                    push_marked(output, &deleted, '-');
+                    push_marked(output, &inserted, '+');
+                    deleted.clear();
+                    inserted.clear();
+                    output.push_str(&text);
+                }
+            }
+        }
+    }
+    push_marked(output, &deleted, '-');
+    push_marked(output, &inserted, '+');
+}
+
+// Push removed or added text as `{-text-}` or `{+text+}`
+fn push_marked(output: &mut String, text: &str, marker: char) {
+    if !text.is_empty() {
+        let _ = write!(output, "{{{}{}{}}}", marker, text, marker);
+    }
+}
@@ -0,0 +1,74 @@
+//! Render a Patch as XML
+
+use super::{Hunk, Line, Patch};
+use std::fmt::Write;
+
+pub fn to_xml(patch: &Patch<'_>) -> String {
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+
+    let _ = writeln!(
+        xml,
+        "<patch original=\"{}\" modified=\"{}\">",
+        escape(&patch.original),
+        escape(&patch.modified)
+    );
+    for hunk in &patch.hunks {
+        write_hunk(&mut xml, hunk);
+    }
+    xml.push_str("</patch>\n");
+
+    xml
+}
+
+fn write_hunk(xml: &mut String, hunk: &Hunk<'_>) {
+    let _ = write!(
+        xml,
+        "  <hunk old_start=\"{}\" old_len=\"{}\" new_start=\"{}\" new_len=\"{}\"",
+        hunk.old_range.start, hunk.old_range.len, hunk.new_range.start, hunk.new_range.len
+    );
+    if let Some(ctx) = hunk.function_context() {
+        let _ = write!(xml, " function_context=\"{}\"", escape(ctx));
+    }
+    xml.push_str(">\n");
+
+    for line in &hunk.lines {
+        let (tag, line) = match line {
+            Line::Context(line) => ("context", line),
+            Line::Delete(line) => ("delete", line),
+            Line::Insert(line) => ("insert", line),
+        };
+
+        let _ = match line.strip_suffix('\n') {
+            Some(line) => writeln!(xml, "    <{0}>{1}</{0}>", tag, escape(line)),
+            None => writeln!(
+                xml,
+                "    <{0} no_newline_at_eof=\"true\">{1}</{0}>",
+                tag,
+                escape(line)
+            ),
+        };
+    }
+
+    xml.push_str("  </hunk>\n");
+}
+
+// Escapes a string so that it can be used as either text or an attribute value
+fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\t' | '\n' | '\r' => {
+                let _ = write!(escaped, "&#{};", c as u32);
+            }
+            // Other control characters can't be represented in XML 1.0, not even as references
+            c if c.is_control() && (c as u32) < 0x20 => escaped.push('\u{FFFD}'),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
@@ -286,6 +286,9 @@ impl SliceLike for str {
     }
 }
 
+// The number of items compared at a time when looking for a common prefix or suffix of two slices
+const COMPARE_CHUNK_LEN: usize = 32;
+
 impl<T> SliceLike for [T]
 where
     T: PartialEq,
@@ -303,21 +306,42 @@ where
     }
 
     fn common_prefix_len(&self, other: &[T]) -> usize {
-        for (i, (item1, item2)) in self.iter().zip(other.iter()).enumerate() {
+        let len = cmp::min(self.len(), other.len());
+        // Skip over whole chunks first, comparing a chunk of items which can be compared bytewise,
+        // like `u8` and `u64`, is a single `memcmp`
+        let mut start = 0;
+        while start + COMPARE_CHUNK_LEN <= len
+            && self[start..start + COMPARE_CHUNK_LEN] == other[start..start + COMPARE_CHUNK_LEN]
+        {
+            start += COMPARE_CHUNK_LEN;
+        }
+
+        for (i, (item1, item2)) in self[start..].iter().zip(&other[start..]).enumerate() {
             if item1 != item2 {
-                return i;
+                return start + i;
             }
         }
-        cmp::min(self.len(), other.len())
+        len
     }
 
     fn common_suffix_len(&self, other: &[T]) -> usize {
-        for (i, (item1, item2)) in self.iter().rev().zip(other.iter().rev()).enumerate() {
+        let len = cmp::min(self.len(), other.len());
+        // See `common_prefix_len`
+        let mut suffix = 0;
+        while suffix + COMPARE_CHUNK_LEN <= len
+            && self[self.len() - suffix - COMPARE_CHUNK_LEN..self.len() - suffix]
+                == other[other.len() - suffix - COMPARE_CHUNK_LEN..other.len() - suffix]
+        {
+            suffix += COMPARE_CHUNK_LEN;
+        }
+
+        let (this, other) = (&self[..self.len() - suffix], &other[..other.len() - suffix]);
+        for (i, (item1, item2)) in this.iter().rev().zip(other.iter().rev()).enumerate() {
             if item1 != item2 {
-                return i;
+                return suffix + i;
             }
         }
-        cmp::min(self.len(), other.len())
+        len
     }
 
     // returns length of overlap of prefix of `self` with suffic of `other`
@@ -416,7 +440,7 @@ where
 }
 
 impl<'a, 'b> DiffRange<'a, 'b, [u8]> {
-    pub fn to_str(&self, text1: &'a str, text2: &'b str) -> DiffRange<'a, 'b, str> {
+    pub fn to_str(self, text1: &'a str, text2: &'b str) -> DiffRange<'a, 'b, str> {
         fn boundary_down(text: &str, pos: usize) -> usize {
             let mut adjust = 0;
             while !text.is_char_boundary(pos - adjust) {
@@ -544,6 +568,35 @@ mod tests {
         assert_eq!(4, text1.common_suffix_len(text2), "Whole case");
     }
 
+    #[test]
+    fn test_common_prefix_suffix_chunks() {
+        // Lengths around multiples of the chunk size
+        let base: Vec<u8> = (0..200).map(|i| i as u8).collect();
+        for &len in &[31, 32, 33, 64, 65, 100, 200] {
+            for &pos in &[0, 1, 31, 32, 33, 63, 64, 99] {
+                if pos >= len {
+                    continue;
+                }
+                let mut changed = base[..len].to_vec();
+                changed[pos] ^= 0xff;
+                let reversed = len - pos - 1;
+
+                let text1 = Range::new(&base[..len], ..);
+                let text2 = Range::new(&changed[..], ..);
+                assert_eq!(pos, text1.common_prefix_len(text2));
+                assert_eq!(reversed, text1.common_suffix_len(text2));
+                assert_eq!(len, text1.common_prefix_len(text1));
+                assert_eq!(len, text1.common_suffix_len(text1));
+
+                // Slices of different lengths
+                let text2 = Range::new(&base[..pos], ..);
+                assert_eq!(pos, text1.common_prefix_len(text2));
+                let text2 = Range::new(&base[len - reversed..len], ..);
+                assert_eq!(reversed, text1.common_suffix_len(text2));
+            }
+        }
+    }
+
     #[test]
     fn test_common_overlap() {
         let text1 = Range::empty();
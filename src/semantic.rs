@@ -0,0 +1,256 @@
+//! Grouping the changes of a diff by the functions they affect
+
+use crate::{
+    diff::create_patch,
+    patch::{Hunk, Line, Patch},
+    utils::LineIter,
+};
+use regex::Regex;
+
+// Matches the start of a function definition in common languages like Rust, Python, JavaScript
+// and Go, capturing its name
+const FUNCTION_PATTERN: &str = r"^\s*(?:(?:pub(?:\([^)]*\))?|export|async|static|const|unsafe)\s+)*(?:fn|def|function|func)\s+([A-Za-z_][A-Za-z0-9_]*)";
+
+/// A patch whose changes are grouped by the functions they affect
+///
+/// See [`create_patch_semantic`].
+///
+/// [`create_patch_semantic`]: fn.create_patch_semantic.html
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SemanticPatch<'a> {
+    patch: Patch<'a>,
+    changes: Vec<SemanticChange<'a>>,
+}
+
+impl<'a> SemanticPatch<'a> {
+    /// Returns the underlying line based patch
+    pub fn patch(&self) -> &Patch<'a> {
+        &self.patch
+    }
+
+    /// Returns the changes in the order they first appear in the patch
+    pub fn changes(&self) -> &[SemanticChange<'a>] {
+        &self.changes
+    }
+}
+
+/// A group of hunks which make the same kind of change to a function
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SemanticChange<'a> {
+    kind: SemanticChangeKind,
+    hunks: Vec<Hunk<'a>>,
+}
+
+impl<'a> SemanticChange<'a> {
+    /// Returns what kind of change this is
+    pub fn kind(&self) -> &SemanticChangeKind {
+        &self.kind
+    }
+
+    /// Returns the hunks making up this change
+    ///
+    /// A hunk which affects several functions is part of the change to each of them.
+    pub fn hunks(&self) -> &[Hunk<'a>] {
+        &self.hunks
+    }
+}
+
+/// The kind of a [`SemanticChange`]
+///
+/// [`SemanticChange`]: struct.SemanticChange.html
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SemanticChangeKind {
+    /// A function which only exists in the modified text
+    FunctionAdded {
+        /// The name of the function
+        name: String,
+    },
+    /// A function which only exists in the original text
+    FunctionRemoved {
+        /// The name of the function
+        name: String,
+    },
+    /// A function which exists in both texts and was changed
+    FunctionModified {
+        /// The name of the function
+        name: String,
+    },
+    /// Changes outside of any function
+    Other,
+}
+
+/// Create a patch between two texts and group its changes by the functions they affect
+///
+/// Functions are found using a regular expression which matches definitions starting with
+/// `fn`, `def`, `function` or `func`, as used by Rust, Python, JavaScript and Go. A function is
+/// assumed to extend to the start of the next one, and functions are matched between the texts
+/// by name. Changed lines before the first function are grouped as
+/// [`SemanticChangeKind::Other`].
+///
+/// Requires the `regex` feature.
+///
+/// ```
+/// use diffy::{create_patch_semantic, SemanticChangeKind};
+///
+/// let original = "fn a() {\n    1\n}\n\nfn b() {\n    2\n}\n";
+/// let modified = "fn a() {\n    3\n}\n\nfn c() {\n    2\n}\n";
+///
+/// let patch = create_patch_semantic(original, modified);
+/// let kinds: Vec<_> = patch.changes().iter().map(|change| change.kind()).collect();
+/// assert_eq!(
+///     kinds,
+///     [
+///         &SemanticChangeKind::FunctionModified { name: "a".to_owned() },
+///         &SemanticChangeKind::FunctionRemoved { name: "b".to_owned() },
+///         &SemanticChangeKind::FunctionAdded { name: "c".to_owned() },
+///     ]
+/// );
+/// ```
+///
+/// [`SemanticChangeKind::Other`]: enum.SemanticChangeKind.html#variant.Other
+pub fn create_patch_semantic<'a>(original: &'a str, modified: &'a str) -> SemanticPatch<'a> {
+    let regex = Regex::new(FUNCTION_PATTERN).unwrap();
+    let old_functions = functions(&regex, original);
+    let new_functions = functions(&regex, modified);
+
+    let kind = |name: Option<&str>| match name {
+        Some(name) => {
+            let name = name.to_owned();
+            let in_old = old_functions.iter().any(|(_, n)| *n == name);
+            let in_new = new_functions.iter().any(|(_, n)| *n == name);
+            match (in_old, in_new) {
+                (true, true) => SemanticChangeKind::FunctionModified { name },
+                (true, false) => SemanticChangeKind::FunctionRemoved { name },
+                _ => SemanticChangeKind::FunctionAdded { name },
+            }
+        }
+        None => SemanticChangeKind::Other,
+    };
+
+    let patch = create_patch(original, modified);
+    let mut changes: Vec<SemanticChange<'a>> = Vec::new();
+
+    for hunk in patch.hunks() {
+        let mut old_line = hunk.old_range().position();
+        let mut new_line = hunk.new_range().position();
+        let mut kinds = Vec::new();
+
+        for line in hunk.lines() {
+            match line {
+                Line::Context(_) => {
+                    old_line += 1;
+                    new_line += 1;
+                }
+                Line::Delete(_) => {
+                    kinds.push(kind(enclosing(&old_functions, old_line)));
+                    old_line += 1;
+                }
+                Line::Insert(_) => {
+                    kinds.push(kind(enclosing(&new_functions, new_line)));
+                    new_line += 1;
+                }
+            }
+        }
+
+        for kind in kinds {
+            match changes.iter_mut().find(|change| change.kind == kind) {
+                Some(change) => {
+                    if change.hunks.last() != Some(hunk) {
+                        change.hunks.push(hunk.clone());
+                    }
+                }
+                None => changes.push(SemanticChange {
+                    kind,
+                    hunks: vec![hunk.clone()],
+                }),
+            }
+        }
+    }
+
+    SemanticPatch { patch, changes }
+}
+
+// Find the zero-based line number and name of each function defined in `text`
+fn functions<'a>(regex: &Regex, text: &'a str) -> Vec<(usize, &'a str)> {
+    LineIter::new(text)
+        .enumerate()
+        .filter_map(|(i, line)| {
+            let name = regex.captures(line)?.get(1)?.as_str();
+            Some((i, name))
+        })
+        .collect()
+}
+
+// The name of the function containing the zero-based `line`
+fn enclosing<'a>(functions: &[(usize, &'a str)], line: usize) -> Option<&'a str> {
+    functions
+        .iter()
+        .take_while(|(start, _)| *start <= line)
+        .last()
+        .map(|(_, name)| *name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn semantic_changes() {
+        let original = "\
+use std::fmt;
+
+pub fn first() {
+    one();
+}
+
+def second():
+    two()
+";
+        let modified = "\
+use std::io;
+
+pub fn first() {
+    three();
+}
+
+def second():
+    two()
+async function third() {
+}
+";
+
+        let patch = create_patch_semantic(original, modified);
+        assert_eq!(patch.patch(), &create_patch(original, modified));
+        let kinds: Vec<_> = patch.changes().iter().map(|c| c.kind().clone()).collect();
+        assert_eq!(
+            kinds,
+            [
+                SemanticChangeKind::Other,
+                SemanticChangeKind::FunctionModified {
+                    name: "first".to_owned()
+                },
+                SemanticChangeKind::FunctionAdded {
+                    name: "third".to_owned()
+                },
+            ]
+        );
+        // The single hunk is part of every change
+        assert_eq!(patch.patch().hunks().len(), 1);
+        for change in patch.changes() {
+            assert_eq!(change.hunks(), patch.patch().hunks());
+        }
+
+        // Lines appended after a function belong to it
+        let patch = create_patch_semantic("fn a() {\n}\n", "fn a() {\n}\n// end\n");
+        assert_eq!(
+            patch.changes()[0].kind(),
+            &SemanticChangeKind::FunctionModified {
+                name: "a".to_owned()
+            }
+        );
+
+        assert!(create_patch_semantic("same\n", "same\n")
+            .changes()
+            .is_empty());
+    }
+}
@@ -0,0 +1,156 @@
+//! Diffing of serializable values
+
+use crate::{
+    apply::{apply, ApplyError},
+    patch::{Patch, PatchBuf},
+};
+use serde::{de::DeserializeOwned, Serialize};
+use std::fmt;
+
+/// Create a patch between the pretty-printed JSON serializations of two values
+///
+/// Since the lines of a [`Patch`] borrow from the texts that were diffed, and those texts only
+/// exist within this function, the patch is returned as a [`PatchBuf`] which owns its lines.
+///
+/// Requires the `serde` and `json` features.
+///
+/// ```
+/// use diffy::{apply_patch_to_struct, create_patch_for_struct};
+/// use std::collections::BTreeMap;
+///
+/// let mut original = BTreeMap::new();
+/// original.insert("name", "diffy".to_owned());
+/// original.insert("version", "0.1.0".to_owned());
+///
+/// let mut modified = original.clone();
+/// modified.insert("version", "0.2.0".to_owned());
+///
+/// let patch = create_patch_for_struct(&original, &modified).unwrap();
+/// assert_eq!(
+///     patch.as_patch().to_string(),
+///     "\
+/// --- original
+/// +++ modified
+/// @@ -1,4 +1,4 @@
+///  {
+///    \"name\": \"diffy\",
+/// -  \"version\": \"0.1.0\"
+/// +  \"version\": \"0.2.0\"
+///  }
+/// \\ No newline at end of file
+/// "
+/// );
+///
+/// let patched: BTreeMap<String, String> =
+///     apply_patch_to_struct(&original, &patch.as_patch()).unwrap();
+/// assert_eq!(patched["version"], "0.2.0");
+/// ```
+///
+/// [`Patch`]: struct.Patch.html
+/// [`PatchBuf`]: struct.PatchBuf.html
+pub fn create_patch_for_struct<T>(original: &T, modified: &T) -> Result<PatchBuf, serde_json::Error>
+where
+    T: Serialize + ?Sized,
+{
+    let original = serde_json::to_string_pretty(original)?;
+    let modified = serde_json::to_string_pretty(modified)?;
+
+    Ok(PatchBuf::from(&crate::create_patch(&original, &modified)))
+}
+
+/// Apply a `Patch` to the pretty-printed JSON serialization of a value and deserialize the result
+///
+/// This is the counterpart of [`create_patch_for_struct`]. Requires the `serde` and `json` features.
+///
+/// [`create_patch_for_struct`]: fn.create_patch_for_struct.html
+pub fn apply_patch_to_struct<S, T>(original: &S, patch: &Patch<'_>) -> Result<T, StructApplyError>
+where
+    S: Serialize + ?Sized,
+    T: DeserializeOwned,
+{
+    let original = serde_json::to_string_pretty(original).map_err(StructApplyError::Json)?;
+    let modified = apply(&original, patch).map_err(StructApplyError::Apply)?;
+
+    serde_json::from_str(&modified).map_err(StructApplyError::Json)
+}
+
+/// An error returned by [`apply_patch_to_struct`]
+///
+/// [`apply_patch_to_struct`]: fn.apply_patch_to_struct.html
+#[derive(Debug)]
+pub enum StructApplyError {
+    /// Serializing the original value or deserializing the patched value failed
+    Json(serde_json::Error),
+    /// The patch could not be applied to the serialized value
+    Apply(ApplyError),
+}
+
+impl fmt::Display for StructApplyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StructApplyError::Json(e) => write!(f, "error converting value to or from JSON: {}", e),
+            StructApplyError::Apply(e) => write!(f, "patch failed to apply: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for StructApplyError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            StructApplyError::Json(e) => Some(e),
+            StructApplyError::Apply(e) => Some(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Config {
+        name: String,
+        retries: u32,
+        hosts: Vec<String>,
+    }
+
+    #[test]
+    fn struct_round_trip() {
+        let original = Config {
+            name: "service".to_owned(),
+            retries: 3,
+            hosts: vec!["a.example.com".to_owned(), "b.example.com".to_owned()],
+        };
+        let modified = Config {
+            name: "service".to_owned(),
+            retries: 5,
+            hosts: vec!["b.example.com".to_owned()],
+        };
+
+        let patch = create_patch_for_struct(&original, &modified).unwrap();
+        let patch = patch.as_patch();
+        let patched: Config = apply_patch_to_struct(&original, &patch).unwrap();
+        assert_eq!(patched, modified);
+
+        // Applying the patch to the wrong value fails
+        let err = apply_patch_to_struct::<_, Config>(&modified, &patch).unwrap_err();
+        assert!(matches!(err, StructApplyError::Apply(_)));
+
+        // Producing invalid JSON fails
+        let patch = Patch::from_str(
+            "\
+--- original
++++ modified
+@@ -1 +1 @@
+-3
+\\ No newline at end of file
++3,
+\\ No newline at end of file
+",
+        )
+        .unwrap();
+        let err = apply_patch_to_struct::<_, u32>(&3, &patch).unwrap_err();
+        assert!(matches!(err, StructApplyError::Json(_)));
+    }
+}
@@ -0,0 +1,153 @@
+//! Pluggable tokenization of the texts being diffed
+
+use crate::utils::LineIter;
+
+/// Splits a text into the tokens which are compared when performing a diff
+///
+/// The tokens become the lines of the resulting [`Patch`], so concatenating the tokens of a
+/// text must reproduce the text exactly. Only patches created with the default
+/// [`LineClassifier`] can be applied with [`apply`], which always works on lines.
+///
+/// ```
+/// use diffy::{DiffOptions, Line, TokenClassifier};
+///
+/// // Split text after each `;`
+/// struct Statements;
+///
+/// impl TokenClassifier for Statements {
+///     fn split_into_tokens<'a>(&self, text: &'a str) -> Vec<&'a str> {
+///         text.split_inclusive(';').collect()
+///     }
+/// }
+///
+/// let mut opts = DiffOptions::new();
+/// opts.set_token_classifier(Statements);
+///
+/// let patch = opts.create_patch("a = 1;b = 2;c = 3;", "a = 1;b = 4;c = 3;");
+/// assert_eq!(
+///     patch.hunks()[0].lines(),
+///     &[
+///         Line::Context("a = 1;"),
+///         Line::Delete("b = 2;"),
+///         Line::Insert("b = 4;"),
+///         Line::Context("c = 3;"),
+///     ]
+/// );
+/// ```
+///
+/// [`Patch`]: struct.Patch.html
+/// [`LineClassifier`]: struct.LineClassifier.html
+/// [`apply`]: fn.apply.html
+pub trait TokenClassifier {
+    /// Split `text` into tokens
+    fn split_into_tokens<'a>(&self, text: &'a str) -> Vec<&'a str>;
+}
+
+/// Splits a text into lines, including their trailing `\n`
+///
+/// This is the classifier used by default.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct LineClassifier;
+
+impl TokenClassifier for LineClassifier {
+    fn split_into_tokens<'a>(&self, text: &'a str) -> Vec<&'a str> {
+        LineIter::new(text).collect()
+    }
+}
+
+/// Splits a text into words and the runs of whitespace between them
+///
+/// ```
+/// use diffy::{TokenClassifier, WordClassifier};
+///
+/// assert_eq!(
+///     WordClassifier.split_into_tokens("hello  world\n"),
+///     ["hello", "  ", "world", "\n"]
+/// );
+/// ```
+#[derive(Debug, Default, Clone, Copy)]
+pub struct WordClassifier;
+
+impl TokenClassifier for WordClassifier {
+    fn split_into_tokens<'a>(&self, text: &'a str) -> Vec<&'a str> {
+        let mut tokens = Vec::new();
+        let mut rest = text;
+
+        while let Some(c) = rest.chars().next() {
+            let is_whitespace = c.is_whitespace();
+            let end = rest
+                .find(|c: char| c.is_whitespace() != is_whitespace)
+                .unwrap_or(rest.len());
+            let (token, remaining) = rest.split_at(end);
+            tokens.push(token);
+            rest = remaining;
+        }
+
+        tokens
+    }
+}
+
+/// Splits a text into individual `char`s
+#[derive(Debug, Default, Clone, Copy)]
+pub struct CharClassifier;
+
+impl TokenClassifier for CharClassifier {
+    fn split_into_tokens<'a>(&self, text: &'a str) -> Vec<&'a str> {
+        text.char_indices()
+            .map(|(i, c)| &text[i..i + c.len_utf8()])
+            .collect()
+    }
+}
+
+/// Splits a text into extended grapheme clusters
+///
+/// Requires the `unicode-segmentation` feature.
+#[cfg(feature = "unicode-segmentation")]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct GraphemeClassifier;
+
+#[cfg(feature = "unicode-segmentation")]
+impl TokenClassifier for GraphemeClassifier {
+    fn split_into_tokens<'a>(&self, text: &'a str) -> Vec<&'a str> {
+        unicode_segmentation::UnicodeSegmentation::graphemes(text, true).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokens_reproduce_text() {
+        let text = "  fn main() {\n\tprintln!(\"héllo\");\r\n}";
+        let classifiers: &[&dyn TokenClassifier] = &[
+            &LineClassifier,
+            &WordClassifier,
+            &CharClassifier,
+            #[cfg(feature = "unicode-segmentation")]
+            &GraphemeClassifier,
+        ];
+
+        for classifier in classifiers {
+            let tokens = classifier.split_into_tokens(text);
+            assert!(tokens.iter().all(|token| !token.is_empty()));
+            assert_eq!(tokens.concat(), text);
+        }
+
+        assert_eq!(
+            WordClassifier.split_into_tokens(" a b\n"),
+            [" ", "a", " ", "b", "\n"]
+        );
+        assert_eq!(CharClassifier.split_into_tokens("hé"), ["h", "é"]);
+        assert!(WordClassifier.split_into_tokens("").is_empty());
+    }
+
+    #[cfg(feature = "unicode-segmentation")]
+    #[test]
+    fn graphemes() {
+        assert_eq!(
+            GraphemeClassifier.split_into_tokens("e\u{301}a"),
+            ["e\u{301}", "a"]
+        );
+    }
+}
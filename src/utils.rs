@@ -10,7 +10,7 @@ pub struct Classifier<'a> {
 }
 
 impl<'a> Classifier<'a> {
-    fn classify(&mut self, record: &'a str) -> u64 {
+    pub fn classify(&mut self, record: &'a str) -> u64 {
         match self.unique_ids.entry(record) {
             Entry::Occupied(o) => *o.get(),
             Entry::Vacant(v) => {
@@ -23,29 +23,29 @@ impl<'a> Classifier<'a> {
 
     pub fn classify_lines(&mut self, text: &'a str) -> (Vec<&'a str>, Vec<u64>) {
         LineIter::new(text)
-            .map(|line| (line, self.classify(&line)))
+            .map(|line| (line, self.classify(line)))
             .unzip()
     }
 }
 
 /// Iterator over the lines of a string, including the `\n` character.
-pub struct LineIter<'a>(&'a str);
+pub struct LineIter<'a, T: ?Sized = str>(&'a T);
 
-impl<'a> LineIter<'a> {
-    pub fn new(text: &'a str) -> Self {
+impl<'a, T: ?Sized> LineIter<'a, T> {
+    pub fn new(text: &'a T) -> Self {
         Self(text)
     }
 }
 
-impl<'a> Iterator for LineIter<'a> {
-    type Item = &'a str;
+impl<'a, T: ?Sized + Text> Iterator for LineIter<'a, T> {
+    type Item = &'a T;
 
     fn next(&mut self) -> Option<Self::Item> {
         if self.0.is_empty() {
             return None;
         }
 
-        let end = if let Some(idx) = self.0.find('\n') {
+        let end = if let Some(idx) = self.0.find_newline() {
             idx + 1
         } else {
             self.0.len()
@@ -56,3 +56,87 @@ impl<'a> Iterator for LineIter<'a> {
         Some(line)
     }
 }
+
+/// A helper trait for processing text like `str` and `[u8]`
+/// Useful for abstracting over those types for parsing as well as breaking input into lines
+pub trait Text {
+    fn is_empty(&self) -> bool;
+    fn len(&self) -> usize;
+    fn find_newline(&self) -> Option<usize>;
+    fn split_at(&self, mid: usize) -> (&Self, &Self);
+    fn as_bytes(&self) -> &[u8];
+    fn from_str(s: &str) -> &Self;
+}
+
+impl Text for str {
+    fn is_empty(&self) -> bool {
+        self.is_empty()
+    }
+
+    fn len(&self) -> usize {
+        self.len()
+    }
+
+    fn find_newline(&self) -> Option<usize> {
+        self.find('\n')
+    }
+
+    fn split_at(&self, mid: usize) -> (&Self, &Self) {
+        self.split_at(mid)
+    }
+
+    fn as_bytes(&self) -> &[u8] {
+        self.as_bytes()
+    }
+
+    fn from_str(s: &str) -> &Self {
+        s
+    }
+}
+
+impl Text for [u8] {
+    fn is_empty(&self) -> bool {
+        self.is_empty()
+    }
+
+    fn len(&self) -> usize {
+        self.len()
+    }
+
+    fn find_newline(&self) -> Option<usize> {
+        self.iter().position(|b| *b == b'\n')
+    }
+
+    fn split_at(&self, mid: usize) -> (&Self, &Self) {
+        self.split_at(mid)
+    }
+
+    fn as_bytes(&self) -> &[u8] {
+        self
+    }
+
+    fn from_str(s: &str) -> &Self {
+        s.as_bytes()
+    }
+}
+
+/// A xorshift generator, which makes the randomized tests reproducible
+#[cfg(test)]
+pub struct Rng(pub u64);
+
+#[cfg(test)]
+impl Rng {
+    pub fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    /// A text made up of `len` lines picked at random from `lines`
+    pub fn text(&mut self, lines: &[&str], len: usize) -> String {
+        (0..len)
+            .map(|_| lines[(self.next() % lines.len() as u64) as usize])
+            .collect()
+    }
+}